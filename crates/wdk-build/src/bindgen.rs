@@ -1,11 +1,12 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 
-use std::borrow::Borrow;
+use std::{borrow::Borrow, cell::RefCell, collections::HashMap};
 
 use bindgen::{
-    callbacks::{ItemInfo, ItemKind, ParseCallbacks},
+    callbacks::{DeriveInfo, FieldInfo, ItemInfo, ItemKind, ParseCallbacks, TypeKind},
     Builder,
+    FieldVisibilityKind,
 };
 
 use crate::{Config, ConfigError};
@@ -24,6 +25,13 @@ fn wdk_default(
         c_header_files: Vec<&str>,
         config: impl Borrow<Config>,
     ) -> Result<Builder, ConfigError>;
+
+    /// Derives `wdk_macros::StructInit` for structs whose first field is their
+    /// size (ie. `Size` or `cbSize`), which generates an `init` constructor
+    /// equivalent to their `*_INIT` function in the WDK headers. The crate that
+    /// includes the generated bindings must depend on `wdk-macros`.
+    #[must_use]
+    fn derive_struct_init(self) -> Builder;
 }
 
 #[derive(Debug)]
@@ -31,6 +39,13 @@ struct WdkCallbacks {
     wdf_function_table_symbol_name: Option<String>,
 }
 
+#[derive(Debug, Default)]
+struct StructInitCallbacks {
+    // Maps the name of each struct to the name of its first field. bindgen
+    // generates the fields of a struct before its derives.
+    first_field_names: RefCell<HashMap<String, String>>,
+}
+
 impl BuilderExt for Builder {
     /// Returns a `bindgen::Builder` with the default configuration for
     /// generation of bindings to the WDK
@@ -101,6 +116,10 @@ fn wdk_default(
 
         Ok(builder)
     }
+
+    fn derive_struct_init(self) -> Self {
+        self.parse_callbacks(Box::<StructInitCallbacks>::default())
+    }
 }
 
 impl ParseCallbacks for WdkCallbacks {
@@ -129,3 +148,30 @@ fn new(config: &Config) -> Self {
         }
     }
 }
+
+impl ParseCallbacks for StructInitCallbacks {
+    fn field_visibility(&self, field_info: FieldInfo<'_>) -> Option<FieldVisibilityKind> {
+        self.first_field_names
+            .borrow_mut()
+            .entry(field_info.type_name.to_string())
+            .or_insert_with(|| field_info.field_name.to_string());
+        None
+    }
+
+    fn add_derives(&self, derive_info: &DeriveInfo<'_>) -> Vec<String> {
+        // Generate an `init` constructor for structs that start with their size, which
+        // is the equivalent of their `*_INIT` function in the WDK headers
+        if derive_info.kind == TypeKind::Struct
+            && matches!(
+                self.first_field_names
+                    .borrow()
+                    .get(derive_info.name)
+                    .map(String::as_str),
+                Some("Size" | "cbSize")
+            )
+        {
+            return vec!["::wdk_macros::StructInit".to_string()];
+        }
+        Vec::new()
+    }
+}
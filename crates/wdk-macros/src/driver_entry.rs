@@ -0,0 +1,183 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `driver_entry` attribute macro.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse2, Error, ItemFn, Result};
+
+pub fn driver_entry_impl(
+    attribute_tokens: TokenStream2,
+    item_tokens: TokenStream2,
+) -> TokenStream2 {
+    match generate_driver_entry(attribute_tokens, item_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// Generate the `DriverEntry` symbol, which converts the raw arguments passed
+/// by the system into safe types, calls the function in `item_tokens`, and
+/// converts its `NtResult` back into an `NTSTATUS`
+fn generate_driver_entry(
+    attribute_tokens: TokenStream2,
+    item_tokens: TokenStream2,
+) -> Result<TokenStream2> {
+    if !attribute_tokens.is_empty() {
+        return Err(Error::new_spanned(
+            attribute_tokens,
+            "driver_entry does not take any arguments",
+        ));
+    }
+
+    let driver_entry_fn = parse2::<ItemFn>(item_tokens)?;
+    let signature = &driver_entry_fn.sig;
+
+    if let Some(asyncness) = &signature.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "driver_entry cannot be an async function",
+        ));
+    }
+    if let Some(unsafety) = &signature.unsafety {
+        return Err(Error::new_spanned(
+            unsafety,
+            "driver_entry cannot be an unsafe function",
+        ));
+    }
+    if let Some(abi) = &signature.abi {
+        return Err(Error::new_spanned(
+            abi,
+            "driver_entry cannot specify an ABI, since the DriverEntry symbol is generated",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &signature.generics,
+            "driver_entry cannot be a generic function",
+        ));
+    }
+
+    let driver_entry_ident = &signature.ident;
+
+    // The type checker enforces the rest of the signature, since the function is
+    // passed to `wdk::wdf::_driver_entry` as a function pointer
+    Ok(quote! {
+        #driver_entry_fn
+
+        #[export_name = "DriverEntry"] // WDF expects a symbol with the name DriverEntry
+        unsafe extern "system" fn __wdk_driver_entry(
+            driver: ::wdk_sys::PDRIVER_OBJECT,
+            registry_path: ::wdk_sys::PCUNICODE_STRING,
+        ) -> ::wdk_sys::NTSTATUS {
+            // SAFETY: `driver` and `registry_path` are the arguments passed by the system
+            // to `DriverEntry`
+            unsafe { ::wdk::wdf::_driver_entry(driver, registry_path, #driver_entry_ident) }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn valid_driver_entry() {
+        let item_tokens = quote! {
+            fn driver_entry(
+                driver: &mut DriverObject,
+                registry_path: &UnicodeStr,
+            ) -> NtResult<()> {
+                Ok(())
+            }
+        };
+        let expected = quote! {
+            fn driver_entry(
+                driver: &mut DriverObject,
+                registry_path: &UnicodeStr,
+            ) -> NtResult<()> {
+                Ok(())
+            }
+
+            #[export_name = "DriverEntry"]
+            unsafe extern "system" fn __wdk_driver_entry(
+                driver: ::wdk_sys::PDRIVER_OBJECT,
+                registry_path: ::wdk_sys::PCUNICODE_STRING,
+            ) -> ::wdk_sys::NTSTATUS {
+                unsafe { ::wdk::wdf::_driver_entry(driver, registry_path, driver_entry) }
+            }
+        };
+
+        pretty_assert_eq!(
+            generate_driver_entry(TokenStream2::new(), item_tokens)
+                .unwrap()
+                .to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn attribute_arguments() {
+        let attribute_tokens = quote! { KMDF };
+        let item_tokens = quote! {
+            fn driver_entry(
+                driver: &mut DriverObject,
+                registry_path: &UnicodeStr,
+            ) -> NtResult<()> {
+                Ok(())
+            }
+        };
+        let expected = "driver_entry does not take any arguments";
+
+        pretty_assert_eq!(
+            generate_driver_entry(attribute_tokens, item_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn unsafe_fn() {
+        let item_tokens = quote! {
+            unsafe fn driver_entry(
+                driver: &mut DriverObject,
+                registry_path: &UnicodeStr,
+            ) -> NtResult<()> {
+                Ok(())
+            }
+        };
+        let expected = "driver_entry cannot be an unsafe function";
+
+        pretty_assert_eq!(
+            generate_driver_entry(TokenStream2::new(), item_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn extern_fn() {
+        let item_tokens = quote! {
+            extern "system" fn driver_entry(
+                driver: &mut DriverObject,
+                registry_path: &UnicodeStr,
+            ) -> NtResult<()> {
+                Ok(())
+            }
+        };
+        let expected =
+            "driver_entry cannot specify an ABI, since the DriverEntry symbol is generated";
+
+        pretty_assert_eq!(
+            generate_driver_entry(TokenStream2::new(), item_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+}
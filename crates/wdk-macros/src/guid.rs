@@ -0,0 +1,137 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `guid!` macro.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse2, Error, LitStr, Result};
+
+/// The fields of a `GUID`, in the order they are written
+type GuidFields = (u32, u16, u16, [u8; 8]);
+
+pub fn guid_impl(input_tokens: TokenStream2) -> TokenStream2 {
+    match generate_guid(input_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// Generate a constant `wdk::Guid` expression from the fields of the GUID in
+/// the string literal in `input_tokens`
+fn generate_guid(input_tokens: TokenStream2) -> Result<TokenStream2> {
+    let guid_literal = parse2::<LitStr>(input_tokens)?;
+    let Some((data1, data2, data3, data4)) = parse_guid(&guid_literal.value()) else {
+        return Err(Error::new_spanned(
+            &guid_literal,
+            "expected a GUID of the form \"XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX\", optionally \
+             enclosed in braces",
+        ));
+    };
+
+    Ok(quote! {
+        ::wdk::Guid::from_fields(#data1, #data2, #data3, [#(#data4),*])
+    })
+}
+
+/// Parse a GUID in its registry format (ex.
+/// `{86E0D1E0-8089-11D0-9CE4-08003E301F73}`), where the braces are optional
+/// and the hexadecimal digits are case-insensitive
+fn parse_guid(guid: &str) -> Option<GuidFields> {
+    let guid = guid
+        .strip_prefix('{')
+        .and_then(|guid| guid.strip_suffix('}'))
+        .unwrap_or(guid);
+
+    let groups = guid.split('-').collect::<Vec<_>>();
+    let [data1, data2, data3, data4_high, data4_low] = groups.as_slice() else {
+        return None;
+    };
+    let is_hex_group = |group: &str, digits| {
+        group.len() == digits && group.bytes().all(|byte| byte.is_ascii_hexdigit())
+    };
+    if !(is_hex_group(data1, 8)
+        && is_hex_group(data2, 4)
+        && is_hex_group(data3, 4)
+        && is_hex_group(data4_high, 4)
+        && is_hex_group(data4_low, 12))
+    {
+        return None;
+    }
+
+    let data4 = (u64::from_str_radix(data4_high, 16).ok()? << 48)
+        | u64::from_str_radix(data4_low, 16).ok()?;
+    Some((
+        u32::from_str_radix(data1, 16).ok()?,
+        u16::from_str_radix(data2, 16).ok()?,
+        u16::from_str_radix(data3, 16).ok()?,
+        data4.to_be_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    use super::*;
+
+    const GUID_DEVINTERFACE_COMPORT: GuidFields = (
+        0x86E0_D1E0,
+        0x8089,
+        0x11D0,
+        [0x9C, 0xE4, 0x08, 0x00, 0x3E, 0x30, 0x1F, 0x73],
+    );
+
+    #[test]
+    fn valid_guid() {
+        let input_tokens = quote! { "86E0D1E0-8089-11D0-9CE4-08003E301F73" };
+        let expected = quote! {
+            ::wdk::Guid::from_fields(
+                2262880736u32,
+                32905u16,
+                4560u16,
+                [156u8, 228u8, 8u8, 0u8, 62u8, 48u8, 31u8, 115u8]
+            )
+        };
+
+        pretty_assert_eq!(
+            generate_guid(input_tokens).unwrap().to_string(),
+            expected.to_string(),
+        );
+    }
+
+    #[test]
+    fn braces_and_lowercase() {
+        pretty_assert_eq!(
+            parse_guid("{86e0d1e0-8089-11d0-9ce4-08003e301f73}"),
+            Some(GUID_DEVINTERFACE_COMPORT),
+        );
+    }
+
+    #[test]
+    fn invalid_guids() {
+        for guid in [
+            "",
+            "86E0D1E0-8089-11D0-9CE4",
+            "86E0D1E0-8089-11D0-9CE4-08003E301F73-00",
+            "86E0D1E08089-11D0-9CE4-08003E301F73",
+            "86E0D1E0-8089-11D0-9CE4-08003E301F7",
+            "86E0D1E0-8089-11D0-9CE4-08003E301F7G",
+            "+6E0D1E0-8089-11D0-9CE4-08003E301F73",
+            "{86E0D1E0-8089-11D0-9CE4-08003E301F73",
+            "86E0D1E0-8089-11D0-9CE4-08003E301F73}",
+        ] {
+            pretty_assert_eq!(parse_guid(guid), None, "{guid} should be invalid");
+        }
+    }
+
+    #[test]
+    fn not_a_string_literal() {
+        let input_tokens = quote! { 0x86E0D1E0 };
+
+        pretty_assert_eq!(
+            generate_guid(input_tokens).unwrap_err().to_string(),
+            "expected string literal",
+        );
+    }
+}
@@ -0,0 +1,452 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `IoControl` derive macro.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt,
+    parse2,
+    Attribute,
+    Data,
+    DeriveInput,
+    Error,
+    Expr,
+    Fields,
+    Ident,
+    Result,
+    Type,
+    Variant,
+};
+
+use crate::StringExt;
+
+pub fn derive_io_control_impl(input_tokens: TokenStream2) -> TokenStream2 {
+    match generate_io_control_impl(input_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// An IOCTL declared by a variant of the enum, along with the parsed contents
+/// of its `#[ioctl(...)]` attribute
+struct IoControlVariant<'a> {
+    ident: &'a Ident,
+    code_ident: Ident,
+    input_type: Option<&'a Type>,
+    output_type: Option<Type>,
+    function: Expr,
+    method: Expr,
+    access: Expr,
+}
+
+impl IoControlVariant<'_> {
+    /// Generate the associated constant holding the I/O control code of the
+    /// variant
+    fn code_constant(&self, device_type: &Expr) -> TokenStream2 {
+        let Self {
+            ident,
+            code_ident,
+            function,
+            method,
+            access,
+            ..
+        } = self;
+        let doc = format!("I/O control code of [`Self::{}`]", ident.unraw());
+        quote! {
+            #[doc = #doc]
+            pub const #code_ident: u32 =
+                ::wdk::ioctl::ctl_code(#device_type, #function, #method, #access);
+        }
+    }
+
+    /// Generate a pattern matching the variant, ignoring its input
+    fn pattern(&self) -> TokenStream2 {
+        let ident = self.ident;
+        if self.input_type.is_some() {
+            quote! { Self::#ident(..) }
+        } else {
+            quote! { Self::#ident }
+        }
+    }
+}
+
+/// Generate an associated `u32` constant holding the I/O control code of each
+/// variant of the enum in `input_tokens`, and an `impl wdk::wdf::IoControl`
+/// that decodes a device control request into the enum
+fn generate_io_control_impl(input_tokens: TokenStream2) -> Result<TokenStream2> {
+    let derive_input = parse2::<DeriveInput>(input_tokens)?;
+
+    let Data::Enum(data_enum) = &derive_input.data else {
+        return Err(Error::new_spanned(
+            &derive_input.ident,
+            "IoControl can only be derived for enums",
+        ));
+    };
+    if !derive_input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &derive_input.generics,
+            "IoControl cannot be derived for generic enums",
+        ));
+    }
+
+    let device_type = parse_device_type(&derive_input.ident, &derive_input.attrs)?;
+    let variants = data_enum
+        .variants
+        .iter()
+        .map(parse_variant)
+        .collect::<Result<Vec<_>>>()?;
+
+    let enum_ident = &derive_input.ident;
+    let code_constants = variants
+        .iter()
+        .map(|variant| variant.code_constant(&device_type));
+
+    let uses_input_buffer = variants.iter().any(|variant| variant.input_type.is_some());
+    let input_buffer_ident = if uses_input_buffer {
+        Ident::new("input_buffer", Span::call_site())
+    } else {
+        Ident::new("_input_buffer", Span::call_site())
+    };
+    let decode_arms = variants.iter().map(|variant| {
+        let IoControlVariant {
+            ident,
+            code_ident,
+            input_type,
+            ..
+        } = variant;
+        input_type.map_or_else(
+            || quote! { Self::#code_ident => Ok(Self::#ident), },
+            |input_type| {
+                quote! {
+                    Self::#code_ident => Ok(Self::#ident(
+                        <#input_type as ::wdk::wdf::Pod>::read_from(#input_buffer_ident)?
+                    )),
+                }
+            },
+        )
+    });
+    let variant_patterns = variants
+        .iter()
+        .map(IoControlVariant::pattern)
+        .collect::<Vec<_>>();
+    let code_idents = variants.iter().map(|variant| &variant.code_ident);
+    let output_lengths = variants.iter().map(|variant| {
+        variant.output_type.as_ref().map_or_else(
+            || quote! { 0 },
+            |output_type| quote! { ::core::mem::size_of::<#output_type>() },
+        )
+    });
+
+    Ok(quote! {
+        impl #enum_ident {
+            #(#code_constants)*
+        }
+
+        impl ::wdk::wdf::IoControl for #enum_ident {
+            fn decode(
+                io_control_code: u32,
+                #input_buffer_ident: &[u8],
+            ) -> ::wdk::NtResult<Self> {
+                match io_control_code {
+                    #(#decode_arms)*
+                    _ => Err(::wdk::NtStatus::INVALID_DEVICE_REQUEST),
+                }
+            }
+
+            fn io_control_code(&self) -> u32 {
+                match self {
+                    #(#variant_patterns => Self::#code_idents,)*
+                }
+            }
+
+            fn output_length(&self) -> usize {
+                match self {
+                    #(#variant_patterns => #output_lengths,)*
+                }
+            }
+        }
+    })
+}
+
+/// Parse the `#[ioctl(device_type = ...)]` attribute of the enum
+fn parse_device_type(enum_ident: &Ident, attributes: &[Attribute]) -> Result<Expr> {
+    let mut device_type = None;
+    for attribute in attributes
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("ioctl"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("device_type") {
+                device_type = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `device_type`"))
+            }
+        })?;
+    }
+
+    device_type.ok_or_else(|| {
+        Error::new_spanned(
+            enum_ident,
+            "IoControl requires an `#[ioctl(device_type = ...)]` attribute on the enum",
+        )
+    })
+}
+
+/// Parse a variant of the enum, along with its `#[ioctl(...)]` attribute
+fn parse_variant(variant: &Variant) -> Result<IoControlVariant<'_>> {
+    let input_type = match &variant.fields {
+        Fields::Unit => None,
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(&fields.unnamed[0].ty),
+        fields => {
+            return Err(Error::new_spanned(
+                fields,
+                "IoControl variants must either be unit variants, or have a single unnamed field \
+                 holding the input of the IOCTL",
+            ));
+        }
+    };
+
+    let mut function = None;
+    let mut method = None;
+    let mut access = None;
+    let mut output_type = None;
+    for attribute in variant
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("ioctl"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("function") {
+                function = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("method") {
+                method = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("access") {
+                access = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("output") {
+                output_type = Some(meta.value()?.parse::<Type>()?);
+            } else {
+                return Err(meta.error("expected `function`, `method`, `access` or `output`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let missing_argument = |argument: &str| {
+        Error::new_spanned(
+            &variant.ident,
+            format!("IoControl variants require `{argument}` in their `#[ioctl(...)]` attribute"),
+        )
+    };
+
+    Ok(IoControlVariant {
+        ident: &variant.ident,
+        code_ident: format_ident!(
+            "{}",
+            variant
+                .ident
+                .unraw()
+                .to_string()
+                .to_snake_case()
+                .to_uppercase()
+        ),
+        input_type,
+        output_type,
+        function: function.ok_or_else(|| missing_argument("function"))?,
+        method: method.ok_or_else(|| missing_argument("method"))?,
+        access: access.ok_or_else(|| missing_argument("access"))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn valid_enum() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS, output = EchoOutput)]
+                Echo(EchoInput),
+                #[ioctl(function = 0x801, method = METHOD_NEITHER, access = FILE_WRITE_ACCESS)]
+                ResetDevice,
+            }
+        };
+        let expected = quote! {
+            impl Ioctl {
+                #[doc = "I/O control code of [`Self::Echo`]"]
+                pub const ECHO: u32 =
+                    ::wdk::ioctl::ctl_code(FILE_DEVICE_UNKNOWN, 0x800, METHOD_BUFFERED, FILE_ANY_ACCESS);
+                #[doc = "I/O control code of [`Self::ResetDevice`]"]
+                pub const RESET_DEVICE: u32 =
+                    ::wdk::ioctl::ctl_code(FILE_DEVICE_UNKNOWN, 0x801, METHOD_NEITHER, FILE_WRITE_ACCESS);
+            }
+
+            impl ::wdk::wdf::IoControl for Ioctl {
+                fn decode(
+                    io_control_code: u32,
+                    input_buffer: &[u8],
+                ) -> ::wdk::NtResult<Self> {
+                    match io_control_code {
+                        Self::ECHO => Ok(Self::Echo(
+                            <EchoInput as ::wdk::wdf::Pod>::read_from(input_buffer)?
+                        )),
+                        Self::RESET_DEVICE => Ok(Self::ResetDevice),
+                        _ => Err(::wdk::NtStatus::INVALID_DEVICE_REQUEST),
+                    }
+                }
+
+                fn io_control_code(&self) -> u32 {
+                    match self {
+                        Self::Echo(..) => Self::ECHO,
+                        Self::ResetDevice => Self::RESET_DEVICE,
+                    }
+                }
+
+                fn output_length(&self) -> usize {
+                    match self {
+                        Self::Echo(..) => ::core::mem::size_of::<EchoOutput>(),
+                        Self::ResetDevice => 0,
+                    }
+                }
+            }
+        };
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens).unwrap().to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn unused_input_buffer() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS)]
+                Reset,
+            }
+        };
+
+        assert!(generate_io_control_impl(input_tokens)
+            .unwrap()
+            .to_string()
+            .contains("_input_buffer : & [u8]"));
+    }
+
+    #[test]
+    fn struct_input() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            struct Ioctl;
+        };
+        let expected = "IoControl can only be derived for enums";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn missing_device_type() {
+        let input_tokens = quote! {
+            enum Ioctl {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS)]
+                Reset,
+            }
+        };
+        let expected = "IoControl requires an `#[ioctl(device_type = ...)]` attribute on the enum";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn missing_method() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl {
+                #[ioctl(function = 0x800, access = FILE_ANY_ACCESS)]
+                Reset,
+            }
+        };
+        let expected = "IoControl variants require `method` in their `#[ioctl(...)]` attribute";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn unknown_argument() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS, input = u32)]
+                Reset,
+            }
+        };
+        let expected = "expected `function`, `method`, `access` or `output`";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn named_fields() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS)]
+                Echo { input: EchoInput },
+            }
+        };
+        let expected = "IoControl variants must either be unit variants, or have a single unnamed \
+                        field holding the input of the IOCTL";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn generic_enum() {
+        let input_tokens = quote! {
+            #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+            enum Ioctl<T> {
+                #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS)]
+                Echo(T),
+            }
+        };
+        let expected = "IoControl cannot be derived for generic enums";
+
+        pretty_assert_eq!(
+            generate_io_control_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+}
@@ -41,6 +41,12 @@
     TypePath,
 };
 
+mod driver_entry;
+mod guid;
+mod io_control;
+mod object_context;
+mod struct_init;
+
 /// A procedural macro that allows WDF functions to be called by name.
 ///
 /// This macro is only intended to be used in the `wdk-sys` crate. Users wanting
@@ -52,6 +58,82 @@ pub fn call_unsafe_wdf_function_binding(input_tokens: TokenStream) -> TokenStrea
     call_unsafe_wdf_function_binding_impl(TokenStream2::from(input_tokens)).into()
 }
 
+/// A derive macro that declares a type as the context type of a WDF object.
+///
+/// This generates an implementation of `wdk::wdf::ObjectContext` with a unique
+/// `wdk::wdf::ObjectContextTypeInfo`, which is the equivalent of the
+/// `WDF_DECLARE_CONTEXT_TYPE` macro in the WDF headers. This macro should be
+/// consumed via its re-export in `wdk::wdf`.
+///
+/// The type must not have any generic parameters, since WDF requires every
+/// context type to have its own unique type information.
+#[proc_macro_derive(ObjectContext)]
+pub fn derive_object_context(input_tokens: TokenStream) -> TokenStream {
+    object_context::derive_object_context_impl(TokenStream2::from(input_tokens)).into()
+}
+
+/// An attribute macro that generates the `DriverEntry` symbol of a WDF driver.
+///
+/// The annotated function receives the driver object and registry path as
+/// `&mut wdk::wdf::DriverObject` and `&wdk::string::UnicodeStr`, and returns a
+/// `wdk::NtResult<()>`. The generated `DriverEntry` converts the raw arguments
+/// passed by the system into these types, and converts the returned
+/// `NtResult` into the `NTSTATUS` returned to the system. This macro should be
+/// consumed via its re-export in `wdk::wdf`.
+#[proc_macro_attribute]
+pub fn driver_entry(attribute_tokens: TokenStream, item_tokens: TokenStream) -> TokenStream {
+    driver_entry::driver_entry_impl(
+        TokenStream2::from(attribute_tokens),
+        TokenStream2::from(item_tokens),
+    )
+    .into()
+}
+
+/// A derive macro that declares an enum of the IOCTLs handled by a driver.
+///
+/// The enum must have an `#[ioctl(device_type = ...)]` attribute, and each
+/// variant an `#[ioctl(function = ..., method = ..., access = ...)]` attribute
+/// describing its I/O control code. A variant may have a single unnamed field,
+/// which holds the input of the IOCTL read from the input buffer of the
+/// request. The type written to the output buffer can be given with
+/// `output = ...`.
+///
+/// This generates an associated `u32` constant holding the I/O control code of
+/// each variant (ex. `IOCTL_ECHO` for a variant named `IoctlEcho`), and an
+/// implementation of `wdk::wdf::IoControl` for typed dispatch in
+/// `EvtIoDeviceControl`. This macro should be consumed via its re-export in
+/// `wdk::wdf`.
+#[proc_macro_derive(IoControl, attributes(ioctl))]
+pub fn derive_io_control(input_tokens: TokenStream) -> TokenStream {
+    io_control::derive_io_control_impl(TokenStream2::from(input_tokens)).into()
+}
+
+/// A derive macro that generates an `init` constructor for a WDK struct whose
+/// first field is its size (ie. `Size` or `cbSize`).
+///
+/// `init` sets that field to the size of the struct, and all other fields to
+/// their default values, so the struct must implement `Default`. This is the
+/// equivalent of the `*_INIT` functions in the WDK headers for structs that do
+/// not need any other fields initialized. This macro is added to the generated
+/// bindings by `wdk_build::BuilderExt::wdk_default`, and should not be used
+/// directly.
+#[proc_macro_derive(StructInit)]
+pub fn derive_struct_init(input_tokens: TokenStream) -> TokenStream {
+    struct_init::derive_struct_init_impl(TokenStream2::from(input_tokens)).into()
+}
+
+/// A function-like macro that declares a GUID from its string representation.
+///
+/// The string literal must be of the form
+/// `"XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX"`, optionally enclosed in braces,
+/// and is validated at compile time. This expands to a constant
+/// `wdk::Guid` expression, so it can be used to initialize `const`s and
+/// `static`s. This macro should be consumed via its re-export in `wdk`.
+#[proc_macro]
+pub fn guid(input_tokens: TokenStream) -> TokenStream {
+    guid::guid_impl(TokenStream2::from(input_tokens)).into()
+}
+
 /// A trait to provide additional functionality to the `String` type
 trait StringExt {
     /// Convert a string to `snake_case`
@@ -0,0 +1,131 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `ObjectContext` derive macro.
+
+use std::ffi::CString;
+
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{ext::IdentExt, parse2, DeriveInput, Error, Result};
+
+pub fn derive_object_context_impl(input_tokens: TokenStream2) -> TokenStream2 {
+    match generate_object_context_impl(input_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// Generate an `unsafe impl wdk::wdf::ObjectContext` for the type in
+/// `input_tokens`, with a `static` holding its `ObjectContextTypeInfo`. This
+/// mirrors the static that `WDF_DECLARE_CONTEXT_TYPE` declares in C.
+fn generate_object_context_impl(input_tokens: TokenStream2) -> Result<TokenStream2> {
+    let derive_input = parse2::<DeriveInput>(input_tokens)?;
+
+    // A `static` declared inside a generic impl is shared between all
+    // instantiations, so each context type must be non-generic to get its own
+    // unique type information
+    if !derive_input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &derive_input.generics,
+            "ObjectContext cannot be derived for generic types",
+        ));
+    }
+
+    let context_type = &derive_input.ident;
+    let context_name = Literal::c_string(
+        &CString::new(context_type.unraw().to_string())
+            .expect("identifiers should never contain a nul byte"),
+    );
+
+    // The generated impl upholds the safety requirements of `ObjectContext` since
+    // `TYPE_INFO` is constructed for `#context_type` and is only reachable
+    // through this impl
+    Ok(quote! {
+        unsafe impl ::wdk::wdf::ObjectContext for #context_type {
+            fn type_info() -> &'static ::wdk::wdf::ObjectContextTypeInfo {
+                static TYPE_INFO: ::wdk::wdf::ObjectContextTypeInfo =
+                    ::wdk::wdf::ObjectContextTypeInfo::new::<#context_type>(#context_name, &TYPE_INFO);
+                &TYPE_INFO
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn valid_struct() {
+        let input_tokens = quote! {
+            struct DeviceContext {
+                open_handle_count: u32,
+            }
+        };
+        let expected = quote! {
+            unsafe impl ::wdk::wdf::ObjectContext for DeviceContext {
+                fn type_info() -> &'static ::wdk::wdf::ObjectContextTypeInfo {
+                    static TYPE_INFO: ::wdk::wdf::ObjectContextTypeInfo =
+                        ::wdk::wdf::ObjectContextTypeInfo::new::<DeviceContext>(c"DeviceContext", &TYPE_INFO);
+                    &TYPE_INFO
+                }
+            }
+        };
+
+        pretty_assert_eq!(
+            generate_object_context_impl(input_tokens)
+                .unwrap()
+                .to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn raw_identifier() {
+        let input_tokens = quote! {
+            struct r#type;
+        };
+
+        assert!(generate_object_context_impl(input_tokens)
+            .unwrap()
+            .to_string()
+            .contains(r#"c"type""#));
+    }
+
+    #[test]
+    fn generic_struct() {
+        let input_tokens = quote! {
+            struct QueueContext<T> {
+                value: T,
+            }
+        };
+        let expected = "ObjectContext cannot be derived for generic types";
+
+        pretty_assert_eq!(
+            generate_object_context_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn struct_with_lifetime() {
+        let input_tokens = quote! {
+            struct QueueContext<'a> {
+                name: &'a str,
+            }
+        };
+        let expected = "ObjectContext cannot be derived for generic types";
+
+        pretty_assert_eq!(
+            generate_object_context_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `StructInit` derive macro.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{ext::IdentExt, parse2, Data, DeriveInput, Error, Fields, Result};
+
+/// Names of the fields that WDK structs use to store their own size
+const SIZE_FIELD_NAMES: [&str; 2] = ["Size", "cbSize"];
+
+pub fn derive_struct_init_impl(input_tokens: TokenStream2) -> TokenStream2 {
+    match generate_struct_init_impl(input_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// Generate an `init` constructor for the struct in `input_tokens`, which sets
+/// its first field to the size of the struct and all other fields to their
+/// default values. This mirrors the `*_INIT` functions in the WDK headers.
+fn generate_struct_init_impl(input_tokens: TokenStream2) -> Result<TokenStream2> {
+    let derive_input = parse2::<DeriveInput>(input_tokens)?;
+
+    // The size is computed in a `const` item, which cannot use the generic
+    // parameters of its enclosing impl
+    if !derive_input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &derive_input.generics,
+            "StructInit cannot be derived for generic types",
+        ));
+    }
+
+    let Data::Struct(data_struct) = &derive_input.data else {
+        return Err(Error::new_spanned(
+            &derive_input.ident,
+            "StructInit can only be derived for structs",
+        ));
+    };
+    let size_field = match &data_struct.fields {
+        Fields::Named(fields) => fields.named.first(),
+        Fields::Unnamed(_) | Fields::Unit => None,
+    }
+    .filter(|field| {
+        field
+            .ident
+            .as_ref()
+            .is_some_and(|ident| SIZE_FIELD_NAMES.contains(&ident.unraw().to_string().as_str()))
+    })
+    .ok_or_else(|| {
+        Error::new_spanned(
+            &derive_input.ident,
+            "StructInit can only be derived for structs whose first field is `Size` or `cbSize`",
+        )
+    })?;
+
+    let struct_ident = &derive_input.ident;
+    let size_field_ident = size_field
+        .ident
+        .as_ref()
+        .expect("named fields should always have an identifier");
+    let size_field_type = &size_field.ty;
+    let doc = format!(
+        "Returns a `{struct_ident}` with `{size_field_ident}` set to the size of the struct, and \
+         all other fields set to their default values"
+    );
+    let overflow_message =
+        format!("size of {struct_ident} should fit in its {size_field_ident} field");
+
+    Ok(quote! {
+        impl #struct_ident {
+            #[doc = #doc]
+            #[must_use]
+            pub fn init() -> Self {
+                const SIZE: #size_field_type = {
+                    let size = ::core::mem::size_of::<#struct_ident>();
+                    assert!(size <= <#size_field_type>::MAX as usize, #overflow_message);
+                    size as #size_field_type
+                };
+
+                Self {
+                    #size_field_ident: SIZE,
+                    ..::core::default::Default::default()
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn size_field() {
+        let input_tokens = quote! {
+            struct _WDF_TIMER_CONFIG {
+                pub Size: ULONG,
+                pub EvtTimerFunc: PFN_WDF_TIMER,
+            }
+        };
+        let expected = quote! {
+            impl _WDF_TIMER_CONFIG {
+                #[doc = "Returns a `_WDF_TIMER_CONFIG` with `Size` set to the size of the struct, and all other fields set to their default values"]
+                #[must_use]
+                pub fn init() -> Self {
+                    const SIZE: ULONG = {
+                        let size = ::core::mem::size_of::<_WDF_TIMER_CONFIG>();
+                        assert!(size <= <ULONG>::MAX as usize, "size of _WDF_TIMER_CONFIG should fit in its Size field");
+                        size as ULONG
+                    };
+
+                    Self {
+                        Size: SIZE,
+                        ..::core::default::Default::default()
+                    }
+                }
+            }
+        };
+
+        pretty_assert_eq!(
+            generate_struct_init_impl(input_tokens).unwrap().to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn cb_size_field() {
+        let input_tokens = quote! {
+            struct tagMONITORINFO {
+                pub cbSize: DWORD,
+                pub dwFlags: DWORD,
+            }
+        };
+
+        assert!(generate_struct_init_impl(input_tokens)
+            .unwrap()
+            .to_string()
+            .contains("cbSize : SIZE"));
+    }
+
+    #[test]
+    fn first_field_is_not_size() {
+        let input_tokens = quote! {
+            struct _UNICODE_STRING {
+                pub Length: USHORT,
+                pub MaximumLength: USHORT,
+                pub Buffer: PWCH,
+            }
+        };
+        let expected =
+            "StructInit can only be derived for structs whose first field is `Size` or `cbSize`";
+
+        pretty_assert_eq!(
+            generate_struct_init_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn union() {
+        let input_tokens = quote! {
+            union _LARGE_INTEGER {
+                pub QuadPart: LONGLONG,
+            }
+        };
+        let expected = "StructInit can only be derived for structs";
+
+        pretty_assert_eq!(
+            generate_struct_init_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn generic_struct() {
+        let input_tokens = quote! {
+            struct Config<T> {
+                pub Size: ULONG,
+                pub value: T,
+            }
+        };
+        let expected = "StructInit cannot be derived for generic types";
+
+        pretty_assert_eq!(
+            generate_struct_init_impl(input_tokens)
+                .unwrap_err()
+                .to_string(),
+            expected
+        );
+    }
+}
@@ -7,14 +7,21 @@
 //! and generates the relevant bindings to WDK APIs.
 
 use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
     env,
+    fmt::Write as _,
     io::Write,
     path::{Path, PathBuf},
+    rc::Rc,
     thread,
 };
 
 use anyhow::Context;
-use bindgen::CodegenConfig;
+use bindgen::{
+    callbacks::{DeriveInfo, ItemInfo, ItemKind, ParseCallbacks, TypeKind},
+    CodegenConfig,
+};
 use lazy_static::lazy_static;
 use tracing::{info, info_span, Span};
 use tracing_subscriber::{
@@ -40,6 +47,10 @@
 const WDFFUNCTIONS_SYMBOL_NAME_PLACEHOLDER: &str =
     "<PLACEHOLDER FOR LITERAL VALUE CONTAINING WDFFUNCTIONS SYMBOL NAME>";
 
+/// Suffix of the C functions in `src/ntddk.c` and `src/wdf.c` that wrap inline
+/// functions from the WDK headers
+const INLINE_FUNCTION_WRAPPER_SUFFIX: &str = "__extern";
+
 const WDF_FUNCTION_COUNT_DECLARATION_EXTERNAL_SYMBOL: &str = "
         // SAFETY: `crate::WdfFunctionCount` is generated as a mutable static, but is not supposed \
                                                               to be ever mutated by WDF.
@@ -139,13 +150,83 @@ macro_rules! call_unsafe_wdf_function_binding {{
 
 type GenerateFn = fn(&Path, &Config) -> Result<(), ConfigError>;
 
+/// The fields of a `GUID`, in the order they are written
+type GuidFields = (u32, u16, u16, [u8; 8]);
+
 const BINDGEN_FILE_GENERATORS_TUPLES: &[(&str, GenerateFn)] = &[
     ("constants.rs", generate_constants),
     ("types.rs", generate_types),
     ("base.rs", generate_base),
     ("wdf.rs", generate_wdf),
+    ("ntddk_inline_functions.rs", generate_ntddk_inline_functions),
+    ("wdf_inline_functions.rs", generate_wdf_inline_functions),
 ];
 
+/// Strips [`INLINE_FUNCTION_WRAPPER_SUFFIX`] from the names of the generated
+/// bindings to the inline function wrappers, so that they are called by the
+/// name of the function they wrap. bindgen adds a `link_name` attribute with
+/// the name of the wrapper.
+#[derive(Debug)]
+struct InlineFunctionWrapperCallbacks;
+
+impl ParseCallbacks for InlineFunctionWrapperCallbacks {
+    fn generated_name_override(&self, item_info: ItemInfo) -> Option<String> {
+        if let ItemInfo {
+            name: item_name,
+            kind: ItemKind::Function,
+            ..
+        } = item_info
+        {
+            return item_name
+                .strip_suffix(INLINE_FUNCTION_WRAPPER_SUFFIX)
+                .map(ToString::to_string);
+        }
+        None
+    }
+}
+
+/// Collects the names of the enums that bindgen generates as newtypes, which
+/// are the only enums that bindgen derives traits for
+#[derive(Debug, Default)]
+struct NewtypeEnumCallbacks {
+    enum_names: Rc<RefCell<BTreeSet<String>>>,
+}
+
+impl ParseCallbacks for NewtypeEnumCallbacks {
+    fn add_derives(&self, derive_info: &DeriveInfo<'_>) -> Vec<String> {
+        if derive_info.kind == TypeKind::Enum {
+            self.enum_names
+                .borrow_mut()
+                .insert(derive_info.name.to_string());
+        }
+        Vec::new()
+    }
+}
+
+/// Returns a `bindgen::Builder` with the default configuration for generation
+/// of bindings to the WDK, `init` constructors for structs that start with
+/// their size, and the enum style selected by the `newtype-enums` feature. The
+/// same enum style must be used for every generated file, since functions and
+/// types refer to each other's enums.
+fn wdk_sys_bindgen_builder(
+    c_header_files: Vec<&str>,
+    config: &Config,
+) -> Result<bindgen::Builder, ConfigError> {
+    let builder = bindgen::Builder::wdk_default(c_header_files, config)?.derive_struct_init();
+    if cfg!(feature = "newtype-enums") {
+        Ok(builder
+            .default_enum_style(bindgen::EnumVariation::NewType {
+                is_bitfield: false,
+                is_global: false,
+            })
+            // `call_unsafe_wdf_function_binding` indexes the WDF function table with the
+            // values of `_WDFFUNCENUM` as integers
+            .constified_enum_module("_WDFFUNCENUM"))
+    } else {
+        Ok(builder)
+    }
+}
+
 fn initialize_tracing() -> Result<(), ParseError> {
     let tracing_filter = EnvFilter::default()
         // Show up to INFO level by default
@@ -201,9 +282,18 @@ fn initialize_tracing() -> Result<(), ParseError> {
 }
 
 fn generate_constants(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
+    // bindgen emits `DEFINE_GUID`s as `extern` statics, which are blocklisted so
+    // that they do not conflict with the constants in guids.rs
+    let guid_names = generate_guids(out_path, config)?;
+
     info!("Generating bindings to WDK: constants.rs");
 
-    Ok(bindgen::Builder::wdk_default(vec!["src/input.h"], config)?
+    Ok(guid_names
+        .iter()
+        .fold(
+            wdk_sys_bindgen_builder(vec!["src/input.h"], config)?,
+            |builder, guid_name| builder.blocklist_item(guid_name),
+        )
         .with_codegen_config(CodegenConfig::VARS)
         .generate()
         .expect("Bindings should succeed to generate")
@@ -213,11 +303,48 @@ fn generate_constants(out_path: &Path, config: &Config) -> Result<(), ConfigErro
 fn generate_types(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
     info!("Generating bindings to WDK: types.rs");
 
-    Ok(bindgen::Builder::wdk_default(vec!["src/input.h"], config)?
+    if !cfg!(feature = "newtype-enums") {
+        return Ok(wdk_sys_bindgen_builder(vec!["src/input.h"], config)?
+            .with_codegen_config(CodegenConfig::TYPES)
+            .generate()
+            .expect("Bindings should succeed to generate")
+            .write_to_file(out_path.join("types.rs"))?);
+    }
+
+    // bindgen derives `Debug` for newtype enums, which only prints the integer, so
+    // the derive is disabled for them in favor of the implementations in enums.rs.
+    // Structs that contain them can then no longer derive `Debug`, so bindgen
+    // implements it for those structs instead.
+    let enum_names = find_newtype_enums(config)?;
+    enum_names
+        .iter()
+        .fold(
+            wdk_sys_bindgen_builder(vec!["src/input.h"], config)?.impl_debug(true),
+            |builder, enum_name| builder.no_debug(enum_name),
+        )
         .with_codegen_config(CodegenConfig::TYPES)
         .generate()
         .expect("Bindings should succeed to generate")
-        .write_to_file(out_path.join("types.rs"))?)
+        .write_to_file(out_path.join("types.rs"))?;
+
+    info!("Generating trait implementations for newtype enums: enums.rs");
+    generate_enum_impls(out_path, &enum_names)?;
+    Ok(())
+}
+
+/// Returns the names of the enums that bindgen generates as newtypes, by
+/// generating the types in `src/input.h` without writing them out
+fn find_newtype_enums(config: &Config) -> Result<BTreeSet<String>, ConfigError> {
+    let callbacks = NewtypeEnumCallbacks::default();
+    let enum_names = Rc::clone(&callbacks.enum_names);
+
+    wdk_sys_bindgen_builder(vec!["src/input.h"], config)?
+        .with_codegen_config(CodegenConfig::TYPES)
+        .parse_callbacks(Box::new(callbacks))
+        .generate()
+        .expect("Bindings should succeed to generate");
+
+    Ok(enum_names.take())
 }
 
 fn generate_base(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
@@ -227,7 +354,7 @@ fn generate_base(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
     };
     info!("Generating bindings to WDK: {outfile_name}.rs");
 
-    Ok(bindgen::Builder::wdk_default(vec!["src/input.h"], config)?
+    Ok(wdk_sys_bindgen_builder(vec!["src/input.h"], config)?
         .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
         .generate()
         .expect("Bindings should succeed to generate")
@@ -241,8 +368,10 @@ fn generate_wdf(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
         // As of NI WDK, this may generate an empty file due to no non-type and non-var
         // items in the wdf headers(i.e. functions are all inlined). This step is
         // intentionally left here in case older/newer WDKs have non-inlined functions
-        // or new WDKs may introduce non-inlined functions.
-        Ok(bindgen::Builder::wdk_default(vec!["src/input.h"], config)?
+        // or new WDKs may introduce non-inlined functions. Bindings to inlined
+        // functions are generated from the wrappers in src/wdf.c by
+        // generate_wdf_inline_functions.
+        Ok(wdk_sys_bindgen_builder(vec!["src/input.h"], config)?
             .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
             // Only generate for files that are prefixed with (case-insensitive) wdf (ie.
             // /some/path/WdfSomeHeader.h), to prevent duplication of code in ntddk.rs
@@ -259,6 +388,70 @@ fn generate_wdf(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
     }
 }
 
+fn generate_ntddk_inline_functions(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
+    if let DriverConfig::Wdm | DriverConfig::Kmdf(_) = &config.driver_config {
+        generate_inline_functions(out_path, config, "src/ntddk.c", "ntddk_inline_functions.rs")
+    } else {
+        info!(
+            "Skipping ntddk_inline_functions.rs generation since driver_config is {:#?}",
+            config.driver_config
+        );
+        Ok(())
+    }
+}
+
+fn generate_wdf_inline_functions(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
+    if let DriverConfig::Kmdf(_) | DriverConfig::Umdf(_) = &config.driver_config {
+        generate_inline_functions(out_path, config, "src/wdf.c", "wdf_inline_functions.rs")
+    } else {
+        info!(
+            "Skipping wdf_inline_functions.rs generation since driver_config is {:#?}",
+            config.driver_config
+        );
+        Ok(())
+    }
+}
+
+/// Generates bindings to the wrappers of inline functions in `c_source_file`,
+/// named after the functions they wrap. bindgen skips the inline functions
+/// themselves, since they are not exported by any library.
+fn generate_inline_functions(
+    out_path: &Path,
+    config: &Config,
+    c_source_file: &str,
+    outfile_name: &str,
+) -> Result<(), ConfigError> {
+    info!("Generating bindings to WDK: {outfile_name}");
+
+    Ok(wdk_sys_bindgen_builder(vec![c_source_file], config)?
+        .with_codegen_config(CodegenConfig::FUNCTIONS)
+        .allowlist_function(format!(".*{INLINE_FUNCTION_WRAPPER_SUFFIX}"))
+        .parse_callbacks(Box::new(InlineFunctionWrapperCallbacks))
+        .generate()
+        .expect("Bindings should succeed to generate")
+        .write_to_file(out_path.join(outfile_name))?)
+}
+
+/// Returns a `cc::Build` with the preprocessor definitions and include paths
+/// of the WDK configuration
+fn wdk_cc_builder(config: &Config) -> Result<cc::Build, ConfigError> {
+    let mut cc_builder = cc::Build::new();
+    for (key, value) in config.get_preprocessor_definitions_iter() {
+        cc_builder.define(&key, value.as_deref());
+    }
+    cc_builder.includes(config.get_include_paths()?);
+    Ok(cc_builder)
+}
+
+/// Compiles `src/{library_name}.c` into a static library linked into `wdk-sys`
+fn compile_c_library(config: &Config, library_name: &str) -> Result<(), ConfigError> {
+    info!("Compiling {library_name}.c");
+    wdk_cc_builder(config)?
+        .file(format!("src/{library_name}.c"))
+        .compile(library_name);
+    Ok(())
+}
+
 /// Generates a `wdf_function_table.rs` file in `OUT_DIR` which contains the
 /// definition of `WDF_FUNCTION_TABLE`. This is required to be generated here
 /// since the size of the table is derived from either a global symbol
@@ -371,6 +564,343 @@ fn generate_test_stubs(out_path: &Path, config: &Config) -> std::io::Result<()>
     Ok(())
 }
 
+/// Generates a `status_names.rs` file in `OUT_DIR` which contains a
+/// `STATUS_NAMES` table mapping the value of each `STATUS_*` constant in
+/// `src/constants.rs`, and in the `constants.rs` generated by bindgen, to its
+/// name. The table is sorted by value, so that names can be looked up with a
+/// binary search. This must run after `constants.rs` is generated.
+fn generate_status_names(out_path: &Path) -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=src/constants.rs");
+    let manual_constants = std::fs::read_to_string("src/constants.rs")?;
+    let generated_constants = std::fs::read_to_string(out_path.join("constants.rs"))?;
+
+    let mut status_names = manual_constants
+        .split(';')
+        .chain(generated_constants.split(';'))
+        .filter_map(parse_status_constant)
+        .collect::<Vec<_>>();
+
+    // Several constants can share a value (ex. `STATUS_SUCCESS` and
+    // `STATUS_WAIT_0`), in which case only the first name in alphabetical order is
+    // kept
+    status_names.sort_unstable();
+    status_names.dedup_by_key(|(value, _)| *value);
+
+    let status_names_file_path = out_path.join("status_names.rs");
+    let mut status_names_file = std::fs::File::create(status_names_file_path)?;
+    writeln!(
+        status_names_file,
+        "/// Names of the `STATUS_*` constants, sorted by value"
+    )?;
+    writeln!(status_names_file, "#[allow(clippy::unreadable_literal)]")?;
+    writeln!(
+        status_names_file,
+        "pub static STATUS_NAMES: &[(NTSTATUS, &::core::ffi::CStr)] = &["
+    )?;
+    for (value, name) in status_names {
+        writeln!(status_names_file, "    ({value}, c\"{name}\"),")?;
+    }
+    writeln!(status_names_file, "];")?;
+    Ok(())
+}
+
+/// Parses a declaration of a `STATUS_*` constant (ex. `pub const
+/// STATUS_SUCCESS: NTSTATUS = 0_i32`), without its trailing `;`, into its value
+/// and name. The declaration may be preceded by other items or doc comments,
+/// and wrapped over several lines by rustfmt. bindgen declares some of these
+/// constants as `u32`, so values are reinterpreted as `NTSTATUS`.
+fn parse_status_constant(declaration: &str) -> Option<(i32, String)> {
+    let (_, declaration) = declaration.rsplit_once("pub const ")?;
+    let declaration = declaration
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    let (name, declaration) = declaration.split_once(':')?;
+    // `STATUS_SEVERITY_*` are the severity values of the top two bits, rather than
+    // statuses
+    if !name.starts_with("STATUS_") || name.starts_with("STATUS_SEVERITY_") {
+        return None;
+    }
+
+    let (_, literal) = declaration.split_once('=')?;
+    let literal = literal.replace('_', "");
+    let literal = ["i32", "u32"]
+        .into_iter()
+        .find_map(|suffix| literal.strip_suffix(suffix))
+        .unwrap_or(&literal);
+    #[allow(clippy::cast_possible_truncation)]
+    let value = literal.parse::<i64>().ok()? as i32;
+    Some((value, name.to_string()))
+}
+
+/// Generates an `enums.rs` file in `OUT_DIR` which implements `Debug`,
+/// `TryFrom` of the underlying integer, and `From` into it, for each of the
+/// `enum_names` that bindgen generated as a newtype (ex. `pub struct
+/// _WDF_IO_QUEUE_DISPATCH_TYPE(pub ::core::ffi::c_int);`) in `types.rs`.
+/// `Debug` prints the name of the variant, rather than only the integer.
+fn generate_enum_impls(out_path: &Path, enum_names: &BTreeSet<String>) -> std::io::Result<()> {
+    let generated_types = std::fs::read_to_string(out_path.join("types.rs"))?;
+
+    let enum_reprs = generated_types
+        .lines()
+        .filter_map(|line| {
+            let (enum_name, repr) = line
+                .trim()
+                .strip_prefix("pub struct ")?
+                .strip_suffix(");")?
+                .split_once("(pub ")?;
+            Some((enum_names.get(enum_name)?.as_str(), repr))
+        })
+        .collect::<BTreeMap<_, _>>();
+    let enum_variants = parse_newtype_enum_variants(&generated_types, &enum_reprs);
+
+    let mut enums_file = std::fs::File::create(out_path.join("enums.rs"))?;
+    for (enum_name, repr) in enum_reprs {
+        let variant_names = enum_variants.get(enum_name).map_or(&[][..], Vec::as_slice);
+        write_enum_impls(&mut enums_file, enum_name, repr, variant_names)?;
+    }
+    Ok(())
+}
+
+/// Parses the variants of the newtype enums in `enum_reprs` out of
+/// `generated_types`. Variants are associated constants (ex. `pub const
+/// WdfIoQueueDispatchSequential: _WDF_IO_QUEUE_DISPATCH_TYPE =
+/// _WDF_IO_QUEUE_DISPATCH_TYPE(1);`), which may be wrapped over several lines.
+/// Only the first variant of each value is kept, since matching on the others
+/// would be unreachable.
+fn parse_newtype_enum_variants<'a>(
+    generated_types: &str,
+    enum_reprs: &BTreeMap<&'a str, &str>,
+) -> BTreeMap<&'a str, Vec<String>> {
+    let mut enum_variants = BTreeMap::<_, Vec<(String, String)>>::new();
+    for declaration in generated_types.split(';') {
+        let Some((_, declaration)) = declaration.rsplit_once("pub const ") else {
+            continue;
+        };
+        let declaration = declaration
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        let Some((variant_name, enum_name, value)) =
+            declaration
+                .split_once(':')
+                .and_then(|(variant_name, rest)| {
+                    let (enum_name, initializer) = rest.split_once('=')?;
+                    let value = initializer
+                        .strip_prefix(enum_name)?
+                        .strip_prefix('(')?
+                        .strip_suffix(')')?;
+                    Some((variant_name, enum_name, value))
+                })
+        else {
+            continue;
+        };
+        let Some((&enum_name, _)) = enum_reprs.get_key_value(enum_name) else {
+            continue;
+        };
+
+        let variants = enum_variants.entry(enum_name).or_default();
+        let value = value.trim_end_matches(',').replace('_', "");
+        if !variants
+            .iter()
+            .any(|(_, variant_value)| *variant_value == value)
+        {
+            variants.push((variant_name.to_string(), value));
+        }
+    }
+
+    enum_variants
+        .into_iter()
+        .map(|(enum_name, variants)| {
+            (
+                enum_name,
+                variants
+                    .into_iter()
+                    .map(|(variant_name, _)| variant_name)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Writes the `Debug`, `TryFrom` and `From` implementations of a newtype enum
+fn write_enum_impls(
+    enums_file: &mut std::fs::File,
+    enum_name: &str,
+    repr: &str,
+    variant_names: &[String],
+) -> std::io::Result<()> {
+    let mut debug_arms = String::new();
+    for variant_name in variant_names {
+        writeln!(
+            debug_arms,
+            r#"            Self::{variant_name} => f.write_str("{variant_name}"),"#
+        )
+        .expect("writing to a String should not fail");
+    }
+    let try_from_body = if variant_names.is_empty() {
+        "Err(value)".to_string()
+    } else {
+        let variant_patterns = variant_names
+            .iter()
+            .map(|variant_name| format!("Self::{variant_name}"))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        format!(
+            "match Self(value) {{
+            {variant_patterns} => Ok(Self(value)),
+            _ => Err(value),
+        }}"
+        )
+    };
+
+    writeln!(
+        enums_file,
+        r#"
+impl ::core::fmt::Debug for {enum_name} {{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+        match *self {{
+{debug_arms}            Self(value) => f.debug_tuple("{enum_name}").field(&value).finish(),
+        }}
+    }}
+}}
+
+impl ::core::convert::TryFrom<{repr}> for {enum_name} {{
+    type Error = {repr};
+
+    /// Returns the variant with the given value, or the value itself if it does
+    /// not match any variant
+    fn try_from(value: {repr}) -> ::core::result::Result<Self, Self::Error> {{
+        {try_from_body}
+    }}
+}}
+
+impl ::core::convert::From<{enum_name}> for {repr} {{
+    fn from(value: {enum_name}) -> Self {{
+        value.0
+    }}
+}}"#
+    )
+}
+
+/// Generates a `guids.rs` file in `OUT_DIR` which contains a `GUID` constant
+/// for each `DEFINE_GUID` in `src/guids.c`. bindgen cannot evaluate these,
+/// since `DEFINE_GUID` only declares an `extern` symbol unless `INITGUID` is
+/// defined. Instead, `src/guids.c` is expanded by the C preprocessor with
+/// `INITGUID` defined, and the resulting definitions are parsed out of its
+/// output. Returns the names of the generated constants.
+fn generate_guids(out_path: &Path, config: &Config) -> Result<Vec<String>, ConfigError> {
+    info!("Generating GUID constants: guids.rs");
+    println!("cargo:rerun-if-changed=src/guids.c");
+
+    let expanded_source = wdk_cc_builder(config)?
+        .define("INITGUID", None)
+        .file("src/guids.c")
+        .expand();
+    let expanded_source = String::from_utf8_lossy(&expanded_source);
+
+    // Preprocessor directives (ie. `#line`) are removed so that they do not end up
+    // in the middle of a declaration
+    let declarations = expanded_source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // A header can be included by several others without include guards, in which
+    // case only the first definition of each GUID is kept
+    let mut guids = BTreeMap::new();
+    for (name, fields) in declarations.split(';').filter_map(parse_guid_definition) {
+        guids.entry(name).or_insert(fields);
+    }
+
+    let guids_file_path = out_path.join("guids.rs");
+    let mut guids_file = std::fs::File::create(guids_file_path)?;
+    for (name, (data1, data2, data3, data4)) in &guids {
+        let data4 = data4
+            .iter()
+            .map(|byte| format!("{byte:#04x}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            guids_file,
+            "pub const {name}: GUID = GUID {{ Data1: {data1:#010x}, Data2: {data2:#06x}, Data3: \
+             {data3:#06x}, Data4: [{data4}] }};"
+        )?;
+    }
+    Ok(guids.into_keys().map(str::to_string).collect())
+}
+
+/// Parses a declaration produced by `DEFINE_GUID` when `INITGUID` is defined
+/// (ex. `extern const GUID __declspec(selectany) GUID_BUS_TYPE_PCI = {
+/// 0xc8ebdfb0L, 0xb510, 0x11d0, { 0x80, 0xe5, 0x00, 0xa0, 0xc9, 0x25, 0x42,
+/// 0xe3 } }`) into the name and fields of the GUID
+fn parse_guid_definition(declaration: &str) -> Option<(&str, GuidFields)> {
+    let (declarator, initializer) = declaration.split_once('=')?;
+
+    let declarator = declarator
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|token| !token.is_empty() && !matches!(*token, "__declspec" | "selectany"))
+        .collect::<Vec<_>>();
+    let [.., guid_type, name] = declarator.as_slice() else {
+        return None;
+    };
+    if *guid_type != "GUID" || !declarator.contains(&"const") {
+        return None;
+    }
+
+    // The initializer must have exactly the shape `{ l, w1, w2, { b1, ..., b8 } }`
+    let shape = initializer
+        .chars()
+        .filter(|c| matches!(c, '{' | '}' | ','))
+        .collect::<String>();
+    if shape != "{,,,{,,,,,,,}}" {
+        return None;
+    }
+    let values = initializer
+        .split(['{', '}', ','])
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(parse_integer_literal)
+        .collect::<Option<Vec<_>>>()?;
+    let [data1, data2, data3, data4 @ ..] = values.as_slice() else {
+        return None;
+    };
+
+    let mut data4_bytes = [0; 8];
+    for (byte, value) in data4_bytes.iter_mut().zip(data4) {
+        *byte = u8::try_from(*value).ok()?;
+    }
+    Some((
+        name,
+        (
+            u32::try_from(*data1).ok()?,
+            u16::try_from(*data2).ok()?,
+            u16::try_from(*data3).ok()?,
+            data4_bytes,
+        ),
+    ))
+}
+
+/// Parses a C integer literal (ex. `0xc8ebdfb0L`), which may be cast to its
+/// field's type (ex. `(USHORT)0x11d0`)
+fn parse_integer_literal(literal: &str) -> Option<u64> {
+    let literal = literal
+        .rsplit_once(')')
+        .map_or(literal, |(_, literal)| literal)
+        .trim()
+        .trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = match literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        Some(hex_digits) => (hex_digits, 16),
+        None if literal.len() > 1 && literal.starts_with('0') => (&literal[1..], 8),
+        None => (literal, 10),
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
 fn main() -> anyhow::Result<()> {
     initialize_tracing()?;
 
@@ -401,28 +931,32 @@ fn main() -> anyhow::Result<()> {
                 }
             });
 
+            if let DriverConfig::Wdm | DriverConfig::Kmdf(_) = config.driver_config {
+                let current_span = Span::current();
+                // Compile a c library to expose symbols for the wrappers of inline functions
+                thread_join_handles.push(
+                    thread::Builder::new()
+                        .name("ntddk.c cc compilation".to_string())
+                        .spawn_scoped(thread_scope, || {
+                            // Parent span must be manually set since spans do not persist across thread boundaries: https://github.com/tokio-rs/tracing/issues/1391
+                            info_span!(parent: current_span, "cc")
+                                .in_scope(|| compile_c_library(&config, "ntddk"))
+                        })
+                        .expect("Scoped Thread should spawn successfully"),
+                );
+            }
+
             if let DriverConfig::Kmdf(_) | DriverConfig::Umdf(_) = config.driver_config {
                 let current_span = Span::current();
                 // Compile a c library to expose symbols that are not exposed because of
-                // __declspec(selectany)
+                // __declspec(selectany), and symbols for the wrappers of inline functions
                 thread_join_handles.push(
                     thread::Builder::new()
                         .name("wdf.c cc compilation".to_string())
                         .spawn_scoped(thread_scope, || {
                             // Parent span must be manually set since spans do not persist across thread boundaries: https://github.com/tokio-rs/tracing/issues/1391
-                            info_span!(parent: current_span, "cc").in_scope(|| {
-                                info!("Compiling wdf.c");
-                                let mut cc_builder = cc::Build::new();
-                                for (key, value) in config.get_preprocessor_definitions_iter() {
-                                    cc_builder.define(&key, value.as_deref());
-                                }
-
-                                cc_builder
-                                    .includes(config.get_include_paths()?)
-                                    .file("src/wdf.c")
-                                    .compile("wdf");
-                                Ok::<(), ConfigError>(())
-                            })
+                            info_span!(parent: current_span, "cc")
+                                .in_scope(|| compile_c_library(&config, "wdf"))
                         })
                         .expect("Scoped Thread should spawn successfully"),
                 );
@@ -452,6 +986,13 @@ fn main() -> anyhow::Result<()> {
                         format!(r#""{thread_name}" thread failed to exit successfully"#)
                     })?;
             }
+
+            // `STATUS_NAMES` is generated from `constants.rs`, so it must wait for the
+            // bindgen threads to complete
+            info_span!("status_names.rs generation").in_scope(|| {
+                generate_status_names(&out_path)?;
+                Ok::<(), std::io::Error>(())
+            })?;
             Ok::<(), anyhow::Error>(())
         })?;
 
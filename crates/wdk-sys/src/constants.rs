@@ -28,6 +28,7 @@ mod bindings {
     use crate::types::*;
 
     include!(concat!(env!("OUT_DIR"), "/constants.rs"));
+    include!(concat!(env!("OUT_DIR"), "/guids.rs"));
 }
 
 #[cfg(any(driver_model__driver_type = "KMDF", driver_model__driver_type = "UMDF"))]
@@ -64,6 +65,8 @@ mod kernel_mode {
     pub const POOL_FLAG_OPTIONAL_END: POOL_FLAGS = 0x8000_0000_0000_0000;
 }
 
+include!(concat!(env!("OUT_DIR"), "/status_names.rs"));
+
 // Due to linker issues with windows_sys, these definitions are manually
 // imported definitions from windows_sys::Win32::Foundation:
 pub const STATUS_ABANDONED: NTSTATUS = 128_i32;
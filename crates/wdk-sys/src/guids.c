@@ -0,0 +1,19 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+// The build script expands this file with INITGUID defined, so that every
+// DEFINE_GUID in these headers is evaluated into guids.rs
+
+#include "input.h"
+
+// Device interface classes (ex. GUID_DEVINTERFACE_COMPORT), which are declared
+// by the same headers in user mode and kernel mode
+#include <ntddser.h>
+#include <ntddstor.h>
+#include <usbiodef.h>
+
+#if !defined(UMDF_VERSION_MAJOR)
+
+#include <wdmguid.h>
+
+#endif // !defined(UMDF_VERSION_MAJOR)
@@ -0,0 +1,90 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "input.h"
+
+// The kernel headers only provide the following functions as FORCEINLINE
+// definitions, so they have no symbol to link against. Each one is wrapped in
+// an exported function with an __extern suffix, which the build script
+// generates bindings to under the name of the wrapped function.
+
+VOID InitializeListHead__extern(_Out_ PLIST_ENTRY ListHead)
+{
+  InitializeListHead(ListHead);
+}
+
+BOOLEAN IsListEmpty__extern(_In_ const LIST_ENTRY *ListHead)
+{
+  return IsListEmpty(ListHead);
+}
+
+VOID InsertHeadList__extern(
+    _Inout_ PLIST_ENTRY ListHead,
+    _Out_ PLIST_ENTRY Entry)
+{
+  InsertHeadList(ListHead, Entry);
+}
+
+VOID InsertTailList__extern(
+    _Inout_ PLIST_ENTRY ListHead,
+    _Out_ PLIST_ENTRY Entry)
+{
+  InsertTailList(ListHead, Entry);
+}
+
+BOOLEAN RemoveEntryList__extern(_In_ PLIST_ENTRY Entry)
+{
+  return RemoveEntryList(Entry);
+}
+
+PLIST_ENTRY RemoveHeadList__extern(_Inout_ PLIST_ENTRY ListHead)
+{
+  return RemoveHeadList(ListHead);
+}
+
+PLIST_ENTRY RemoveTailList__extern(_Inout_ PLIST_ENTRY ListHead)
+{
+  return RemoveTailList(ListHead);
+}
+
+PIO_STACK_LOCATION IoGetCurrentIrpStackLocation__extern(_In_ PIRP Irp)
+{
+  return IoGetCurrentIrpStackLocation(Irp);
+}
+
+PIO_STACK_LOCATION IoGetNextIrpStackLocation__extern(_In_ PIRP Irp)
+{
+  return IoGetNextIrpStackLocation(Irp);
+}
+
+VOID IoSkipCurrentIrpStackLocation__extern(_Inout_ PIRP Irp)
+{
+  IoSkipCurrentIrpStackLocation(Irp);
+}
+
+VOID IoCopyCurrentIrpStackLocationToNext__extern(_Inout_ PIRP Irp)
+{
+  IoCopyCurrentIrpStackLocationToNext(Irp);
+}
+
+VOID IoMarkIrpPending__extern(_Inout_ PIRP Irp)
+{
+  IoMarkIrpPending(Irp);
+}
+
+VOID IoSetCompletionRoutine__extern(
+    _In_ PIRP Irp,
+    _In_opt_ PIO_COMPLETION_ROUTINE CompletionRoutine,
+    _In_opt_ PVOID Context,
+    _In_ BOOLEAN InvokeOnSuccess,
+    _In_ BOOLEAN InvokeOnError,
+    _In_ BOOLEAN InvokeOnCancel)
+{
+  IoSetCompletionRoutine(
+      Irp,
+      CompletionRoutine,
+      Context,
+      InvokeOnSuccess,
+      InvokeOnError,
+      InvokeOnCancel);
+}
@@ -17,4 +17,5 @@ mod bindings {
     use crate::types::*;
 
     include!(concat!(env!("OUT_DIR"), "/ntddk.rs"));
+    include!(concat!(env!("OUT_DIR"), "/ntddk_inline_functions.rs"));
 }
@@ -10,6 +10,8 @@
 #[allow(unsafe_op_in_unsafe_fn)]
 #[allow(clippy::cast_lossless)]
 #[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_possible_wrap)]
+#[allow(clippy::cast_sign_loss)]
 #[allow(clippy::cognitive_complexity)]
 #[allow(clippy::default_trait_access)]
 #[rustversion::attr(
@@ -59,4 +61,6 @@
 #[allow(clippy::use_self)]
 mod bindings {
     include!(concat!(env!("OUT_DIR"), "/types.rs"));
+    #[cfg(feature = "newtype-enums")]
+    include!(concat!(env!("OUT_DIR"), "/enums.rs"));
 }
@@ -2,3 +2,61 @@
    License: MIT OR Apache-2.0 */
 
 #include "input.h"
+
+// The WDF headers only provide the following functions as FORCEINLINE
+// definitions, so they have no symbol to link against. Each one is wrapped in
+// an exported function with an __extern suffix, which the build script
+// generates bindings to under the name of the wrapped function.
+
+VOID WDF_DRIVER_CONFIG_INIT__extern(
+    _Out_ PWDF_DRIVER_CONFIG Config,
+    _In_opt_ PFN_WDF_DRIVER_DEVICE_ADD EvtDriverDeviceAdd)
+{
+  WDF_DRIVER_CONFIG_INIT(Config, EvtDriverDeviceAdd);
+}
+
+VOID WDF_OBJECT_ATTRIBUTES_INIT__extern(_Out_ PWDF_OBJECT_ATTRIBUTES Attributes)
+{
+  WDF_OBJECT_ATTRIBUTES_INIT(Attributes);
+}
+
+VOID WDF_PNPPOWER_EVENT_CALLBACKS_INIT__extern(
+    _Out_ PWDF_PNPPOWER_EVENT_CALLBACKS Callbacks)
+{
+  WDF_PNPPOWER_EVENT_CALLBACKS_INIT(Callbacks);
+}
+
+VOID WDF_IO_QUEUE_CONFIG_INIT__extern(
+    _Out_ PWDF_IO_QUEUE_CONFIG Config,
+    _In_ WDF_IO_QUEUE_DISPATCH_TYPE DispatchType)
+{
+  WDF_IO_QUEUE_CONFIG_INIT(Config, DispatchType);
+}
+
+VOID WDF_IO_QUEUE_CONFIG_INIT_DEFAULT_QUEUE__extern(
+    _Out_ PWDF_IO_QUEUE_CONFIG Config,
+    _In_ WDF_IO_QUEUE_DISPATCH_TYPE DispatchType)
+{
+  WDF_IO_QUEUE_CONFIG_INIT_DEFAULT_QUEUE(Config, DispatchType);
+}
+
+VOID WDF_REQUEST_SEND_OPTIONS_INIT__extern(
+    _Out_ PWDF_REQUEST_SEND_OPTIONS Options,
+    _In_ ULONG Flags)
+{
+  WDF_REQUEST_SEND_OPTIONS_INIT(Options, Flags);
+}
+
+VOID WDF_TIMER_CONFIG_INIT__extern(
+    _Out_ PWDF_TIMER_CONFIG Config,
+    _In_ PFN_WDF_TIMER EvtTimerFunc)
+{
+  WDF_TIMER_CONFIG_INIT(Config, EvtTimerFunc);
+}
+
+VOID WDF_WORKITEM_CONFIG_INIT__extern(
+    _Out_ PWDF_WORKITEM_CONFIG Config,
+    _In_ PFN_WDF_WORKITEM EvtWorkItemFunc)
+{
+  WDF_WORKITEM_CONFIG_INIT(Config, EvtWorkItemFunc);
+}
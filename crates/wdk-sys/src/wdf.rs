@@ -18,6 +18,7 @@ mod bindings {
     use crate::types::*;
 
     include!(concat!(env!("OUT_DIR"), "/wdf.rs"));
+    include!(concat!(env!("OUT_DIR"), "/wdf_inline_functions.rs"));
 }
 
 include!(concat!(env!("OUT_DIR"), "/wdf_function_table.rs"));
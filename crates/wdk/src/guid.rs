@@ -0,0 +1,194 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use wdk_sys::GUID;
+
+/// A globally unique identifier ([`GUID`]), such as the class of a device
+/// interface.
+///
+/// GUIDs are formatted and parsed in their registry format (ex.
+/// `{CDC35B6E-0BE4-4936-BF5F-5537380A7C1A}`). GUIDs defined by a driver, such
+/// as the class of its own device interface, should be declared with
+/// [`guid!`](crate::guid), which validates the string at compile time. GUIDs
+/// declared with `DEFINE_GUID` in the WDK headers (ex.
+/// `GUID_DEVICE_INTERFACE_ARRIVAL` or `GUID_DEVINTERFACE_COMPORT`) are
+/// available as [`GUID`] constants in [`wdk_sys`], and convert with
+/// [`Guid::from`].
+///
+/// # Examples
+///
+/// ```rust
+/// use wdk::{guid, Guid};
+///
+/// const GUID_DEVINTERFACE_ECHO: Guid = guid!("CDC35B6E-0BE4-4936-BF5F-5537380A7C1A");
+///
+/// assert_eq!(
+///     "{cdc35b6e-0be4-4936-bf5f-5537380a7c1a}".parse(),
+///     Ok(GUID_DEVINTERFACE_ECHO)
+/// );
+/// assert_eq!(
+///     GUID_DEVINTERFACE_ECHO.to_string(),
+///     "{CDC35B6E-0BE4-4936-BF5F-5537380A7C1A}"
+/// );
+/// ```
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Guid(GUID);
+
+/// The error returned when parsing a [`Guid`] from a string that is not in the
+/// registry format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseGuidError(());
+
+impl Guid {
+    /// Create a GUID from its fields, in the order they are written
+    #[must_use]
+    pub const fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self(GUID {
+            Data1: data1,
+            Data2: data2,
+            Data3: data3,
+            Data4: data4,
+        })
+    }
+
+    /// Create a GUID from a 128-bit integer, whose most significant bits hold
+    /// the first field. This matches the order in which a GUID is written.
+    #[must_use]
+    // clippy::cast_possible_truncation: each field is deliberately truncated out of
+    // `value`
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn from_u128(value: u128) -> Self {
+        Self::from_fields(
+            (value >> 96) as u32,
+            (value >> 80) as u16,
+            (value >> 64) as u16,
+            (value as u64).to_be_bytes(),
+        )
+    }
+
+    /// Returns the fields of the GUID, in the order they are written
+    #[must_use]
+    pub const fn as_fields(&self) -> (u32, u16, u16, &[u8; 8]) {
+        (self.0.Data1, self.0.Data2, self.0.Data3, &self.0.Data4)
+    }
+
+    /// Returns the GUID as a 128-bit integer, whose most significant bits hold
+    /// the first field
+    #[must_use]
+    pub fn to_u128(&self) -> u128 {
+        let (data1, data2, data3, data4) = self.as_fields();
+        u128::from(data1) << 96
+            | u128::from(data2) << 80
+            | u128::from(data3) << 64
+            | u128::from(u64::from_be_bytes(*data4))
+    }
+
+    /// Returns a raw pointer to the underlying [`GUID`]
+    #[must_use]
+    pub const fn as_raw(&self) -> *const GUID {
+        core::ptr::from_ref(&self.0)
+    }
+}
+
+impl From<GUID> for Guid {
+    fn from(guid: GUID) -> Self {
+        Self(guid)
+    }
+}
+
+impl From<Guid> for GUID {
+    fn from(guid: Guid) -> Self {
+        guid.0
+    }
+}
+
+impl PartialEq for Guid {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_u128() == other.to_u128()
+    }
+}
+
+impl Eq for Guid {}
+
+impl PartialOrd for Guid {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Guid {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_u128().cmp(&other.to_u128())
+    }
+}
+
+impl Hash for Guid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_u128().hash(state);
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (data1, data2, data3, data4) = self.as_fields();
+        write!(
+            f,
+            "{{{data1:08X}-{data2:04X}-{data3:04X}-{:02X}{:02X}-",
+            data4[0], data4[1]
+        )?;
+        for byte in &data4[2..] {
+            write!(f, "{byte:02X}")?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for Guid {
+    type Err = ParseGuidError;
+
+    /// Parse a GUID in its registry format, where the braces are optional and
+    /// the hexadecimal digits are case-insensitive
+    fn from_str(guid: &str) -> Result<Self, Self::Err> {
+        let guid = guid
+            .strip_prefix('{')
+            .and_then(|guid| guid.strip_suffix('}'))
+            .unwrap_or(guid);
+
+        let mut groups = guid.split('-');
+        let mut value = 0;
+        for digits in [8, 4, 4, 4, 12] {
+            let group = groups
+                .next()
+                .filter(|group| {
+                    group.len() == digits && group.bytes().all(|byte| byte.is_ascii_hexdigit())
+                })
+                .ok_or(ParseGuidError(()))?;
+            let group = u64::from_str_radix(group, 16).map_err(|_| ParseGuidError(()))?;
+            value = value << (digits * 4) | u128::from(group);
+        }
+        if groups.next().is_some() {
+            return Err(ParseGuidError(()));
+        }
+        Ok(Self::from_u128(value))
+    }
+}
+
+impl fmt::Display for ParseGuidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid GUID syntax")
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Construction and decoding of I/O control codes (IOCTLs).
+//!
+//! An I/O control code packs a device type, function code, transfer method
+//! and required access into a single `u32`:
+//!
+//! ```text
+//! | 31 ... 16   | 15 ... 14 | 13 ... 2 | 1 ... 0 |
+//! | device type |  access   | function | method  |
+//! ```
+
+/// Build an I/O control code. This is the equivalent of the `CTL_CODE` macro
+/// in the WDK headers.
+///
+/// `method` is one of the `METHOD_*` constants and `access` is one of the
+/// `FILE_*_ACCESS` constants in `wdk-sys`. Function codes below `0x800` are
+/// reserved for Microsoft.
+///
+/// # Examples
+///
+/// ```rust
+/// use wdk::ioctl::ctl_code;
+/// use wdk_sys::{FILE_ANY_ACCESS, FILE_DEVICE_UNKNOWN, METHOD_BUFFERED};
+///
+/// const IOCTL_ECHO: u32 = ctl_code(FILE_DEVICE_UNKNOWN, 0x800, METHOD_BUFFERED, FILE_ANY_ACCESS);
+/// ```
+///
+/// # Panics
+///
+/// Panics if any of the arguments does not fit in its field of the I/O control
+/// code. When evaluated in a constant, this results in a compilation error.
+#[must_use]
+pub const fn ctl_code(device_type: u32, function: u32, method: u32, access: u32) -> u32 {
+    assert!(device_type <= 0xFFFF, "device type must fit in 16 bits");
+    assert!(function <= 0xFFF, "function code must fit in 12 bits");
+    assert!(method <= 0b11, "transfer method must fit in 2 bits");
+    assert!(access <= 0b11, "required access must fit in 2 bits");
+
+    (device_type << 16) | (access << 14) | (function << 2) | method
+}
+
+/// A decoded I/O control code, splitting it back into the fields that
+/// [`ctl_code`] packs together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IoControlCode(u32);
+
+impl IoControlCode {
+    /// Wrap a raw I/O control code
+    #[must_use]
+    pub const fn from_raw(io_control_code: u32) -> Self {
+        Self(io_control_code)
+    }
+
+    /// Returns the raw I/O control code
+    #[must_use]
+    pub const fn as_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the device type (ex. `FILE_DEVICE_UNKNOWN`). This is the
+    /// equivalent of the `DEVICE_TYPE_FROM_CTL_CODE` macro in the WDK headers.
+    #[must_use]
+    pub const fn device_type(self) -> u32 {
+        self.0 >> 16
+    }
+
+    /// Returns the required access (ex. `FILE_ANY_ACCESS`)
+    #[must_use]
+    pub const fn access(self) -> u32 {
+        (self.0 >> 14) & 0b11
+    }
+
+    /// Returns the function code
+    #[must_use]
+    pub const fn function(self) -> u32 {
+        (self.0 >> 2) & 0xFFF
+    }
+
+    /// Returns the transfer method (ex. `METHOD_BUFFERED`). This is the
+    /// equivalent of the `METHOD_FROM_CTL_CODE` macro in the WDK headers.
+    #[must_use]
+    pub const fn method(self) -> u32 {
+        self.0 & 0b11
+    }
+
+    /// Returns whether the function code is in the range reserved for
+    /// vendors, rather than Microsoft
+    #[must_use]
+    pub const fn is_custom(self) -> bool {
+        self.function() >= 0x800
+    }
+}
+
+impl From<u32> for IoControlCode {
+    fn from(io_control_code: u32) -> Self {
+        Self::from_raw(io_control_code)
+    }
+}
+
+impl From<IoControlCode> for u32 {
+    fn from(io_control_code: IoControlCode) -> Self {
+        io_control_code.as_raw()
+    }
+}
@@ -7,6 +7,22 @@
 
 #![no_std]
 
+// Allows macros from `wdk-macros` that generate `::wdk` paths to be used inside
+// this crate
+extern crate self as wdk;
+
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+pub use guid::{Guid, ParseGuidError};
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+pub use nt_status::{status_name, NtResult, NtStatus, Severity};
 #[cfg(any(
     all(
         feature = "alloc",
@@ -20,6 +36,12 @@
     driver_model__driver_type = "KMDF",
     driver_model__driver_type = "UMDF"
 ))]
+pub use wdk_sys::__proc_macros::guid;
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
 pub use wdk_sys::NT_SUCCESS as nt_success;
 #[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
 pub use wdk_sys::PAGED_CODE as paged_code;
@@ -33,6 +55,34 @@
 ))]
 mod print;
 
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+mod guid;
+
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+mod nt_status;
+
+pub mod ioctl;
+
+#[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+pub mod registry;
+
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+pub mod string;
+
+pub mod time;
+
 #[cfg(any(driver_model__driver_type = "KMDF", driver_model__driver_type = "UMDF"))]
 pub mod wdf;
 
@@ -64,3 +114,45 @@ pub fn dbg_break() {
         panic!("dbg_break function called from unsupported architecture");
     }
 }
+
+/// Assert that the current thread is running at `PASSIVE_LEVEL`, in debug
+/// builds of kernel-mode drivers. User-mode drivers always run at
+/// `PASSIVE_LEVEL`.
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+#[cfg_attr(
+    driver_model__driver_type = "UMDF",
+    allow(clippy::missing_const_for_fn)
+)]
+#[track_caller]
+pub(crate) fn debug_assert_passive_level() {
+    #[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+    {
+        let irql;
+        // SAFETY: `KeGetCurrentIrql` can be called at any IRQL
+        unsafe {
+            irql = wdk_sys::ntddk::KeGetCurrentIrql();
+        }
+        debug_assert_eq!(
+            u32::from(irql),
+            wdk_sys::PASSIVE_LEVEL,
+            "this function must be called at PASSIVE_LEVEL"
+        );
+    }
+}
+
+/// Returns the integer value of a variant of a WDK enum. This works whether
+/// `wdk-sys` generates its enums as integer constants or as newtypes (ie. with
+/// its `newtype-enums` feature enabled), since the feature may be enabled by
+/// any crate in the dependency graph.
+#[cfg(any(
+    driver_model__driver_type = "WDM",
+    driver_model__driver_type = "KMDF",
+    driver_model__driver_type = "UMDF"
+))]
+pub(crate) fn enum_value(value: impl Into<core::ffi::c_int>) -> core::ffi::c_int {
+    value.into()
+}
@@ -0,0 +1,210 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{ffi::CStr, fmt};
+
+use wdk_sys::{
+    NTSTATUS,
+    NT_ERROR,
+    NT_INFORMATION,
+    NT_SUCCESS,
+    NT_WARNING,
+    STATUS_CANCELLED,
+    STATUS_INVALID_DEVICE_REQUEST,
+    STATUS_NAMES,
+};
+
+/// Returns the symbolic name of `nt_status` (ex. `STATUS_INVALID_PARAMETER`).
+///
+/// Returns `None` if `nt_status` is not the value of one of the `STATUS_*`
+/// constants in `wdk-sys`. If several constants share the value, the first
+/// name in alphabetical order is returned. The names are looked up in a sorted
+/// table generated when `wdk-sys` is built, so this does not allocate.
+///
+/// # Examples
+///
+/// ```rust
+/// use wdk::status_name;
+/// use wdk_sys::STATUS_INVALID_PARAMETER;
+///
+/// assert_eq!(
+///     status_name(STATUS_INVALID_PARAMETER),
+///     Some(c"STATUS_INVALID_PARAMETER")
+/// );
+/// ```
+#[must_use]
+pub fn status_name(nt_status: NTSTATUS) -> Option<&'static CStr> {
+    STATUS_NAMES
+        .binary_search_by_key(&nt_status, |&(value, _)| value)
+        .ok()
+        .map(|index| STATUS_NAMES[index].1)
+}
+
+/// A `Result` whose error variant is an [`NtStatus`]
+pub type NtResult<T> = Result<T, NtStatus>;
+
+/// An [`NTSTATUS`] value.
+///
+/// This is returned as the error of the fallible functions in this crate, and
+/// can be converted from and into an [`NTSTATUS`] so that `?` works with
+/// functions returning either. Its [`Debug`] and [`Display`](fmt::Display)
+/// implementations print the symbolic name of the status (ex.
+/// `STATUS_INVALID_PARAMETER`) when it is known.
+///
+/// See the [NTSTATUS reference](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-erref/87fba13e-bf06-450e-83b1-9241dc81e781) for details on the layout of the value.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct NtStatus(NTSTATUS);
+
+/// The severity of an [`NtStatus`], stored in its two most significant bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// 0 - 0x3FFFFFFF
+    Success,
+    /// 0x40000000 - 0x7FFFFFFF
+    Informational,
+    /// 0x80000000 - 0xBFFFFFFF
+    Warning,
+    /// 0xC0000000 - 0xFFFFFFFF
+    Error,
+}
+
+impl NtStatus {
+    /// `STATUS_CANCELLED`, the status of requests that were cancelled before
+    /// the driver finished processing them
+    pub const CANCELLED: Self = Self(STATUS_CANCELLED);
+
+    /// `STATUS_INVALID_DEVICE_REQUEST`, the status of requests that the driver
+    /// does not handle (ex. an unknown IOCTL)
+    pub const INVALID_DEVICE_REQUEST: Self = Self(STATUS_INVALID_DEVICE_REQUEST);
+
+    /// Wrap a raw [`NTSTATUS`]
+    #[must_use]
+    pub const fn from_raw(nt_status: NTSTATUS) -> Self {
+        Self(nt_status)
+    }
+
+    /// Returns the raw [`NTSTATUS`]
+    #[must_use]
+    pub const fn as_raw(self) -> NTSTATUS {
+        self.0
+    }
+
+    /// Returns `Ok(())` if the status is a success or informational type, and
+    /// `Err(self)` otherwise
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the status is a warning or error
+    /// type.
+    pub const fn ok(self) -> NtResult<()> {
+        if self.is_success() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Returns whether the status is a success or informational type. See
+    /// [`NT_SUCCESS`].
+    #[must_use]
+    pub const fn is_success(self) -> bool {
+        NT_SUCCESS(self.0)
+    }
+
+    /// Returns whether the status is an informational type. See
+    /// [`NT_INFORMATION`].
+    #[must_use]
+    pub const fn is_information(self) -> bool {
+        NT_INFORMATION(self.0)
+    }
+
+    /// Returns whether the status is a warning type. See [`NT_WARNING`].
+    #[must_use]
+    pub const fn is_warning(self) -> bool {
+        NT_WARNING(self.0)
+    }
+
+    /// Returns whether the status is an error type. See [`NT_ERROR`].
+    #[must_use]
+    pub const fn is_error(self) -> bool {
+        NT_ERROR(self.0)
+    }
+
+    /// Returns the severity of the status
+    #[must_use]
+    pub const fn severity(self) -> Severity {
+        if self.is_error() {
+            Severity::Error
+        } else if self.is_warning() {
+            Severity::Warning
+        } else if self.is_information() {
+            Severity::Informational
+        } else {
+            Severity::Success
+        }
+    }
+
+    /// Returns whether the status is customer-defined, rather than defined by
+    /// Microsoft
+    #[must_use]
+    pub const fn is_customer(self) -> bool {
+        self.bits() & (1 << 29) != 0
+    }
+
+    /// Returns the facility of the status (ex. `FACILITY_USB_ERROR_CODE`)
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn facility(self) -> u16 {
+        ((self.bits() >> 16) & 0xFFF) as u16
+    }
+
+    /// Returns the code of the status within its facility
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn code(self) -> u16 {
+        self.bits() as u16
+    }
+
+    /// Returns the symbolic name of the status (ex.
+    /// `STATUS_INVALID_PARAMETER`), if it is known. See [`status_name`].
+    #[must_use]
+    pub fn name(self) -> Option<&'static CStr> {
+        status_name(self.0)
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    const fn bits(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+impl From<NTSTATUS> for NtStatus {
+    fn from(nt_status: NTSTATUS) -> Self {
+        Self::from_raw(nt_status)
+    }
+}
+
+impl From<NtStatus> for NTSTATUS {
+    fn from(nt_status: NtStatus) -> Self {
+        nt_status.as_raw()
+    }
+}
+
+impl fmt::Debug for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name().and_then(|name| name.to_str().ok()) {
+            Some(name) => write!(f, "NtStatus({name})"),
+            None => write!(f, "NtStatus({:#010x})", self.bits()),
+        }
+    }
+}
+
+impl fmt::Display for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name().and_then(|name| name.to_str().ok()) {
+            Some(name) => write!(f, "{name} ({:#010x})", self.bits()),
+            None => write!(f, "{:#010x}", self.bits()),
+        }
+    }
+}
@@ -0,0 +1,601 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+extern crate alloc;
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use serde::{
+    de::{
+        self,
+        value::{SeqDeserializer, StrDeserializer},
+        DeserializeOwned,
+        DeserializeSeed,
+        IntoDeserializer,
+        MapAccess,
+        Visitor,
+    },
+    forward_to_deserialize_any,
+};
+#[cfg(driver_model__driver_type = "KMDF")]
+use wdk_sys::KEY_READ;
+use wdk_sys::{
+    STATUS_INVALID_PARAMETER,
+    STATUS_OBJECT_NAME_NOT_FOUND,
+    STATUS_OBJECT_TYPE_MISMATCH,
+};
+
+use super::{RegistryKey, RegistryValue};
+#[cfg(driver_model__driver_type = "KMDF")]
+use crate::wdf::Driver;
+use crate::{string::UnicodeString, NtResult, NtStatus};
+
+/// A set of named registry values that a struct can be deserialized from, such
+/// as a [`RegistryKey`].
+///
+/// Implementing this trait for an in-memory set of values allows the
+/// deserialization of settings to be tested without a registry.
+pub trait ValueSource {
+    /// Read the value `name`, returning [`None`] if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value exists but cannot be
+    /// read
+    fn value(&self, name: &str) -> NtResult<Option<RegistryValue>>;
+}
+
+/// Deserializer of structs from the values of a [`ValueSource`].
+///
+/// Each field of the struct is read from the value of the same name, so
+/// fields are usually renamed with `#[serde(rename_all = "PascalCase")]` to
+/// follow registry naming conventions. Fields whose value does not exist take
+/// their `#[serde(default)]`, or are [`None`] if they are [`Option`]s.
+///
+/// Registry types are mapped to Rust types as follows:
+/// * `REG_DWORD` and `REG_QWORD` to integers, interpreting them as two's
+///   complement for signed fields, and to `bool`s, which are `true` if they are
+///   not 0
+/// * `REG_SZ` and `REG_EXPAND_SZ` to `String`s and unit enum variants
+/// * `REG_MULTI_SZ` to sequences of `String`s
+/// * `REG_BINARY` to sequences of `u8`s, or byte buffers
+pub struct Deserializer<'a, S: ?Sized> {
+    source: &'a S,
+}
+
+/// Errors that can occur when deserializing a struct from registry values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Error returned when the registry cannot be read
+    NtStatus(NtStatus),
+    /// Error returned when a value does not exist, and its field has no
+    /// default
+    MissingValue(&'static str),
+    /// Error returned when a value cannot be deserialized as the type of its
+    /// field, such as when it has a different registry type
+    InvalidValue {
+        /// Name of the value
+        name: &'static str,
+        /// Message describing the error
+        message: String,
+    },
+    /// Catch-all error returned when a more specific error is not available
+    Custom(String),
+}
+
+/// Deserializer of a single [`RegistryValue`]
+struct ValueDeserializer {
+    value: RegistryValue,
+}
+
+/// Access to the fields of a struct, in the order they are declared, skipping
+/// the fields whose value does not exist
+struct Fields<'a, S: ?Sized> {
+    source: &'a S,
+    names: core::slice::Iter<'static, &'static str>,
+    value: Option<(&'static str, RegistryValue)>,
+}
+
+/// Deserialize a `T` from the values of `source`, such as a [`RegistryKey`].
+/// See [`Deserializer`] for how values are mapped to fields.
+///
+/// # Errors
+///
+/// This function will return an error if a value cannot be read, a value
+/// without a default does not exist, or a value cannot be deserialized as the
+/// type of its field.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use serde::Deserialize;
+/// use wdk::{
+///     registry::{self, RegistryKey},
+///     unicode_string,
+///     NtResult,
+/// };
+/// use wdk_sys::KEY_READ;
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "PascalCase")]
+/// struct Settings {
+///     #[serde(default)]
+///     verbose_output: bool,
+///     device_names: Vec<String>,
+/// }
+///
+/// fn load_settings() -> NtResult<Settings> {
+///     let key = RegistryKey::open(
+///         unicode_string!("\\Registry\\Machine\\Software\\Sample"),
+///         KEY_READ,
+///     )?;
+///     Ok(registry::from_key(&key)?)
+/// }
+/// ```
+pub fn from_key<T, S>(source: &S) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+    S: ValueSource + ?Sized,
+{
+    T::deserialize(Deserializer::new(source))
+}
+
+/// Deserialize a `T` from the values of the `Parameters` key of `driver`,
+/// opened via [`RegistryKey::open_driver_parameters`]. See [`Deserializer`]
+/// for how values are mapped to fields.
+///
+/// # Errors
+///
+/// This function will return an error if the key cannot be opened, a value
+/// cannot be read, a value without a default does not exist, or a value cannot
+/// be deserialized as the type of its field.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use serde::Deserialize;
+/// use wdk::{registry, wdf::Driver, NtResult};
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "PascalCase")]
+/// struct Settings {
+///     #[serde(default = "default_timeout_ms")]
+///     timeout_ms: u32,
+///     #[serde(default)]
+///     verbose_output: bool,
+///     serial_number: Option<String>,
+/// }
+///
+/// const fn default_timeout_ms() -> u32 {
+///     1000
+/// }
+///
+/// fn load_settings(driver: &Driver) -> NtResult<Settings> {
+///     Ok(registry::from_driver_parameters(driver)?)
+/// }
+/// ```
+#[cfg(driver_model__driver_type = "KMDF")]
+pub fn from_driver_parameters<T: DeserializeOwned>(driver: &Driver) -> Result<T, Error> {
+    let parameters = RegistryKey::open_driver_parameters(driver, KEY_READ)?;
+    from_key(&parameters)
+}
+
+impl ValueSource for RegistryKey {
+    fn value(&self, name: &str) -> NtResult<Option<RegistryValue>> {
+        let name = UnicodeString::try_from(name)?;
+        match self.get_value(&name) {
+            Ok(value) => Ok(Some(value)),
+            Err(nt_status) if nt_status.as_raw() == STATUS_OBJECT_NAME_NOT_FOUND => Ok(None),
+            Err(nt_status) => Err(nt_status),
+        }
+    }
+}
+
+impl<'a, S: ValueSource + ?Sized> Deserializer<'a, S> {
+    /// Create a deserializer of the values of `source`
+    pub const fn new(source: &'a S) -> Self {
+        Self { source }
+    }
+}
+
+impl<'de, S: ValueSource + ?Sized> de::Deserializer<'de> for Deserializer<'_, S> {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple tuple_struct map enum
+        identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::custom(
+            "only structs can be deserialized from registry values",
+        ))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields {
+            source: self.source,
+            names: fields.iter(),
+            value: None,
+        })
+    }
+}
+
+impl<'de, S: ValueSource + ?Sized> MapAccess<'de> for Fields<'_, S> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        for &name in self.names.by_ref() {
+            if let Some(value) = self.source.value(name)? {
+                self.value = Some((name, value));
+                let key: StrDeserializer<'_, Error> = name.into_deserializer();
+                return seed.deserialize(key).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let Some((name, value)) = self.value.take() else {
+            return Err(de::Error::custom("value requested before its name"));
+        };
+        seed.deserialize(ValueDeserializer { value })
+            .map_err(|error| match error {
+                Error::Custom(message) => Error::InvalidValue { name, message },
+                error => error,
+            })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            RegistryValue::Dword(value) => visitor.visit_u32(value),
+            RegistryValue::Qword(value) => visitor.visit_u64(value),
+            RegistryValue::String(string) | RegistryValue::ExpandString(string) => {
+                visitor.visit_string(to_string(&string)?)
+            }
+            RegistryValue::MultiString(strings) => {
+                let strings = strings
+                    .iter()
+                    .map(to_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                SeqDeserializer::new(strings.into_iter()).deserialize_any(visitor)
+            }
+            RegistryValue::Binary(data) => visitor.visit_byte_buf(data),
+            RegistryValue::Other { value_type, .. } => Err(de::Error::custom(format_args!(
+                "registry type {value_type} is not supported"
+            ))),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            RegistryValue::Dword(value) => visitor.visit_bool(value != 0),
+            RegistryValue::Qword(value) => visitor.visit_bool(value != 0),
+            value => Self { value }.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_signed(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_signed(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_signed(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_signed(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Values that do not exist are skipped by `Fields`, so this value exists
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            RegistryValue::Binary(data) => {
+                SeqDeserializer::new(data.into_iter()).deserialize_any(visitor)
+            }
+            value => Self { value }.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            RegistryValue::String(string) | RegistryValue::ExpandString(string) => {
+                let variant: de::value::StringDeserializer<Error> =
+                    to_string(&string)?.into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            value => Self { value }.deserialize_any(visitor),
+        }
+    }
+}
+
+impl ValueDeserializer {
+    /// Deserialize a signed integer, which is stored in the registry as its
+    /// two's complement
+    // clippy::cast_possible_wrap: the wrap is the intended reinterpretation
+    #[allow(clippy::cast_possible_wrap)]
+    fn deserialize_signed<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            RegistryValue::Dword(value) => visitor.visit_i32(value as i32),
+            RegistryValue::Qword(value) => visitor.visit_i64(value as i64),
+            value => de::Deserializer::deserialize_any(Self { value }, visitor),
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::MissingValue(field)
+    }
+}
+
+impl de::StdError for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NtStatus(nt_status) => write!(f, "failed to read the registry: {nt_status}"),
+            Self::MissingValue(name) => write!(f, "registry value {name} does not exist"),
+            Self::InvalidValue { name, message } => {
+                write!(f, "invalid registry value {name}: {message}")
+            }
+            Self::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<NtStatus> for Error {
+    fn from(nt_status: NtStatus) -> Self {
+        Self::NtStatus(nt_status)
+    }
+}
+
+impl From<Error> for NtStatus {
+    /// Returns the [`NtStatus`] of a failure to read the registry, or the
+    /// closest status for other errors: `STATUS_OBJECT_NAME_NOT_FOUND` for
+    /// missing values, `STATUS_OBJECT_TYPE_MISMATCH` for invalid values, and
+    /// `STATUS_INVALID_PARAMETER` otherwise.
+    fn from(error: Error) -> Self {
+        match error {
+            Error::NtStatus(nt_status) => nt_status,
+            Error::MissingValue(_) => STATUS_OBJECT_NAME_NOT_FOUND.into(),
+            Error::InvalidValue { .. } => STATUS_OBJECT_TYPE_MISMATCH.into(),
+            Error::Custom(_) => STATUS_INVALID_PARAMETER.into(),
+        }
+    }
+}
+
+fn to_string(unicode_string: &UnicodeString) -> Result<String, Error> {
+    String::try_from(unicode_string).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use serde::Deserialize;
+    use wdk_sys::{
+        REG_BINARY,
+        REG_DWORD,
+        REG_EXPAND_SZ,
+        REG_MULTI_SZ,
+        REG_NONE,
+        REG_QWORD,
+        REG_SZ,
+    };
+
+    use super::*;
+
+    /// A key whose values are stored in memory, as the raw data of each
+    /// registry type
+    struct InMemoryKey(Vec<(&'static str, u32, Vec<u8>)>);
+
+    impl ValueSource for InMemoryKey {
+        fn value(&self, name: &str) -> NtResult<Option<RegistryValue>> {
+            self.0
+                .iter()
+                .find(|(value_name, ..)| value_name.eq_ignore_ascii_case(name))
+                .map(|(_, value_type, data)| RegistryValue::from_raw(*value_type, data))
+                .transpose()
+        }
+    }
+
+    /// Encode `strings` as nul-terminated UTF-16 strings
+    fn utf16(strings: &[&str]) -> Vec<u8> {
+        strings
+            .iter()
+            .flat_map(|string| string.encode_utf16().chain([0]))
+            .flat_map(u16::to_ne_bytes)
+            .collect()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    enum Mode {
+        Fast,
+        Safe,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "PascalCase")]
+    struct Settings {
+        timeout_ms: u32,
+        #[serde(default)]
+        verbose_output: bool,
+        max_transfer_length: u64,
+        offset: i32,
+        #[serde(default = "default_level")]
+        level: u8,
+        name: String,
+        device_names: Vec<String>,
+        key: Vec<u8>,
+        serial_number: Option<String>,
+        mode: Mode,
+    }
+
+    const fn default_level() -> u8 {
+        3
+    }
+
+    fn settings_key() -> InMemoryKey {
+        InMemoryKey(vec![
+            ("TimeoutMs", REG_DWORD, 500u32.to_ne_bytes().to_vec()),
+            ("VerboseOutput", REG_DWORD, 1u32.to_ne_bytes().to_vec()),
+            (
+                "MaxTransferLength",
+                REG_QWORD,
+                (1u64 << 40).to_ne_bytes().to_vec(),
+            ),
+            ("Offset", REG_DWORD, (-5i32).to_ne_bytes().to_vec()),
+            ("Level", REG_DWORD, 7u32.to_ne_bytes().to_vec()),
+            ("Name", REG_SZ, utf16(&["sample"])),
+            ("DeviceNames", REG_MULTI_SZ, utf16(&["first", "second", ""])),
+            ("Key", REG_BINARY, vec![1, 2, 3]),
+            ("SerialNumber", REG_EXPAND_SZ, utf16(&["%Serial%"])),
+            ("Mode", REG_SZ, utf16(&["Safe"])),
+        ])
+    }
+
+    #[test]
+    fn test_all_types() {
+        let settings: Settings = from_key(&settings_key()).unwrap();
+
+        assert_eq!(
+            settings,
+            Settings {
+                timeout_ms: 500,
+                verbose_output: true,
+                max_transfer_length: 1 << 40,
+                offset: -5,
+                level: 7,
+                name: "sample".into(),
+                device_names: vec!["first".into(), "second".into()],
+                key: vec![1, 2, 3],
+                serial_number: Some("%Serial%".into()),
+                mode: Mode::Safe,
+            }
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mut key = settings_key();
+        key.0
+            .retain(|(name, ..)| !["VerboseOutput", "Level", "SerialNumber"].contains(name));
+
+        let settings: Settings = from_key(&key).unwrap();
+
+        assert!(!settings.verbose_output);
+        assert_eq!(settings.level, default_level());
+        assert_eq!(settings.serial_number, None);
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let mut key = settings_key();
+        key.0.retain(|(name, ..)| *name != "TimeoutMs");
+
+        let error = from_key::<Settings, _>(&key).unwrap_err();
+
+        assert_eq!(error, Error::MissingValue("TimeoutMs"));
+        assert_eq!(NtStatus::from(error).as_raw(), STATUS_OBJECT_NAME_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let mut key = settings_key();
+        key.0[0] = ("TimeoutMs", REG_SZ, utf16(&["500"]));
+
+        let error = from_key::<Settings, _>(&key).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::InvalidValue {
+                name: "TimeoutMs",
+                ..
+            }
+        ));
+        assert_eq!(NtStatus::from(error).as_raw(), STATUS_OBJECT_TYPE_MISMATCH);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut key = settings_key();
+        key.0[4] = ("Level", REG_DWORD, 256u32.to_ne_bytes().to_vec());
+
+        let error = from_key::<Settings, _>(&key).unwrap_err();
+
+        assert!(matches!(error, Error::InvalidValue { name: "Level", .. }));
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let mut key = settings_key();
+        key.0[5] = ("Name", REG_NONE, Vec::new());
+
+        let error = from_key::<Settings, _>(&key).unwrap_err();
+
+        assert!(matches!(error, Error::InvalidValue { name: "Name", .. }));
+    }
+
+    #[test]
+    fn test_not_a_struct() {
+        let error = from_key::<u32, _>(&settings_key()).unwrap_err();
+
+        assert!(matches!(error, Error::Custom(_)));
+    }
+}
@@ -0,0 +1,1070 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Access to the registry, such as the `Parameters` key of a driver.
+//!
+//! A [`RegistryKey`] is an open key handle, which is closed when it is dropped.
+//! Keys can be opened by path (ex. `\Registry\Machine\Software\Sample`), or, in
+//! KMDF drivers, through the framework via
+//! [`RegistryKey::open_driver_parameters`] and
+//! [`RegistryKey::open_device_key`]. Values are read and written with typed
+//! accessors for each registry type, and the subkeys and values of a key can
+//! be enumerated when the `alloc` feature is enabled.
+//!
+//! With the `serde` feature, a struct of settings can be deserialized from the
+//! values of a key in one call, with `from_key`, or in KMDF drivers from the
+//! `Parameters` key of the driver, with `from_driver_parameters`.
+//!
+//! All functions in this module must be called at `PASSIVE_LEVEL`, which is
+//! asserted in debug builds.
+//!
+//! # Examples
+//!
+//! ```rust, no_run
+//! use wdk::{registry::RegistryKey, unicode_string, NtResult};
+//! use wdk_sys::KEY_READ;
+//!
+//! fn read_timeout() -> NtResult<u32> {
+//!     let key = RegistryKey::open(
+//!         unicode_string!("\\Registry\\Machine\\Software\\Sample"),
+//!         KEY_READ,
+//!     )?;
+//!     key.get_u32(unicode_string!("TimeoutMs"))
+//! }
+//! ```
+
+#[cfg(feature = "serde")]
+pub use de::*;
+
+#[cfg(feature = "serde")]
+mod de;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::mem::{offset_of, size_of};
+
+#[cfg(driver_model__driver_type = "KMDF")]
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    PLUGPLAY_REGKEY_DEVICE,
+    PLUGPLAY_REGKEY_DRIVER,
+    WDFKEY,
+};
+use wdk_sys::{
+    ntddk::{ZwClose, ZwCreateKey, ZwDeleteValueKey, ZwOpenKey, ZwQueryValueKey, ZwSetValueKey},
+    _KEY_VALUE_INFORMATION_CLASS,
+    ACCESS_MASK,
+    HANDLE,
+    KEY_VALUE_PARTIAL_INFORMATION,
+    NTSTATUS,
+    OBJECT_ATTRIBUTES,
+    OBJ_CASE_INSENSITIVE,
+    OBJ_KERNEL_HANDLE,
+    PULONG,
+    PVOID,
+    REG_BINARY,
+    REG_DWORD,
+    REG_OPTION_NON_VOLATILE,
+    REG_QWORD,
+    STATUS_BUFFER_OVERFLOW,
+    STATUS_INVALID_BUFFER_SIZE,
+    STATUS_INVALID_PARAMETER,
+    STATUS_OBJECT_TYPE_MISMATCH,
+    ULONG,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{
+    ntddk::{ZwEnumerateKey, ZwEnumerateValueKey},
+    _KEY_INFORMATION_CLASS,
+    KEY_BASIC_INFORMATION,
+    KEY_VALUE_FULL_INFORMATION,
+    REG_EXPAND_SZ,
+    REG_MULTI_SZ,
+    REG_SZ,
+    STATUS_BUFFER_TOO_SMALL,
+    STATUS_INSUFFICIENT_RESOURCES,
+    STATUS_NO_MORE_ENTRIES,
+};
+
+#[cfg(feature = "alloc")]
+use crate::string::UnicodeString;
+#[cfg(driver_model__driver_type = "KMDF")]
+use crate::wdf::{Device, Driver};
+use crate::{nt_success, string::UnicodeStr, NtResult};
+
+/// An open registry key.
+///
+/// The key is closed when the [`RegistryKey`] is dropped. Values are read with
+/// the typed getters (ex. [`RegistryKey::get_u32`]), which fail with
+/// `STATUS_OBJECT_TYPE_MISMATCH` if the value has a different registry type,
+/// and `STATUS_OBJECT_NAME_NOT_FOUND` if it does not exist.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{registry::RegistryKey, unicode_string, NtResult};
+/// use wdk_sys::KEY_ALL_ACCESS;
+///
+/// fn count_starts() -> NtResult<u32> {
+///     let key = RegistryKey::create(
+///         unicode_string!("\\Registry\\Machine\\Software\\Sample"),
+///         KEY_ALL_ACCESS,
+///     )?;
+///     let starts = key.get_u32(unicode_string!("Starts")).unwrap_or(0) + 1;
+///     key.set_u32(unicode_string!("Starts"), starts)?;
+///     Ok(starts)
+/// }
+/// ```
+pub struct RegistryKey {
+    handle: HANDLE,
+    /// The framework key object that owns `handle`, for keys opened via WDF
+    #[cfg(driver_model__driver_type = "KMDF")]
+    wdf_key: Option<WDFKEY>,
+}
+
+// SAFETY: `handle` is a kernel handle, which is valid in the context of any
+// thread
+unsafe impl Send for RegistryKey {}
+// SAFETY: The `Zw*Key` functions can be called concurrently on the same key
+// handle
+unsafe impl Sync for RegistryKey {}
+
+/// The registry keys of a device that can be opened with
+/// [`RegistryKey::open_device_key`]
+#[cfg(driver_model__driver_type = "KMDF")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceKeyType {
+    /// The hardware key of the device (`PLUGPLAY_REGKEY_DEVICE`), which holds
+    /// settings specific to the device instance
+    Hardware,
+    /// The software key of the device (`PLUGPLAY_REGKEY_DRIVER`), which holds
+    /// settings of the driver for the device instance
+    Software,
+}
+
+/// The data of a registry value, decoded according to its registry type.
+///
+/// Strings are decoded up to their first nul terminator. Values whose data
+/// does not match their type (ex. a `REG_DWORD` that is not 4 bytes long) are
+/// returned as [`RegistryValue::Other`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegistryValue {
+    /// A `REG_DWORD` value
+    Dword(u32),
+    /// A `REG_QWORD` value
+    Qword(u64),
+    /// A `REG_SZ` value
+    String(UnicodeString),
+    /// A `REG_EXPAND_SZ` value, whose environment variable references (ex.
+    /// `%SystemRoot%`) are not expanded
+    ExpandString(UnicodeString),
+    /// A `REG_MULTI_SZ` value
+    MultiString(Vec<UnicodeString>),
+    /// A `REG_BINARY` value
+    Binary(Vec<u8>),
+    /// A value of any other registry type, as raw bytes
+    Other {
+        /// The registry type of the value (ex. `REG_NONE`)
+        value_type: ULONG,
+        /// The raw data of the value
+        data: Vec<u8>,
+    },
+}
+
+/// Iterator over the names of the subkeys of a [`RegistryKey`], returned by
+/// [`RegistryKey::subkeys`]
+#[cfg(feature = "alloc")]
+pub struct SubKeys<'a> {
+    entries: Entries<'a>,
+}
+
+/// Iterator over the names and data of the values of a [`RegistryKey`],
+/// returned by [`RegistryKey::values`]
+#[cfg(feature = "alloc")]
+pub struct Values<'a> {
+    entries: Entries<'a>,
+}
+
+/// The state shared by [`SubKeys`] and [`Values`], which enumerate entries by
+/// index until `STATUS_NO_MORE_ENTRIES` or the first error
+#[cfg(feature = "alloc")]
+struct Entries<'a> {
+    key: &'a RegistryKey,
+    index: ULONG,
+    buffer: Vec<u64>,
+    done: bool,
+}
+
+/// The signature shared by `ZwEnumerateKey` and `ZwEnumerateValueKey`
+#[cfg(feature = "alloc")]
+type EnumerateFn = unsafe fn(HANDLE, ULONG, PVOID, ULONG, PULONG) -> NTSTATUS;
+
+impl RegistryKey {
+    /// Open the existing key at the absolute `path` (ex.
+    /// `\Registry\Machine\Software\Sample`), via `ZwOpenKey`, with the
+    /// `access` rights (ex. [`KEY_READ`](wdk_sys::KEY_READ)).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the key cannot be opened. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwOpenKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwopenkey#return-value)
+    pub fn open(path: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        Self::open_relative(core::ptr::null_mut(), path, access)
+    }
+
+    /// Open the key at the absolute `path`, via `ZwCreateKey`, with the
+    /// `access` rights (ex. [`KEY_ALL_ACCESS`](wdk_sys::KEY_ALL_ACCESS)). The
+    /// key is created, as a non-volatile key, if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the key cannot be opened or created. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwCreateKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwcreatekey#return-value)
+    pub fn create(path: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        Self::create_relative(core::ptr::null_mut(), path, access)
+    }
+
+    /// Open the existing subkey `name` of this key, via `ZwOpenKey`, with the
+    /// `access` rights.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the subkey cannot be opened. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwOpenKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwopenkey#return-value)
+    pub fn open_subkey(&self, name: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        Self::open_relative(self.handle, name, access)
+    }
+
+    /// Open the subkey `name` of this key, via `ZwCreateKey`, with the
+    /// `access` rights. The subkey is created, as a non-volatile key, if it
+    /// does not exist.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the subkey cannot be opened or created. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwCreateKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwcreatekey#return-value)
+    pub fn create_subkey(&self, name: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        Self::create_relative(self.handle, name, access)
+    }
+
+    /// Open the `Parameters` key under the service key of `driver`, via
+    /// `WdfDriverOpenParametersRegistryKey`, with the `access` rights.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to open the key. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDriverOpenParametersRegistryKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdriveropenparametersregistrykey#return-value)
+    #[cfg(driver_model__driver_type = "KMDF")]
+    pub fn open_driver_parameters(driver: &Driver, access: ACCESS_MASK) -> NtResult<Self> {
+        crate::debug_assert_passive_level();
+
+        let mut wdf_key: WDFKEY = core::ptr::null_mut();
+        let nt_status;
+        // SAFETY: `driver` is a valid framework driver object. Without object
+        // attributes, the key is parented to the driver, and it is closed when the
+        // returned `RegistryKey` is dropped.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDriverOpenParametersRegistryKey,
+                driver.as_raw(),
+                access,
+                core::ptr::null_mut(),
+                &mut wdf_key,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: `wdf_key` was just successfully opened by WDF
+        Ok(unsafe { Self::from_wdf_key(wdf_key) })
+    }
+
+    /// Open the `key_type` key of `device`, via `WdfDeviceOpenRegistryKey`,
+    /// with the `access` rights.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to open the key. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDeviceOpenRegistryKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdeviceopenregistrykey#return-value)
+    #[cfg(driver_model__driver_type = "KMDF")]
+    pub fn open_device_key(
+        device: &Device,
+        key_type: DeviceKeyType,
+        access: ACCESS_MASK,
+    ) -> NtResult<Self> {
+        crate::debug_assert_passive_level();
+
+        let mut wdf_key: WDFKEY = core::ptr::null_mut();
+        let nt_status;
+        // SAFETY: `device` is a valid framework device object. Without object
+        // attributes, the key is parented to the device, and it is closed when the
+        // returned `RegistryKey` is dropped.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDeviceOpenRegistryKey,
+                device.as_raw(),
+                key_type.as_raw(),
+                access,
+                core::ptr::null_mut(),
+                &mut wdf_key,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: `wdf_key` was just successfully opened by WDF
+        Ok(unsafe { Self::from_wdf_key(wdf_key) })
+    }
+
+    /// Returns the underlying key handle
+    #[must_use]
+    pub const fn as_raw(&self) -> HANDLE {
+        self.handle
+    }
+
+    /// Read the `REG_DWORD` value `name`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_OBJECT_TYPE_MISMATCH` if the value is not a `REG_DWORD`, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    pub fn get_u32(&self, name: &UnicodeStr) -> NtResult<u32> {
+        self.get_fixed(name, REG_DWORD).map(u32::from_ne_bytes)
+    }
+
+    /// Read the `REG_QWORD` value `name`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_OBJECT_TYPE_MISMATCH` if the value is not a `REG_QWORD`, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    pub fn get_u64(&self, name: &UnicodeStr) -> NtResult<u64> {
+        self.get_fixed(name, REG_QWORD).map(u64::from_ne_bytes)
+    }
+
+    /// Read the value `name`, of any registry type
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INSUFFICIENT_RESOURCES` if the value cannot be copied, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn get_value(&self, name: &UnicodeStr) -> NtResult<RegistryValue> {
+        crate::debug_assert_passive_level();
+
+        let mut buffer = Vec::new();
+        let length =
+            query_information_growing(&mut buffer, |information, length, result_length| {
+                self.query_value(name, information, length, result_length)
+            })?;
+        let (value_type, data) = parse_partial_information(as_bytes(&buffer, length))?;
+        RegistryValue::from_raw(value_type, data)
+    }
+
+    /// Read the `REG_SZ` or `REG_EXPAND_SZ` value `name`. Expandable strings
+    /// are returned without expanding their environment variable references.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_OBJECT_TYPE_MISMATCH` if the value is not a string, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn get_string(&self, name: &UnicodeStr) -> NtResult<UnicodeString> {
+        match self.get_value(name)? {
+            RegistryValue::String(string) | RegistryValue::ExpandString(string) => Ok(string),
+            _ => Err(STATUS_OBJECT_TYPE_MISMATCH.into()),
+        }
+    }
+
+    /// Read the `REG_MULTI_SZ` value `name`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_OBJECT_TYPE_MISMATCH` if the value is not a `REG_MULTI_SZ`, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn get_multi_string(&self, name: &UnicodeStr) -> NtResult<Vec<UnicodeString>> {
+        match self.get_value(name)? {
+            RegistryValue::MultiString(strings) => Ok(strings),
+            _ => Err(STATUS_OBJECT_TYPE_MISMATCH.into()),
+        }
+    }
+
+    /// Read the `REG_BINARY` value `name`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_OBJECT_TYPE_MISMATCH` if the value is not a `REG_BINARY`, or an error if it cannot be read. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwQueryValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwqueryvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn get_binary(&self, name: &UnicodeStr) -> NtResult<Vec<u8>> {
+        match self.get_value(name)? {
+            RegistryValue::Binary(data) => Ok(data),
+            _ => Err(STATUS_OBJECT_TYPE_MISMATCH.into()),
+        }
+    }
+
+    /// Write `value` to the value `name`, as a `REG_DWORD`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value cannot be written. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwSetValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwsetvaluekey#return-value)
+    pub fn set_u32(&self, name: &UnicodeStr, value: u32) -> NtResult<()> {
+        self.set_value(name, REG_DWORD, &value.to_ne_bytes())
+    }
+
+    /// Write `value` to the value `name`, as a `REG_QWORD`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value cannot be written. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwSetValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwsetvaluekey#return-value)
+    pub fn set_u64(&self, name: &UnicodeStr, value: u64) -> NtResult<()> {
+        self.set_value(name, REG_QWORD, &value.to_ne_bytes())
+    }
+
+    /// Write `data` to the value `name`, as a `REG_BINARY`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INVALID_PARAMETER` if `data` is longer than `ULONG::MAX` bytes, or an error if the value cannot be written. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwSetValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwsetvaluekey#return-value)
+    pub fn set_binary(&self, name: &UnicodeStr, data: &[u8]) -> NtResult<()> {
+        self.set_value(name, REG_BINARY, data)
+    }
+
+    /// Write `value` to the value `name`, as a nul-terminated `REG_SZ`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INSUFFICIENT_RESOURCES` if the data cannot be allocated, or an error if the value cannot be written. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwSetValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwsetvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn set_string(&self, name: &UnicodeStr, value: &UnicodeStr) -> NtResult<()> {
+        let data = encode_strings(&[value], false)?;
+        self.set_value(name, REG_SZ, &data)
+    }
+
+    /// Write `values` to the value `name`, as a `REG_MULTI_SZ`. Empty strings
+    /// cannot be stored in a `REG_MULTI_SZ`, since they mark the end of the
+    /// list.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INVALID_PARAMETER` if any of the `values` is empty, `STATUS_INSUFFICIENT_RESOURCES` if the data cannot be allocated, or an error if the value cannot be written. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwSetValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwsetvaluekey#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn set_multi_string(&self, name: &UnicodeStr, values: &[&UnicodeStr]) -> NtResult<()> {
+        if values.iter().any(|value| value.is_empty()) {
+            return Err(STATUS_INVALID_PARAMETER.into());
+        }
+
+        let data = encode_strings(values, true)?;
+        self.set_value(name, REG_MULTI_SZ, &data)
+    }
+
+    /// Delete the value `name`, via `ZwDeleteValueKey`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value cannot be deleted. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [ZwDeleteValueKey Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwdeletevaluekey#return-value)
+    pub fn delete_value(&self, name: &UnicodeStr) -> NtResult<()> {
+        crate::debug_assert_passive_level();
+
+        let nt_status;
+        // SAFETY: `handle` is an open key handle owned by `self`, and `name` is a
+        // valid `UNICODE_STRING` that `ZwDeleteValueKey` does not modify
+        unsafe {
+            nt_status = ZwDeleteValueKey(self.handle, name.as_raw().cast_mut());
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the names of the subkeys of this key, via
+    /// `ZwEnumerateKey`.
+    ///
+    /// The iterator stops after the first error. Subkeys that are created or
+    /// deleted during the enumeration may be skipped or returned twice.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub const fn subkeys(&self) -> SubKeys<'_> {
+        SubKeys {
+            entries: Entries::new(self),
+        }
+    }
+
+    /// Returns an iterator over the names and data of the values of this key,
+    /// via `ZwEnumerateValueKey`.
+    ///
+    /// The iterator stops after the first error. Values that are created or
+    /// deleted during the enumeration may be skipped or returned twice.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub const fn values(&self) -> Values<'_> {
+        Values {
+            entries: Entries::new(self),
+        }
+    }
+
+    /// Wrap a key handle opened by the driver
+    const fn from_handle(handle: HANDLE) -> Self {
+        Self {
+            handle,
+            #[cfg(driver_model__driver_type = "KMDF")]
+            wdf_key: None,
+        }
+    }
+
+    /// Wrap a framework key object opened by the driver
+    ///
+    /// # Safety
+    ///
+    /// `wdf_key` must be a valid handle to an open framework key object, which
+    /// is not closed by anything other than the returned `RegistryKey`
+    #[cfg(driver_model__driver_type = "KMDF")]
+    unsafe fn from_wdf_key(wdf_key: WDFKEY) -> Self {
+        let handle;
+        // SAFETY: The caller guarantees that `wdf_key` is a valid, open key
+        unsafe {
+            handle = call_unsafe_wdf_function_binding!(WdfRegistryWdmGetHandle, wdf_key);
+        }
+        Self {
+            handle,
+            wdf_key: Some(wdf_key),
+        }
+    }
+
+    fn open_relative(root: HANDLE, path: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        crate::debug_assert_passive_level();
+
+        let mut object_attributes = key_object_attributes(root, path);
+        let mut handle: HANDLE = core::ptr::null_mut();
+        let nt_status;
+        // SAFETY: `object_attributes` is fully initialized, and refers to `path` and
+        // `root`, which are valid for the duration of the call. The opened handle is
+        // closed when the returned `RegistryKey` is dropped.
+        unsafe {
+            nt_status = ZwOpenKey(&mut handle, access, &mut object_attributes);
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(Self::from_handle(handle))
+    }
+
+    fn create_relative(root: HANDLE, path: &UnicodeStr, access: ACCESS_MASK) -> NtResult<Self> {
+        crate::debug_assert_passive_level();
+
+        let mut object_attributes = key_object_attributes(root, path);
+        let mut handle: HANDLE = core::ptr::null_mut();
+        let nt_status;
+        // SAFETY: `object_attributes` is fully initialized, and refers to `path` and
+        // `root`, which are valid for the duration of the call. The class and
+        // disposition are optional. The opened handle is closed when the returned
+        // `RegistryKey` is dropped.
+        unsafe {
+            nt_status = ZwCreateKey(
+                &mut handle,
+                access,
+                &mut object_attributes,
+                0,
+                core::ptr::null_mut(),
+                REG_OPTION_NON_VOLATILE,
+                core::ptr::null_mut(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(Self::from_handle(handle))
+    }
+
+    /// Read the value `name`, which must be of `value_type` and exactly `N`
+    /// bytes long, without allocating
+    fn get_fixed<const N: usize>(&self, name: &UnicodeStr, value_type: ULONG) -> NtResult<[u8; N]> {
+        crate::debug_assert_passive_level();
+
+        // Large enough for the `KEY_VALUE_PARTIAL_INFORMATION` of a `REG_QWORD`
+        let mut buffer = [0u64; 4];
+        let (nt_status, length) =
+            query_information(&mut buffer, |information, length, result_length| {
+                self.query_value(name, information, length, result_length)
+            });
+        if nt_status == STATUS_BUFFER_OVERFLOW {
+            // The value is too long to be of `value_type`
+            return Err(STATUS_OBJECT_TYPE_MISMATCH.into());
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let (actual_type, data) = parse_partial_information(as_bytes(&buffer, length))?;
+        if actual_type != value_type {
+            return Err(STATUS_OBJECT_TYPE_MISMATCH.into());
+        }
+        data.try_into()
+            .map_err(|_| STATUS_OBJECT_TYPE_MISMATCH.into())
+    }
+
+    /// Call `ZwQueryValueKey` for the value `name`, with a
+    /// `KEY_VALUE_PARTIAL_INFORMATION` buffer of `length` bytes
+    fn query_value(
+        &self,
+        name: &UnicodeStr,
+        information: PVOID,
+        length: ULONG,
+        result_length: PULONG,
+    ) -> NTSTATUS {
+        // SAFETY: `handle` is an open key handle owned by `self`, `name` is a valid
+        // `UNICODE_STRING` that `ZwQueryValueKey` does not modify, and
+        // `query_information` passes a buffer that is valid for writes of `length`
+        // bytes
+        unsafe {
+            ZwQueryValueKey(
+                self.handle,
+                name.as_raw().cast_mut(),
+                _KEY_VALUE_INFORMATION_CLASS::KeyValuePartialInformation,
+                information,
+                length,
+                result_length,
+            )
+        }
+    }
+
+    fn set_value(&self, name: &UnicodeStr, value_type: ULONG, data: &[u8]) -> NtResult<()> {
+        crate::debug_assert_passive_level();
+
+        let Ok(data_size) = ULONG::try_from(data.len()) else {
+            return Err(STATUS_INVALID_PARAMETER.into());
+        };
+        let nt_status;
+        // SAFETY: `handle` is an open key handle owned by `self`, `name` is a valid
+        // `UNICODE_STRING`, and `data` is valid for reads of `data_size` bytes.
+        // `ZwSetValueKey` does not modify `name` or `data`.
+        unsafe {
+            nt_status = ZwSetValueKey(
+                self.handle,
+                name.as_raw().cast_mut(),
+                0,
+                value_type,
+                data.as_ptr().cast_mut().cast(),
+                data_size,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RegistryKey {
+    fn drop(&mut self) {
+        crate::debug_assert_passive_level();
+
+        #[cfg(driver_model__driver_type = "KMDF")]
+        if let Some(wdf_key) = self.wdf_key {
+            // SAFETY: `wdf_key` is an open key owned by `self`, and `handle`, which
+            // belongs to it, is not used after it is closed
+            unsafe {
+                call_unsafe_wdf_function_binding!(WdfRegistryClose, wdf_key);
+            }
+            return;
+        }
+
+        let nt_status;
+        // SAFETY: `handle` is an open key handle owned by `self`, which is not used
+        // after it is closed
+        unsafe {
+            nt_status = ZwClose(self.handle);
+        }
+        debug_assert!(
+            nt_success(nt_status),
+            "closing an open key handle should succeed"
+        );
+    }
+}
+
+#[cfg(driver_model__driver_type = "KMDF")]
+impl DeviceKeyType {
+    const fn as_raw(self) -> ULONG {
+        match self {
+            Self::Hardware => PLUGPLAY_REGKEY_DEVICE,
+            Self::Software => PLUGPLAY_REGKEY_DRIVER,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl RegistryValue {
+    /// Decode the `data` of a value of the registry type `value_type`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing
+    /// `STATUS_INSUFFICIENT_RESOURCES` if the decoded value cannot be
+    /// allocated, or `STATUS_INVALID_PARAMETER` if a string is longer than
+    /// [`UNICODE_STRING_MAX_LENGTH`](crate::string::UNICODE_STRING_MAX_LENGTH).
+    pub fn from_raw(value_type: ULONG, data: &[u8]) -> NtResult<Self> {
+        match value_type {
+            REG_DWORD => {
+                if let Ok(bytes) = data.try_into() {
+                    return Ok(Self::Dword(u32::from_ne_bytes(bytes)));
+                }
+            }
+            REG_QWORD => {
+                if let Ok(bytes) = data.try_into() {
+                    return Ok(Self::Qword(u64::from_ne_bytes(bytes)));
+                }
+            }
+            REG_SZ => return decode_string(data).map(Self::String),
+            REG_EXPAND_SZ => return decode_string(data).map(Self::ExpandString),
+            REG_MULTI_SZ => return decode_multi_string(data).map(Self::MultiString),
+            REG_BINARY => return try_to_vec(data).map(Self::Binary),
+            _ => {}
+        }
+
+        Ok(Self::Other {
+            value_type,
+            data: try_to_vec(data)?,
+        })
+    }
+
+    /// Returns the registry type of the value (ex. `REG_DWORD`)
+    #[must_use]
+    pub const fn value_type(&self) -> ULONG {
+        match self {
+            Self::Dword(_) => REG_DWORD,
+            Self::Qword(_) => REG_QWORD,
+            Self::String(_) => REG_SZ,
+            Self::ExpandString(_) => REG_EXPAND_SZ,
+            Self::MultiString(_) => REG_MULTI_SZ,
+            Self::Binary(_) => REG_BINARY,
+            Self::Other { value_type, .. } => *value_type,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Iterator for SubKeys<'_> {
+    type Item = NtResult<UnicodeString>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next(enumerate_key)?;
+        Some(entry.and_then(|bytes| {
+            let name_length = read_ulong(bytes, offset_of!(KEY_BASIC_INFORMATION, NameLength))?;
+            let name = read_bytes(bytes, offset_of!(KEY_BASIC_INFORMATION, Name), name_length)?;
+            decode_utf16(name)
+        }))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::iter::FusedIterator for SubKeys<'_> {}
+
+#[cfg(feature = "alloc")]
+impl Iterator for Values<'_> {
+    type Item = NtResult<(UnicodeString, RegistryValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next(enumerate_value_key)?;
+        Some(entry.and_then(|bytes| {
+            let value_type = read_ulong(bytes, offset_of!(KEY_VALUE_FULL_INFORMATION, Type))?;
+            let data_offset =
+                read_ulong(bytes, offset_of!(KEY_VALUE_FULL_INFORMATION, DataOffset))?;
+            let data_length =
+                read_ulong(bytes, offset_of!(KEY_VALUE_FULL_INFORMATION, DataLength))?;
+            let name_length =
+                read_ulong(bytes, offset_of!(KEY_VALUE_FULL_INFORMATION, NameLength))?;
+            let name = read_bytes(
+                bytes,
+                offset_of!(KEY_VALUE_FULL_INFORMATION, Name),
+                name_length,
+            )?;
+            let data = read_bytes(bytes, data_offset as usize, data_length)?;
+            Ok((
+                decode_utf16(name)?,
+                RegistryValue::from_raw(value_type, data)?,
+            ))
+        }))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::iter::FusedIterator for Values<'_> {}
+
+#[cfg(feature = "alloc")]
+impl<'a> Entries<'a> {
+    const fn new(key: &'a RegistryKey) -> Self {
+        Self {
+            key,
+            index: 0,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Returns the bytes of the next entry, as returned by `enumerate`, or
+    /// [`None`] once all entries have been returned
+    fn next(&mut self, enumerate: EnumerateFn) -> Option<NtResult<&[u8]>> {
+        if self.done {
+            return None;
+        }
+        crate::debug_assert_passive_level();
+
+        let handle = self.key.handle;
+        let index = self.index;
+        let result =
+            query_information_growing(&mut self.buffer, |information, length, result_length| {
+                // SAFETY: `handle` is an open key handle, kept alive by the borrow of its
+                // `RegistryKey`, and `query_information` passes a buffer that is valid for
+                // writes of `length` bytes
+                unsafe { enumerate(handle, index, information, length, result_length) }
+            });
+        self.index += 1;
+
+        match result {
+            Ok(length) => Some(Ok(as_bytes(&self.buffer, length))),
+            Err(nt_status) => {
+                self.done = true;
+                (nt_status.as_raw() != STATUS_NO_MORE_ENTRIES).then_some(Err(nt_status))
+            }
+        }
+    }
+}
+
+/// Returns the [`OBJECT_ATTRIBUTES`] of the key `name`, relative to the key
+/// `root` if it is not null, for a kernel handle
+const fn key_object_attributes(root: HANDLE, name: &UnicodeStr) -> OBJECT_ATTRIBUTES {
+    OBJECT_ATTRIBUTES {
+        Length: object_attributes_size(),
+        RootDirectory: root,
+        // `ZwOpenKey` and `ZwCreateKey` do not modify the name
+        ObjectName: name.as_raw().cast_mut(),
+        Attributes: OBJ_KERNEL_HANDLE | OBJ_CASE_INSENSITIVE,
+        SecurityDescriptor: core::ptr::null_mut(),
+        SecurityQualityOfService: core::ptr::null_mut(),
+    }
+}
+
+/// Returns the size of [`OBJECT_ATTRIBUTES`], as set by
+/// `InitializeObjectAttributes`
+const fn object_attributes_size() -> ULONG {
+    // clippy::cast_possible_truncation: `OBJECT_ATTRIBUTES` is a few dozen bytes
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        size_of::<OBJECT_ATTRIBUTES>() as ULONG
+    }
+}
+
+/// Call `query`, which fills a variable-length `*_INFORMATION` structure, with
+/// all of `buffer`. Returns the status of the call and the number of bytes
+/// that it reported, which is the required size if the buffer was too small.
+fn query_information(
+    buffer: &mut [u64],
+    query: impl FnOnce(PVOID, ULONG, PULONG) -> NTSTATUS,
+) -> (NTSTATUS, usize) {
+    let length = ULONG::try_from(core::mem::size_of_val(buffer)).unwrap_or(ULONG::MAX);
+    let mut result_length: ULONG = 0;
+    let nt_status = query(buffer.as_mut_ptr().cast(), length, &mut result_length);
+    (nt_status, result_length as usize)
+}
+
+/// Call `query` as in [`query_information`], growing `buffer` until the whole
+/// structure fits. Returns the number of bytes written to `buffer`.
+#[cfg(feature = "alloc")]
+fn query_information_growing(
+    buffer: &mut Vec<u64>,
+    mut query: impl FnMut(PVOID, ULONG, PULONG) -> NTSTATUS,
+) -> NtResult<usize> {
+    loop {
+        let (nt_status, result_length) = query_information(buffer, &mut query);
+        if nt_status != STATUS_BUFFER_OVERFLOW && nt_status != STATUS_BUFFER_TOO_SMALL {
+            if !nt_success(nt_status) {
+                return Err(nt_status.into());
+            }
+            return Ok(result_length);
+        }
+
+        let words = result_length.div_ceil(size_of::<u64>());
+        // A well-behaved `query` always asks for more than it was given
+        if words <= buffer.len() {
+            return Err(nt_status.into());
+        }
+        if buffer.try_reserve_exact(words - buffer.len()).is_err() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+        }
+        buffer.resize(words, 0);
+    }
+}
+
+/// Returns the first `length` bytes of `buffer`, or all of them if it is
+/// shorter
+fn as_bytes(buffer: &[u64], length: usize) -> &[u8] {
+    // SAFETY: The slice covers exactly the memory of `buffer`, which is fully
+    // initialized, and `u8` has no alignment requirements
+    let bytes = unsafe {
+        core::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), core::mem::size_of_val(buffer))
+    };
+    &bytes[..length.min(bytes.len())]
+}
+
+/// Read the `ULONG` field at `offset` of a `*_INFORMATION` structure
+fn read_ulong(bytes: &[u8], offset: usize) -> NtResult<ULONG> {
+    bytes
+        .get(offset..offset + size_of::<ULONG>())
+        .and_then(|field| field.try_into().ok())
+        .map(ULONG::from_ne_bytes)
+        .ok_or_else(|| STATUS_INVALID_BUFFER_SIZE.into())
+}
+
+/// Returns the `length` bytes at `offset` of a `*_INFORMATION` structure
+fn read_bytes(bytes: &[u8], offset: usize, length: ULONG) -> NtResult<&[u8]> {
+    bytes
+        .get(offset..)
+        .and_then(|tail| tail.get(..length as usize))
+        .ok_or_else(|| STATUS_INVALID_BUFFER_SIZE.into())
+}
+
+/// Returns the type and data of a `KEY_VALUE_PARTIAL_INFORMATION`
+fn parse_partial_information(bytes: &[u8]) -> NtResult<(ULONG, &[u8])> {
+    let value_type = read_ulong(bytes, offset_of!(KEY_VALUE_PARTIAL_INFORMATION, Type))?;
+    let data_length = read_ulong(bytes, offset_of!(KEY_VALUE_PARTIAL_INFORMATION, DataLength))?;
+    let data = read_bytes(
+        bytes,
+        offset_of!(KEY_VALUE_PARTIAL_INFORMATION, Data),
+        data_length,
+    )?;
+    Ok((value_type, data))
+}
+
+/// Call `ZwEnumerateKey` for the `KEY_BASIC_INFORMATION` of a subkey
+///
+/// # Safety
+///
+/// The arguments must be valid for `ZwEnumerateKey`
+#[cfg(feature = "alloc")]
+unsafe fn enumerate_key(
+    handle: HANDLE,
+    index: ULONG,
+    information: PVOID,
+    length: ULONG,
+    result_length: PULONG,
+) -> NTSTATUS {
+    // SAFETY: The caller guarantees that the arguments are valid
+    unsafe {
+        ZwEnumerateKey(
+            handle,
+            index,
+            _KEY_INFORMATION_CLASS::KeyBasicInformation,
+            information,
+            length,
+            result_length,
+        )
+    }
+}
+
+/// Call `ZwEnumerateValueKey` for the `KEY_VALUE_FULL_INFORMATION` of a value
+///
+/// # Safety
+///
+/// The arguments must be valid for `ZwEnumerateValueKey`
+#[cfg(feature = "alloc")]
+unsafe fn enumerate_value_key(
+    handle: HANDLE,
+    index: ULONG,
+    information: PVOID,
+    length: ULONG,
+    result_length: PULONG,
+) -> NTSTATUS {
+    // SAFETY: The caller guarantees that the arguments are valid
+    unsafe {
+        ZwEnumerateValueKey(
+            handle,
+            index,
+            _KEY_VALUE_INFORMATION_CLASS::KeyValueFullInformation,
+            information,
+            length,
+            result_length,
+        )
+    }
+}
+
+/// Copy `data` into a new [`Vec`], failing with
+/// `STATUS_INSUFFICIENT_RESOURCES` if it cannot be allocated
+#[cfg(feature = "alloc")]
+fn try_to_vec<T: Copy>(data: &[T]) -> NtResult<Vec<T>> {
+    let mut vec = Vec::new();
+    if vec.try_reserve_exact(data.len()).is_err() {
+        return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+    }
+    vec.extend_from_slice(data);
+    Ok(vec)
+}
+
+/// Returns the UTF-16 code units stored in `bytes`, ignoring a trailing odd
+/// byte
+#[cfg(feature = "alloc")]
+fn utf16_units(bytes: &[u8]) -> NtResult<Vec<u16>> {
+    let mut utf16 = Vec::new();
+    if utf16.try_reserve_exact(bytes.len() / 2).is_err() {
+        return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+    }
+    utf16.extend(
+        bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_ne_bytes([unit[0], unit[1]])),
+    );
+    Ok(utf16)
+}
+
+/// Decode a counted UTF-16 string, such as a key or value name
+#[cfg(feature = "alloc")]
+fn decode_utf16(bytes: &[u8]) -> NtResult<UnicodeString> {
+    UnicodeString::from_utf16(&utf16_units(bytes)?)
+}
+
+/// Decode the data of a `REG_SZ` or `REG_EXPAND_SZ`, up to its first nul
+/// terminator
+#[cfg(feature = "alloc")]
+fn decode_string(data: &[u8]) -> NtResult<UnicodeString> {
+    let utf16 = utf16_units(data)?;
+    let length = utf16
+        .iter()
+        .position(|&unit| unit == 0)
+        .unwrap_or(utf16.len());
+    UnicodeString::from_utf16(&utf16[..length])
+}
+
+/// Decode the data of a `REG_MULTI_SZ`, whose list of nul-terminated strings
+/// ends with an empty string
+#[cfg(feature = "alloc")]
+fn decode_multi_string(data: &[u8]) -> NtResult<Vec<UnicodeString>> {
+    let utf16 = utf16_units(data)?;
+    let mut strings = Vec::new();
+    for string in utf16
+        .split(|&unit| unit == 0)
+        .take_while(|string| !string.is_empty())
+    {
+        if strings.try_reserve(1).is_err() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+        }
+        strings.push(UnicodeString::from_utf16(string)?);
+    }
+    Ok(strings)
+}
+
+/// Encode `strings` as a sequence of nul-terminated UTF-16 strings, followed by
+/// an empty string if `terminate_list` is set
+#[cfg(feature = "alloc")]
+fn encode_strings(strings: &[&UnicodeStr], terminate_list: bool) -> NtResult<Vec<u8>> {
+    let units =
+        strings.iter().map(|string| string.len() + 1).sum::<usize>() + usize::from(terminate_list);
+    let mut data = Vec::new();
+    if data.try_reserve_exact(units * size_of::<u16>()).is_err() {
+        return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+    }
+
+    for string in strings {
+        for unit in string.as_utf16().iter().chain(&[0]) {
+            data.extend_from_slice(&unit.to_ne_bytes());
+        }
+    }
+    if terminate_list {
+        data.extend_from_slice(&0u16.to_ne_bytes());
+    }
+    Ok(data)
+}
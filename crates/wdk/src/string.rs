@@ -0,0 +1,852 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Safe wrappers for the counted strings used by the WDK
+//! ([`UNICODE_STRING`] and [`ANSI_STRING`]).
+//!
+//! [`UnicodeStr`] and [`AnsiStr`] are borrowed views of strings owned by
+//! someone else (ex. the registry path passed to `DriverEntry`), while
+//! [`UnicodeString`] and [`AnsiString`] own a buffer allocated with the global
+//! allocator. Static strings can be declared at compile time with
+//! [`unicode_string!`](crate::unicode_string).
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    string::{FromUtf16Error, String},
+    vec::Vec,
+};
+#[cfg(feature = "alloc")]
+use core::{borrow::Borrow, ops::Deref};
+use core::{
+    char::DecodeUtf16Error,
+    cmp::Ordering,
+    fmt::{self, Write},
+    hash::{Hash, Hasher},
+};
+
+use wdk_sys::{ANSI_STRING, PCUNICODE_STRING, UNICODE_STRING};
+#[cfg(feature = "alloc")]
+use wdk_sys::{STATUS_INSUFFICIENT_RESOURCES, STATUS_INVALID_PARAMETER};
+
+#[cfg(feature = "alloc")]
+use crate::NtResult;
+
+/// The maximum number of UTF-16 code units in a [`UNICODE_STRING`], whose
+/// length is a `USHORT` number of bytes
+pub const UNICODE_STRING_MAX_LENGTH: usize = u16::MAX as usize / core::mem::size_of::<u16>();
+
+/// The maximum number of bytes in an [`ANSI_STRING`], whose length is a
+/// `USHORT`
+pub const ANSI_STRING_MAX_LENGTH: usize = u16::MAX as usize;
+
+/// Declare a `&'static` [`UnicodeStr`](crate::string::UnicodeStr) from a string
+/// literal. The literal is encoded as UTF-16 at compile time.
+///
+/// # Examples
+///
+/// ```rust
+/// use wdk::{string::UnicodeStr, unicode_string};
+///
+/// let device_name: &'static UnicodeStr = unicode_string!("\\Device\\Sample");
+/// assert_eq!(device_name, "\\Device\\Sample");
+/// ```
+///
+/// # Panics
+///
+/// Fails to compile if the literal is longer than
+/// [`UNICODE_STRING_MAX_LENGTH`](crate::string::UNICODE_STRING_MAX_LENGTH)
+/// UTF-16 code units.
+#[macro_export]
+macro_rules! unicode_string {
+    ($string:expr $(,)?) => {{
+        const STRING: &str = $string;
+        const UTF16: [u16; $crate::string::_utf16_len(STRING)] =
+            $crate::string::_encode_utf16(STRING);
+        static UNICODE_STR: $crate::string::UnicodeStr =
+            $crate::string::UnicodeStr::_from_static_utf16(&UTF16);
+        &UNICODE_STR
+    }};
+}
+
+/// Returns the number of UTF-16 code units needed to encode `string`. This is
+/// an implementation detail of [`unicode_string!`](crate::unicode_string).
+#[doc(hidden)]
+#[must_use]
+pub const fn _utf16_len(string: &str) -> usize {
+    let bytes = string.as_bytes();
+    let mut length = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        let (width, units) = utf8_sequence_width(bytes[index]);
+        length += units;
+        index += width;
+    }
+    length
+}
+
+/// Encode `string` as `N` UTF-16 code units. This is an implementation detail
+/// of [`unicode_string!`](crate::unicode_string).
+#[doc(hidden)]
+#[must_use]
+#[allow(clippy::cast_lossless, clippy::cast_possible_truncation)]
+pub const fn _encode_utf16<const N: usize>(string: &str) -> [u16; N] {
+    assert!(
+        N <= UNICODE_STRING_MAX_LENGTH,
+        "string is too long for a UNICODE_STRING"
+    );
+
+    let bytes = string.as_bytes();
+    let mut utf16 = [0; N];
+    let mut byte_index = 0;
+    let mut unit_index = 0;
+    while byte_index < bytes.len() {
+        let (width, _) = utf8_sequence_width(bytes[byte_index]);
+        // `string` is valid UTF-8, so every sequence has its continuation bytes
+        let code_point = match width {
+            1 => bytes[byte_index] as u32,
+            2 => ((bytes[byte_index] & 0x1F) as u32) << 6 | (bytes[byte_index + 1] & 0x3F) as u32,
+            3 => {
+                ((bytes[byte_index] & 0x0F) as u32) << 12
+                    | ((bytes[byte_index + 1] & 0x3F) as u32) << 6
+                    | (bytes[byte_index + 2] & 0x3F) as u32
+            }
+            _ => {
+                ((bytes[byte_index] & 0x07) as u32) << 18
+                    | ((bytes[byte_index + 1] & 0x3F) as u32) << 12
+                    | ((bytes[byte_index + 2] & 0x3F) as u32) << 6
+                    | (bytes[byte_index + 3] & 0x3F) as u32
+            }
+        };
+
+        if code_point >= 0x1_0000 {
+            let code_point = code_point - 0x1_0000;
+            utf16[unit_index] = 0xD800 | (code_point >> 10) as u16;
+            utf16[unit_index + 1] = 0xDC00 | (code_point & 0x3FF) as u16;
+            unit_index += 2;
+        } else {
+            utf16[unit_index] = code_point as u16;
+            unit_index += 1;
+        }
+        byte_index += width;
+    }
+    assert!(unit_index == N, "N must be the UTF-16 length of the string");
+
+    utf16
+}
+
+/// Returns the number of bytes in the UTF-8 sequence starting with
+/// `leading_byte`, and the number of UTF-16 code units it encodes to
+const fn utf8_sequence_width(leading_byte: u8) -> (usize, usize) {
+    match leading_byte {
+        0xF0.. => (4, 2),
+        0xE0.. => (3, 1),
+        0xC0.. => (2, 1),
+        _ => (1, 1),
+    }
+}
+
+/// A borrowed [`UNICODE_STRING`].
+///
+/// This is a view of a counted UTF-16 string owned by someone else, such as
+/// the registry path that the system passes to `DriverEntry`. The string is
+/// not required to be valid UTF-16 or nul-terminated.
+#[repr(transparent)]
+pub struct UnicodeStr(UNICODE_STRING);
+
+// SAFETY: A `UnicodeStr` is only ever accessed through shared references, which
+// only read the string
+unsafe impl Sync for UnicodeStr {}
+
+impl UnicodeStr {
+    /// Borrow the [`UNICODE_STRING`] pointed to by `unicode_string`
+    ///
+    /// # Safety
+    ///
+    /// `unicode_string` must point to a valid [`UNICODE_STRING`], whose
+    /// `Buffer` is either null or valid for reads of `Length` bytes. Neither
+    /// the [`UNICODE_STRING`] nor its buffer may be mutated for the lifetime
+    /// `'a`.
+    #[must_use]
+    pub const unsafe fn from_raw<'a>(unicode_string: PCUNICODE_STRING) -> &'a Self {
+        // SAFETY: `UnicodeStr` is `repr(transparent)` over `UNICODE_STRING`, and the
+        // caller guarantees that `unicode_string` is valid for `'a`
+        unsafe { &*unicode_string.cast::<Self>() }
+    }
+
+    /// Wrap a static UTF-16 buffer. This is an implementation detail of
+    /// [`unicode_string!`](crate::unicode_string).
+    #[doc(hidden)]
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn _from_static_utf16(utf16: &'static [u16]) -> Self {
+        assert!(
+            utf16.len() <= UNICODE_STRING_MAX_LENGTH,
+            "string is too long for a UNICODE_STRING"
+        );
+
+        let length = (utf16.len() * core::mem::size_of::<u16>()) as u16;
+        Self(UNICODE_STRING {
+            Length: length,
+            MaximumLength: length,
+            // The buffer is never written through, since a `UnicodeStr` is only ever
+            // accessed through shared references
+            Buffer: utf16.as_ptr().cast_mut(),
+        })
+    }
+
+    /// Returns a raw pointer to the underlying [`UNICODE_STRING`]
+    #[must_use]
+    pub const fn as_raw(&self) -> PCUNICODE_STRING {
+        core::ptr::from_ref(&self.0)
+    }
+
+    /// Returns the UTF-16 code units of the string
+    #[must_use]
+    pub fn as_utf16(&self) -> &[u16] {
+        if self.0.Buffer.is_null() {
+            return &[];
+        }
+
+        // SAFETY: This is safe because:
+        //         1. `Buffer` is non-null, and valid for reads of `Length` bytes for
+        //            the lifetime of `self`, as guaranteed by `UnicodeStr::from_raw`.
+        //         2. `Buffer` points to `len()` consecutive properly initialized
+        //            `WCHAR`s.
+        //         3. The buffer is not mutated for the lifetime of `self`.
+        //         4. `Length` is a `USHORT`, so the total size is never larger than
+        //            `isize::MAX`.
+        unsafe { core::slice::from_raw_parts(self.0.Buffer, self.len()) }
+    }
+
+    /// Returns the length of the string, in UTF-16 code units
+    #[must_use]
+    pub fn len(&self) -> usize {
+        usize::from(self.0.Length) / core::mem::size_of::<u16>()
+    }
+
+    /// Returns whether the string is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the `char`s of the string, yielding an error
+    /// for each unpaired surrogate
+    pub fn chars(&self) -> impl Iterator<Item = Result<char, DecodeUtf16Error>> + '_ {
+        char::decode_utf16(self.as_utf16().iter().copied())
+    }
+
+    /// Returns an iterator over the `char`s of the string, replacing each
+    /// unpaired surrogate with [`char::REPLACEMENT_CHARACTER`]
+    pub fn chars_lossy(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars()
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Returns whether the string is equal to `other`, ignoring case. Each
+    /// `char` is compared by its Unicode uppercase mapping.
+    #[must_use]
+    pub fn eq_ignore_case(&self, other: &Self) -> bool {
+        self.chars_lossy()
+            .flat_map(char::to_uppercase)
+            .eq(other.chars_lossy().flat_map(char::to_uppercase))
+    }
+}
+
+impl PartialEq for UnicodeStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_utf16() == other.as_utf16()
+    }
+}
+
+impl Eq for UnicodeStr {}
+
+impl PartialEq<str> for UnicodeStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_utf16().iter().copied().eq(other.encode_utf16())
+    }
+}
+
+impl PartialEq<&str> for UnicodeStr {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialOrd for UnicodeStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UnicodeStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_utf16().cmp(other.as_utf16())
+    }
+}
+
+impl Hash for UnicodeStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_utf16().hash(state);
+    }
+}
+
+impl fmt::Display for UnicodeStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars_lossy().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl fmt::Debug for UnicodeStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.chars_lossy() {
+            for c in c.escape_debug() {
+                f.write_char(c)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&UnicodeStr> for String {
+    type Error = FromUtf16Error;
+
+    fn try_from(unicode_str: &UnicodeStr) -> Result<Self, Self::Error> {
+        Self::from_utf16(unicode_str.as_utf16())
+    }
+}
+
+/// An owned [`UNICODE_STRING`], whose buffer is allocated with the global
+/// allocator (ex. `wdk_alloc::WdkAllocator` in kernel-mode drivers).
+///
+/// [`UnicodeString`] dereferences to [`UnicodeStr`], so it can be passed
+/// anywhere a borrowed string is expected. Since the length of a
+/// [`UNICODE_STRING`] is limited to [`UNICODE_STRING_MAX_LENGTH`] code units,
+/// and allocation failures must be handled in drivers, all functions that grow
+/// the string are fallible.
+#[cfg(feature = "alloc")]
+pub struct UnicodeString {
+    raw: UNICODE_STRING,
+    buffer: Vec<u16>,
+}
+
+// SAFETY: `raw` only points into `buffer`, which is owned by the
+// `UnicodeString`
+#[cfg(feature = "alloc")]
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for UnicodeString {}
+// SAFETY: `raw` only points into `buffer`, which is only mutated through
+// `&mut UnicodeString`
+#[cfg(feature = "alloc")]
+unsafe impl Sync for UnicodeString {}
+
+#[cfg(feature = "alloc")]
+impl UnicodeString {
+    /// Create an empty string, without allocating
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            raw: UNICODE_STRING {
+                Length: 0,
+                MaximumLength: 0,
+                Buffer: core::ptr::null_mut(),
+            },
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Create a string from UTF-16 code units
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `utf16` is longer than
+    /// [`UNICODE_STRING_MAX_LENGTH`] (`STATUS_INVALID_PARAMETER`), or the
+    /// buffer cannot be allocated (`STATUS_INSUFFICIENT_RESOURCES`).
+    pub fn from_utf16(utf16: &[u16]) -> NtResult<Self> {
+        let mut unicode_string = Self::new();
+        unicode_string.push_utf16(utf16.iter().copied(), utf16.len())?;
+        Ok(unicode_string)
+    }
+
+    /// Append `string` to the end of this string
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the resulting string would be
+    /// longer than [`UNICODE_STRING_MAX_LENGTH`] (`STATUS_INVALID_PARAMETER`),
+    /// or the buffer cannot be grown (`STATUS_INSUFFICIENT_RESOURCES`). The
+    /// string is left unchanged on error.
+    pub fn push_str(&mut self, string: &str) -> NtResult<()> {
+        self.push_utf16(string.encode_utf16(), string.encode_utf16().count())
+    }
+
+    /// Append `unicode_str` to the end of this string
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the resulting string would be
+    /// longer than [`UNICODE_STRING_MAX_LENGTH`] (`STATUS_INVALID_PARAMETER`),
+    /// or the buffer cannot be grown (`STATUS_INSUFFICIENT_RESOURCES`). The
+    /// string is left unchanged on error.
+    pub fn push(&mut self, unicode_str: &UnicodeStr) -> NtResult<()> {
+        let utf16 = unicode_str.as_utf16();
+        self.push_utf16(utf16.iter().copied(), utf16.len())
+    }
+
+    /// Returns the string as a borrowed [`UnicodeStr`]
+    #[must_use]
+    pub const fn as_unicode_str(&self) -> &UnicodeStr {
+        // SAFETY: `raw` is a valid `UNICODE_STRING` whose buffer is `buffer`, which
+        // cannot be mutated while `self` is borrowed
+        unsafe { UnicodeStr::from_raw(&self.raw) }
+    }
+
+    /// Returns a raw pointer to the underlying [`UNICODE_STRING`]
+    #[must_use]
+    pub const fn as_raw(&self) -> PCUNICODE_STRING {
+        core::ptr::from_ref(&self.raw)
+    }
+
+    fn push_utf16(&mut self, utf16: impl Iterator<Item = u16>, length: usize) -> NtResult<()> {
+        if self.buffer.len() + length > UNICODE_STRING_MAX_LENGTH {
+            return Err(STATUS_INVALID_PARAMETER.into());
+        }
+        if self.buffer.try_reserve(length).is_err() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+        }
+
+        self.buffer.extend(utf16);
+        self.update_raw();
+        Ok(())
+    }
+
+    /// Point `raw` at the current contents of `buffer`
+    #[allow(clippy::cast_possible_truncation)]
+    fn update_raw(&mut self) {
+        let capacity = self.buffer.capacity().min(UNICODE_STRING_MAX_LENGTH);
+        // Both lengths are at most `UNICODE_STRING_MAX_LENGTH` code units, so they fit
+        // in a `USHORT` number of bytes
+        self.raw = UNICODE_STRING {
+            Length: (self.buffer.len() * core::mem::size_of::<u16>()) as u16,
+            MaximumLength: (capacity * core::mem::size_of::<u16>()) as u16,
+            Buffer: self.buffer.as_mut_ptr(),
+        };
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for UnicodeString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for UnicodeString {
+    type Target = UnicodeStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_unicode_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<UnicodeStr> for UnicodeString {
+    fn as_ref(&self) -> &UnicodeStr {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Borrow<UnicodeStr> for UnicodeString {
+    fn borrow(&self) -> &UnicodeStr {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&str> for UnicodeString {
+    type Error = crate::NtStatus;
+
+    fn try_from(string: &str) -> NtResult<Self> {
+        let mut unicode_string = Self::new();
+        unicode_string.push_str(string)?;
+        Ok(unicode_string)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&String> for UnicodeString {
+    type Error = crate::NtStatus;
+
+    fn try_from(string: &String) -> NtResult<Self> {
+        Self::try_from(string.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&UnicodeStr> for UnicodeString {
+    type Error = crate::NtStatus;
+
+    fn try_from(unicode_str: &UnicodeStr) -> NtResult<Self> {
+        Self::from_utf16(unicode_str.as_utf16())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&UnicodeString> for String {
+    type Error = FromUtf16Error;
+
+    fn try_from(unicode_string: &UnicodeString) -> Result<Self, Self::Error> {
+        Self::try_from(unicode_string.as_unicode_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for UnicodeString {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.push_str(string).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartialEq for UnicodeString {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Eq for UnicodeString {}
+
+#[cfg(feature = "alloc")]
+impl PartialEq<UnicodeStr> for UnicodeString {
+    fn eq(&self, other: &UnicodeStr) -> bool {
+        **self == *other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartialEq<str> for UnicodeString {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartialEq<&str> for UnicodeString {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartialOrd for UnicodeString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Ord for UnicodeString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Hash for UnicodeString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for UnicodeString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for UnicodeString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A borrowed [`ANSI_STRING`].
+///
+/// This is a view of a counted 8-bit string owned by someone else. The string
+/// is not required to be valid UTF-8 or nul-terminated.
+#[repr(transparent)]
+pub struct AnsiStr(ANSI_STRING);
+
+// SAFETY: An `AnsiStr` is only ever accessed through shared references, which
+// only read the string
+unsafe impl Sync for AnsiStr {}
+
+impl AnsiStr {
+    /// Borrow the [`ANSI_STRING`] pointed to by `ansi_string`
+    ///
+    /// # Safety
+    ///
+    /// `ansi_string` must point to a valid [`ANSI_STRING`], whose `Buffer` is
+    /// either null or valid for reads of `Length` bytes. Neither the
+    /// [`ANSI_STRING`] nor its buffer may be mutated for the lifetime `'a`.
+    #[must_use]
+    pub const unsafe fn from_raw<'a>(ansi_string: *const ANSI_STRING) -> &'a Self {
+        // SAFETY: `AnsiStr` is `repr(transparent)` over `ANSI_STRING`, and the caller
+        // guarantees that `ansi_string` is valid for `'a`
+        unsafe { &*ansi_string.cast::<Self>() }
+    }
+
+    /// Returns a raw pointer to the underlying [`ANSI_STRING`]
+    #[must_use]
+    pub const fn as_raw(&self) -> *const ANSI_STRING {
+        core::ptr::from_ref(&self.0)
+    }
+
+    /// Returns the bytes of the string
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.0.Buffer.is_null() {
+            return &[];
+        }
+
+        // SAFETY: `Buffer` is non-null, valid for reads of `Length` bytes and not
+        // mutated for the lifetime of `self`, as guaranteed by `AnsiStr::from_raw`
+        unsafe { core::slice::from_raw_parts(self.0.Buffer.cast::<u8>(), self.len()) }
+    }
+
+    /// Returns the length of the string, in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        usize::from(self.0.Length)
+    }
+
+    /// Returns whether the string is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the string is equal to `other`, ignoring ASCII case
+    #[must_use]
+    pub fn eq_ignore_ascii_case(&self, other: &Self) -> bool {
+        self.as_bytes().eq_ignore_ascii_case(other.as_bytes())
+    }
+}
+
+impl PartialEq for AnsiStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for AnsiStr {}
+
+impl PartialEq<str> for AnsiStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for AnsiStr {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialOrd for AnsiStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AnsiStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Hash for AnsiStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl fmt::Display for AnsiStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.as_bytes().utf8_chunks() {
+            f.write_str(chunk.valid())?;
+            if !chunk.invalid().is_empty() {
+                f.write_char(char::REPLACEMENT_CHARACTER)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AnsiStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for byte in self.as_bytes() {
+            for c in byte.escape_ascii() {
+                f.write_char(char::from(c))?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// An owned [`ANSI_STRING`], whose buffer is allocated with the global
+/// allocator (ex. `wdk_alloc::WdkAllocator` in kernel-mode drivers).
+///
+/// [`AnsiString`] dereferences to [`AnsiStr`]. Since the length of an
+/// [`ANSI_STRING`] is limited to [`ANSI_STRING_MAX_LENGTH`] bytes, and
+/// allocation failures must be handled in drivers, all functions that grow the
+/// string are fallible.
+#[cfg(feature = "alloc")]
+pub struct AnsiString {
+    raw: ANSI_STRING,
+    buffer: Vec<u8>,
+}
+
+// SAFETY: `raw` only points into `buffer`, which is owned by the `AnsiString`
+#[cfg(feature = "alloc")]
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for AnsiString {}
+// SAFETY: `raw` only points into `buffer`, which is only mutated through
+// `&mut AnsiString`
+#[cfg(feature = "alloc")]
+unsafe impl Sync for AnsiString {}
+
+#[cfg(feature = "alloc")]
+impl AnsiString {
+    /// Create an empty string, without allocating
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            raw: ANSI_STRING {
+                Length: 0,
+                MaximumLength: 0,
+                Buffer: core::ptr::null_mut(),
+            },
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Append `bytes` to the end of this string
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the resulting string would be
+    /// longer than [`ANSI_STRING_MAX_LENGTH`] (`STATUS_INVALID_PARAMETER`), or
+    /// the buffer cannot be grown (`STATUS_INSUFFICIENT_RESOURCES`). The string
+    /// is left unchanged on error.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> NtResult<()> {
+        if self.buffer.len() + bytes.len() > ANSI_STRING_MAX_LENGTH {
+            return Err(STATUS_INVALID_PARAMETER.into());
+        }
+        if self.buffer.try_reserve(bytes.len()).is_err() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+        }
+
+        self.buffer.extend_from_slice(bytes);
+        // Both lengths are at most `ANSI_STRING_MAX_LENGTH`, so they fit in a `USHORT`
+        self.raw = ANSI_STRING {
+            Length: self.buffer.len() as u16,
+            MaximumLength: self.buffer.capacity().min(ANSI_STRING_MAX_LENGTH) as u16,
+            Buffer: self.buffer.as_mut_ptr().cast(),
+        };
+        Ok(())
+    }
+
+    /// Returns the string as a borrowed [`AnsiStr`]
+    #[must_use]
+    pub const fn as_ansi_str(&self) -> &AnsiStr {
+        // SAFETY: `raw` is a valid `ANSI_STRING` whose buffer is `buffer`, which
+        // cannot be mutated while `self` is borrowed
+        unsafe { AnsiStr::from_raw(&self.raw) }
+    }
+
+    /// Returns a raw pointer to the underlying [`ANSI_STRING`]
+    #[must_use]
+    pub const fn as_raw(&self) -> *const ANSI_STRING {
+        core::ptr::from_ref(&self.raw)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for AnsiString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for AnsiString {
+    type Target = AnsiStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_ansi_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&[u8]> for AnsiString {
+    type Error = crate::NtStatus;
+
+    fn try_from(bytes: &[u8]) -> NtResult<Self> {
+        let mut ansi_string = Self::new();
+        ansi_string.push_bytes(bytes)?;
+        Ok(ansi_string)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&str> for AnsiString {
+    type Error = crate::NtStatus;
+
+    fn try_from(string: &str) -> NtResult<Self> {
+        Self::try_from(string.as_bytes())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for AnsiString {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.push_bytes(string.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PartialEq for AnsiString {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Eq for AnsiString {}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for AnsiString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for AnsiString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
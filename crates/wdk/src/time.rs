@@ -0,0 +1,406 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Kernel time and intervals.
+//!
+//! The WDK expresses time as a signed count of 100-nanosecond intervals, where
+//! negative values are relative to the current time and positive values are
+//! absolute system times. This module provides typed equivalents:
+//! * Relative intervals are [`Duration`]s.
+//! * Absolute times are [`SystemTime`]s, which count from January 1, 1601
+//!   (UTC).
+//! * Elapsed time is measured with [`Instant`]s, which are monotonic.
+//! * Due times passed to APIs such as `WdfTimerStart` and
+//!   `KeDelayExecutionThread` are [`Timeout`]s.
+
+use core::{
+    ops::{Add, AddAssign, Sub, SubAssign},
+    time::Duration,
+};
+
+/// The number of nanoseconds in one of the 100-nanosecond intervals used by
+/// the WDK
+const NANOSECONDS_PER_INTERVAL: u128 = 100;
+
+/// The number of 100-nanosecond intervals in a second
+const INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// When a wait or timer should expire.
+///
+/// This is the safe equivalent of the `LONGLONG` due times passed to WDK APIs
+/// such as `WdfTimerStart`, whose sign distinguishes relative from absolute
+/// times.
+///
+/// # Examples
+///
+/// ```rust
+/// use core::time::Duration;
+///
+/// use wdk::time::Timeout;
+///
+/// let timeout = Timeout::from(Duration::from_millis(10));
+/// assert_eq!(timeout.as_raw(), -100_000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Timeout {
+    /// Expire once the interval has elapsed. Relative timeouts are not
+    /// affected by changes to the system time.
+    Relative(Duration),
+    /// Expire at an absolute system time. Absolute timeouts track changes to
+    /// the system time.
+    Absolute(SystemTime),
+}
+
+/// A point in time, as measured by the system clock.
+///
+/// This is a count of 100-nanosecond intervals since January 1, 1601 (UTC),
+/// which is the format of the system time used throughout the WDK (ex.
+/// `KeQuerySystemTimePrecise`). Unlike [`Instant`], the system time is not
+/// monotonic, since it can be changed by the user or synchronized with a time
+/// server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime(u64);
+
+/// A measurement of a monotonically nondecreasing clock, for measuring
+/// elapsed time.
+///
+/// Instants are obtained from the performance counter via
+/// `KeQueryPerformanceCounter`, and are only meaningful relative to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Timeout {
+    /// Convert a raw due time, in 100-nanosecond intervals, where negative
+    /// values are relative and positive values are absolute
+    #[must_use]
+    pub const fn from_raw(due_time: i64) -> Self {
+        if due_time < 0 {
+            Self::Relative(intervals_to_duration(due_time.unsigned_abs()))
+        } else {
+            Self::Absolute(SystemTime::from_intervals(due_time.unsigned_abs()))
+        }
+    }
+
+    /// Returns the raw due time, in 100-nanosecond intervals, where negative
+    /// values are relative and positive values are absolute.
+    ///
+    /// Relative intervals are rounded up to the next 100 nanoseconds, and both
+    /// kinds of timeouts saturate at the largest representable value.
+    #[must_use]
+    pub fn as_raw(self) -> i64 {
+        match self {
+            Self::Relative(duration) => duration_to_intervals(duration)
+                .and_then(|intervals| i64::try_from(intervals).ok())
+                .map_or(i64::MIN, |intervals| -intervals),
+            Self::Absolute(system_time) => {
+                i64::try_from(system_time.as_intervals()).unwrap_or(i64::MAX)
+            }
+        }
+    }
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Self {
+        Self::Relative(duration)
+    }
+}
+
+impl From<SystemTime> for Timeout {
+    fn from(system_time: SystemTime) -> Self {
+        Self::Absolute(system_time)
+    }
+}
+
+impl SystemTime {
+    /// The Unix epoch, January 1, 1970 (UTC)
+    pub const UNIX_EPOCH: Self = Self(11_644_473_600 * INTERVALS_PER_SECOND);
+
+    /// Returns the current system time, via `KeQuerySystemTimePrecise`
+    #[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+    #[must_use]
+    pub fn now() -> Self {
+        let mut current_time = wdk_sys::LARGE_INTEGER::default();
+        // SAFETY: `current_time` is a valid `LARGE_INTEGER` to write the system time
+        // to, and `KeQuerySystemTimePrecise` can be called at any IRQL
+        unsafe {
+            wdk_sys::ntddk::KeQuerySystemTimePrecise(&mut current_time);
+        }
+
+        // SAFETY: `QuadPart` covers the whole `LARGE_INTEGER`, so it is always
+        // initialized
+        let intervals = unsafe { current_time.QuadPart };
+        // The system time is never before 1601
+        Self(intervals.unsigned_abs())
+    }
+
+    /// Create a system time from a count of 100-nanosecond intervals since
+    /// January 1, 1601 (UTC)
+    #[must_use]
+    pub const fn from_intervals(intervals: u64) -> Self {
+        Self(intervals)
+    }
+
+    /// Returns the count of 100-nanosecond intervals since January 1, 1601
+    /// (UTC)
+    #[must_use]
+    pub const fn as_intervals(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or [`None`] if
+    /// `earlier` is later than `self`
+    #[must_use]
+    pub const fn checked_duration_since(self, earlier: Self) -> Option<Duration> {
+        match self.0.checked_sub(earlier.0) {
+            Some(intervals) => Some(intervals_to_duration(intervals)),
+            None => None,
+        }
+    }
+
+    /// Returns `self + duration`, or [`None`] if it overflows
+    #[must_use]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        duration_to_intervals(duration)
+            .and_then(|intervals| self.0.checked_add(intervals))
+            .map(Self)
+    }
+
+    /// Returns `self - duration`, or [`None`] if it would be before January 1,
+    /// 1601
+    #[must_use]
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        duration_to_intervals(duration)
+            .and_then(|intervals| self.0.checked_sub(intervals))
+            .map(Self)
+    }
+}
+
+impl Instant {
+    /// Returns the current instant, via `KeQueryPerformanceCounter`
+    #[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+    #[must_use]
+    pub fn now() -> Self {
+        let mut frequency = wdk_sys::LARGE_INTEGER::default();
+        let counter;
+        // SAFETY: `frequency` is a valid `LARGE_INTEGER` to write the counter
+        // frequency to, and `KeQueryPerformanceCounter` can be called at any IRQL
+        unsafe {
+            counter = wdk_sys::ntddk::KeQueryPerformanceCounter(&mut frequency);
+        }
+
+        // SAFETY: `QuadPart` covers the whole `LARGE_INTEGER`, so it is always
+        // initialized
+        let counter = unsafe { counter.QuadPart }.unsigned_abs();
+        // SAFETY: `QuadPart` covers the whole `LARGE_INTEGER`, so it is always
+        // initialized
+        let frequency = unsafe { frequency.QuadPart }.unsigned_abs();
+        // The counter ticks at `frequency` Hz, and converting it to 100-nanosecond
+        // intervals in 128 bits cannot overflow
+        let intervals =
+            u128::from(counter) * u128::from(INTERVALS_PER_SECOND) / u128::from(frequency.max(1));
+        Self(u64::try_from(intervals).unwrap_or(u64::MAX))
+    }
+
+    /// Returns the time elapsed since this instant
+    #[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+    #[must_use]
+    pub fn elapsed(self) -> Duration {
+        Self::now().duration_since(self)
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if `earlier`
+    /// is later than `self`
+    #[must_use]
+    pub const fn duration_since(self, earlier: Self) -> Duration {
+        intervals_to_duration(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or [`None`] if
+    /// `earlier` is later than `self`
+    #[must_use]
+    pub const fn checked_duration_since(self, earlier: Self) -> Option<Duration> {
+        match self.0.checked_sub(earlier.0) {
+            Some(intervals) => Some(intervals_to_duration(intervals)),
+            None => None,
+        }
+    }
+
+    /// Returns `self + duration`, or [`None`] if it overflows
+    #[must_use]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        duration_to_intervals(duration)
+            .and_then(|intervals| self.0.checked_add(intervals))
+            .map(Self)
+    }
+
+    /// Returns `self - duration`, or [`None`] if it underflows
+    #[must_use]
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        duration_to_intervals(duration)
+            .and_then(|intervals| self.0.checked_sub(intervals))
+            .map(Self)
+    }
+}
+
+macro_rules! impl_time_arithmetic {
+    ($time:ty) => {
+        impl Add<Duration> for $time {
+            type Output = Self;
+
+            /// # Panics
+            ///
+            /// Panics if the result overflows. See `checked_add` for a version
+            /// without panic.
+            fn add(self, duration: Duration) -> Self {
+                self.checked_add(duration)
+                    .expect("overflow when adding duration to time")
+            }
+        }
+
+        impl AddAssign<Duration> for $time {
+            fn add_assign(&mut self, duration: Duration) {
+                *self = *self + duration;
+            }
+        }
+
+        impl Sub<Duration> for $time {
+            type Output = Self;
+
+            /// # Panics
+            ///
+            /// Panics if the result underflows. See `checked_sub` for a version
+            /// without panic.
+            fn sub(self, duration: Duration) -> Self {
+                self.checked_sub(duration)
+                    .expect("overflow when subtracting duration from time")
+            }
+        }
+
+        impl SubAssign<Duration> for $time {
+            fn sub_assign(&mut self, duration: Duration) {
+                *self = *self - duration;
+            }
+        }
+    };
+}
+
+impl_time_arithmetic!(SystemTime);
+impl_time_arithmetic!(Instant);
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if `earlier`
+    /// is later than `self`
+    fn sub(self, earlier: Self) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Put the current thread to sleep for at least `duration`, via
+/// `KeDelayExecutionThread`.
+///
+/// This must be called at `PASSIVE_LEVEL`, which is asserted in debug builds.
+/// The sleep is not alertable, and its precision is limited by the system
+/// clock tick.
+#[cfg(any(driver_model__driver_type = "WDM", driver_model__driver_type = "KMDF"))]
+pub fn sleep(duration: Duration) {
+    crate::debug_assert_passive_level();
+
+    let mut interval = wdk_sys::LARGE_INTEGER {
+        QuadPart: Timeout::Relative(duration).as_raw(),
+    };
+    let nt_status;
+    // SAFETY: `interval` is a valid relative interval, and the caller is running at
+    // `PASSIVE_LEVEL`
+    unsafe {
+        // clippy::cast_possible_truncation: `KernelMode` is 0
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            nt_status = wdk_sys::ntddk::KeDelayExecutionThread(
+                crate::enum_value(wdk_sys::_MODE::KernelMode) as wdk_sys::KPROCESSOR_MODE,
+                u8::from(false),
+                &mut interval,
+            );
+        }
+    }
+    debug_assert!(
+        crate::nt_success(nt_status),
+        "non-alertable kernel-mode waits always succeed"
+    );
+}
+
+/// Convert a count of 100-nanosecond intervals to a [`Duration`]
+#[allow(clippy::cast_possible_truncation)]
+const fn intervals_to_duration(intervals: u64) -> Duration {
+    // The remainder is less than one second, so it always fits in a u32
+    let nanoseconds = (intervals % INTERVALS_PER_SECOND * 100) as u32;
+    Duration::new(intervals / INTERVALS_PER_SECOND, nanoseconds)
+}
+
+/// Convert `duration` to a count of 100-nanosecond intervals, rounding up, or
+/// [`None`] if it overflows
+fn duration_to_intervals(duration: Duration) -> Option<u64> {
+    u64::try_from(duration.as_nanos().div_ceil(NANOSECONDS_PER_INTERVAL)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_timeout_is_negative() {
+        assert_eq!(
+            Timeout::Relative(Duration::from_secs(1)).as_raw(),
+            -10_000_000
+        );
+        assert_eq!(Timeout::Relative(Duration::ZERO).as_raw(), 0);
+        assert_eq!(
+            Timeout::from_raw(-10_000_000),
+            Timeout::Relative(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn absolute_timeout_is_positive() {
+        assert_eq!(
+            Timeout::Absolute(SystemTime::from_intervals(42)).as_raw(),
+            42
+        );
+        assert_eq!(
+            Timeout::from_raw(42),
+            Timeout::Absolute(SystemTime::from_intervals(42))
+        );
+        assert_eq!(
+            Timeout::from_raw(0),
+            Timeout::Absolute(SystemTime::from_intervals(0))
+        );
+    }
+
+    #[test]
+    fn relative_timeout_rounds_up() {
+        assert_eq!(Timeout::Relative(Duration::from_nanos(1)).as_raw(), -1);
+        assert_eq!(Timeout::Relative(Duration::from_nanos(100)).as_raw(), -1);
+        assert_eq!(Timeout::Relative(Duration::from_nanos(101)).as_raw(), -2);
+    }
+
+    #[test]
+    fn timeout_saturates() {
+        assert_eq!(Timeout::Relative(Duration::MAX).as_raw(), i64::MIN);
+        assert_eq!(
+            Timeout::Absolute(SystemTime::from_intervals(u64::MAX)).as_raw(),
+            i64::MAX
+        );
+        assert_eq!(
+            Timeout::from_raw(i64::MIN),
+            Timeout::Relative(intervals_to_duration(i64::MIN.unsigned_abs()))
+        );
+    }
+
+    #[test]
+    fn raw_timeout_round_trips() {
+        for due_time in [i64::MIN + 1, -10_000_000, -1, 0, 1, 10_000_000, i64::MAX] {
+            assert_eq!(Timeout::from_raw(due_time).as_raw(), due_time);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Support for objects whose callback is stored in their own context space
+//! (`Timer`, `WorkItem` and `Dpc`).
+//!
+//! The `create` method of these objects stores the callback before returning
+//! the object's wrapper, and the callback cannot run until the object is
+//! started or enqueued through that wrapper. The callback is therefore always
+//! present when WDF invokes it.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(all(feature = "alloc", driver_model__driver_type = "KMDF"))]
+use super::{Dpc, DpcCallback};
+use super::{ObjectContext, WdfHandle, WdfObject};
+#[cfg(feature = "alloc")]
+use super::{ObjectContextTypeInfo, Timer, TimerCallback, WorkItem, WorkItemCallback};
+
+/// Returns the `T` callback stored in the context space of `object`, for the
+/// WDF event that invokes it. This only returns [`None`] if the invariant
+/// documented in this module is broken, in which case the event is ignored.
+pub(super) fn stored_callback<T: ObjectContext + Sync, H: WdfHandle>(
+    object: &WdfObject<H>,
+) -> Option<&T> {
+    object.context::<T>()
+}
+
+/// The callback of objects of type `O` created with a `create_with_closure`
+/// method (ex.
+/// [`TimerBuilder::create_with_closure`](super::TimerBuilder::create_with_closure))
+#[cfg(feature = "alloc")]
+pub(super) struct ClosureCallback<O> {
+    closure: Box<dyn Fn(&O) + Send + Sync>,
+}
+
+#[cfg(feature = "alloc")]
+impl<O> ClosureCallback<O> {
+    pub(super) fn new(closure: impl Fn(&O) + Send + Sync + 'static) -> Self {
+        Self {
+            closure: Box::new(closure),
+        }
+    }
+}
+
+/// Implements [`ObjectContext`] for `ClosureCallback<$object>`. The
+/// `ObjectContext` derive macro rejects generic types, since each
+/// instantiation needs its own `ObjectContextTypeInfo`.
+#[cfg(feature = "alloc")]
+macro_rules! impl_closure_callback_object_context {
+    ($object:ty) => {
+        // SAFETY: `TYPE_INFO` is constructed for `ClosureCallback<$object>` and is only
+        // reachable through this impl
+        unsafe impl ObjectContext for ClosureCallback<$object> {
+            fn type_info() -> &'static ObjectContextTypeInfo {
+                static TYPE_INFO: ObjectContextTypeInfo = ObjectContextTypeInfo::new::<
+                    ClosureCallback<$object>,
+                >(c"ClosureCallback", &TYPE_INFO);
+                &TYPE_INFO
+            }
+        }
+    };
+}
+
+#[cfg(feature = "alloc")]
+impl_closure_callback_object_context!(Timer);
+
+#[cfg(feature = "alloc")]
+impl TimerCallback for ClosureCallback<Timer> {
+    fn expired(&self, timer: &Timer) {
+        (self.closure)(timer);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_closure_callback_object_context!(WorkItem);
+
+#[cfg(feature = "alloc")]
+impl WorkItemCallback for ClosureCallback<WorkItem> {
+    fn run(&self, work_item: &WorkItem) {
+        (self.closure)(work_item);
+    }
+}
+
+#[cfg(all(feature = "alloc", driver_model__driver_type = "KMDF"))]
+impl_closure_callback_object_context!(Dpc);
+
+#[cfg(all(feature = "alloc", driver_model__driver_type = "KMDF"))]
+impl DpcCallback for ClosureCallback<Dpc> {
+    fn run(&self, dpc: &Dpc) {
+        (self.closure)(dpc);
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    cell::UnsafeCell,
+    ffi::CStr,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub use wdk_sys::__proc_macros::ObjectContext;
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    MEMORY_ALLOCATION_ALIGNMENT,
+    PVOID,
+    WDFOBJECT,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_CONTEXT_TYPE_INFO,
+};
+
+use super::{object_attributes, wdf_struct_size, WdfHandle, WdfObject};
+use crate::{nt_success, NtResult};
+
+/// A Rust type that can be stored in the context space of a WDF object.
+///
+/// This trait should be implemented via `#[derive(ObjectContext)]`, which
+/// declares the [`ObjectContextTypeInfo`] that WDF uses to identify the
+/// context type. This is the equivalent of the `WDF_DECLARE_CONTEXT_TYPE`
+/// macro in the WDF headers.
+///
+/// WDF zero-initializes context space, so a context value must be stored with
+/// [`WdfObject::init_context`] or [`WdfObject::allocate_context`] before it can
+/// be accessed. The value is dropped from the object's `EvtCleanupCallback`.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::wdf::ObjectContext;
+///
+/// #[derive(ObjectContext)]
+/// struct DeviceContext {
+///     open_handle_count: u32,
+/// }
+///
+/// let mut attributes = DeviceContext::object_attributes();
+/// ```
+///
+/// # Safety
+///
+/// [`ObjectContext::type_info`] must always return the same
+/// [`ObjectContextTypeInfo`], which must have been constructed for `Self` and
+/// must not be used by any other type.
+pub unsafe trait ObjectContext: Sized + 'static {
+    /// Returns the WDF type information for this context type
+    fn type_info() -> &'static ObjectContextTypeInfo;
+
+    /// Set `Self` as the context type in `attributes`, and register an
+    /// `EvtCleanupCallback` that drops the context value. This is the
+    /// equivalent of the `WDF_OBJECT_ATTRIBUTES_SET_CONTEXT_TYPE` macro in the
+    /// WDF headers.
+    ///
+    /// `attributes` must not already have an `EvtCleanupCallback` set, since
+    /// it is needed to drop the context value.
+    fn set_context_type(attributes: &mut WDF_OBJECT_ATTRIBUTES) {
+        debug_assert!(
+            attributes.EvtCleanupCallback.is_none(),
+            "EvtCleanupCallback is required to drop the object context"
+        );
+        attributes.ContextTypeInfo = Self::type_info().unique_type();
+        attributes.EvtCleanupCallback = Some(cleanup_context::<Self>);
+    }
+
+    /// Returns a [`WDF_OBJECT_ATTRIBUTES`] with `Self` set as its context
+    /// type. This is the equivalent of the
+    /// `WDF_OBJECT_ATTRIBUTES_INIT_CONTEXT_TYPE` macro in the WDF headers.
+    #[must_use]
+    fn object_attributes() -> WDF_OBJECT_ATTRIBUTES {
+        let mut attributes = object_attributes();
+        Self::set_context_type(&mut attributes);
+        attributes
+    }
+}
+
+/// Type information that WDF uses to identify an [`ObjectContext`] type.
+///
+/// This is the equivalent of the static `WDF_OBJECT_CONTEXT_TYPE_INFO` that
+/// the `WDF_DECLARE_CONTEXT_TYPE` macro in the WDF headers declares, and
+/// should only be constructed by `#[derive(ObjectContext)]`.
+#[repr(transparent)]
+pub struct ObjectContextTypeInfo(WDF_OBJECT_CONTEXT_TYPE_INFO);
+
+// SAFETY: `ObjectContextTypeInfo` is never mutated after construction, and all
+// of its pointers point to `'static` data.
+unsafe impl Sync for ObjectContextTypeInfo {}
+
+impl ObjectContextTypeInfo {
+    /// Construct the type information for `T`. `unique_type` must be a
+    /// reference to the static that is initialized with the returned value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` requires a greater alignment than WDF guarantees for
+    /// context space. Since this is evaluated when initializing a static,
+    /// this results in a compilation error.
+    #[must_use]
+    pub const fn new<T: ObjectContext>(name: &'static CStr, unique_type: &'static Self) -> Self {
+        assert!(
+            core::mem::align_of::<ContextSpace<T>>() <= MEMORY_ALLOCATION_ALIGNMENT as usize,
+            "ObjectContext types cannot require greater alignment than MEMORY_ALLOCATION_ALIGNMENT"
+        );
+
+        Self(WDF_OBJECT_CONTEXT_TYPE_INFO {
+            Size: wdf_struct_size::<WDF_OBJECT_CONTEXT_TYPE_INFO>(),
+            ContextName: name.as_ptr().cast_mut(),
+            ContextSize: core::mem::size_of::<ContextSpace<T>>(),
+            UniqueType: core::ptr::from_ref(&unique_type.0),
+            EvtDriverGetUniqueContextType: None,
+        })
+    }
+
+    const fn unique_type(&self) -> *const WDF_OBJECT_CONTEXT_TYPE_INFO {
+        self.0.UniqueType
+    }
+}
+
+/// The layout of the context space allocated by WDF for an [`ObjectContext`]
+/// type. WDF zero-initializes context space, so `initialized` tracks whether
+/// `value` has been written yet.
+///
+/// WDF can invoke an object's callbacks, which read its context, as soon as the
+/// object is created (ex. a queue of a started device), which may be before
+/// its creator has stored the context value. `initialized` is atomic so that
+/// such a callback either observes the fully written `value` or no value at
+/// all, and [`ContextSpace::publish`] stores the value without requiring
+/// exclusive access to the context space.
+#[repr(C)]
+struct ContextSpace<T> {
+    initialized: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> ContextSpace<T> {
+    fn get(&self) -> Option<&T> {
+        if !self.initialized.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: `value` is only mutated through `&mut self` once `initialized` is
+        // set, and the `Acquire` load synchronizes with the `Release` store that set it
+        let value = unsafe { &*self.value.get() };
+        // SAFETY: `value` is always initialized when `initialized` is set
+        Some(unsafe { value.assume_init_ref() })
+    }
+
+    fn get_mut(&mut self) -> Option<&mut T> {
+        if !*self.initialized.get_mut() {
+            return None;
+        }
+        // SAFETY: `value` is always initialized when `initialized` is set
+        Some(unsafe { self.value.get_mut().assume_init_mut() })
+    }
+
+    fn set(&mut self, value: T) -> &mut T {
+        self.clear();
+        self.value.get_mut().write(value);
+        *self.initialized.get_mut() = true;
+        // SAFETY: `value` was just initialized
+        unsafe { self.value.get_mut().assume_init_mut() }
+    }
+
+    /// Store `value` in a context space that has not been initialized yet,
+    /// while other threads may concurrently call [`ContextSpace::get`]
+    ///
+    /// # Safety
+    ///
+    /// The context space must not have been initialized, and no other thread
+    /// may store a value in it concurrently
+    unsafe fn publish(&self, value: T) {
+        debug_assert!(
+            !self.initialized.load(Ordering::Relaxed),
+            "context space should not be initialized yet"
+        );
+        // SAFETY: The caller guarantees that this is the only writer, and readers
+        // never access `value` until `initialized` is set below
+        unsafe { (*self.value.get()).write(value) };
+        self.initialized.store(true, Ordering::Release);
+    }
+
+    fn clear(&mut self) {
+        if *self.initialized.get_mut() {
+            *self.initialized.get_mut() = false;
+            // SAFETY: `value` is always initialized when `initialized` is set, and it
+            // is never read again until it is re-initialized by `set`
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<H: WdfHandle> WdfObject<H> {
+    /// Returns a reference to the `T` context of this object, or [`None`] if
+    /// the object has no `T` context space, or it has not been initialized.
+    /// This is the equivalent of the accessor function declared by the
+    /// `WDF_DECLARE_CONTEXT_TYPE` macro in the WDF headers.
+    #[must_use]
+    pub fn context<T: ObjectContext + Sync>(&self) -> Option<&T> {
+        // SAFETY: `self` keeps the object and its context space alive for the lifetime
+        // of the returned reference
+        unsafe { context_space::<T>(self.as_raw().as_wdf_object()).as_ref() }
+            .and_then(ContextSpace::get)
+    }
+
+    /// Returns a mutable reference to the `T` context of this object, or
+    /// [`None`] if the object has no `T` context space, or it has not been
+    /// initialized.
+    ///
+    /// # Safety
+    ///
+    /// No callback of this object that accesses its `T` context may run
+    /// while the returned reference exists (ex. the object must not have been
+    /// started, enqueued or connected yet). Callbacks access the context
+    /// through their own handle to the object, so `&mut self` alone does not
+    /// guarantee exclusive access to it.
+    #[must_use]
+    pub unsafe fn context_mut<T: ObjectContext>(&mut self) -> Option<&mut T> {
+        // SAFETY: `self` keeps the object and its context space alive for the lifetime
+        // of the returned reference. `&mut self` guarantees no other reference to the
+        // context is obtained through this `WdfObject` in the meantime, and the caller
+        // guarantees that none is obtained by the object's callbacks.
+        unsafe { context_space::<T>(self.as_raw().as_wdf_object()).as_mut() }
+            .and_then(ContextSpace::get_mut)
+    }
+
+    /// Store `value` in the `T` context space of this object, dropping any
+    /// previously stored value. The context space must have been declared when
+    /// creating the object, via [`ObjectContext::object_attributes`] or
+    /// [`ObjectContext::set_context_type`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `value` back if the object has no `T` context space.
+    ///
+    /// # Safety
+    ///
+    /// No callback of this object that accesses its `T` context may run
+    /// while the returned reference exists (ex. the object must not have been
+    /// started, enqueued or connected yet). Callbacks access the context
+    /// through their own handle to the object, so `&mut self` alone does not
+    /// guarantee exclusive access to it.
+    pub unsafe fn init_context<T: ObjectContext>(&mut self, value: T) -> Result<&mut T, T> {
+        // SAFETY: `self` keeps the object and its context space alive for the lifetime
+        // of the returned reference. `&mut self` guarantees no other reference to the
+        // context is obtained through this `WdfObject` in the meantime, and the caller
+        // guarantees that none is obtained by the object's callbacks.
+        match unsafe { context_space::<T>(self.as_raw().as_wdf_object()).as_mut() } {
+            Some(context_space) => Ok(context_space.set(value)),
+            None => Err(value),
+        }
+    }
+
+    /// Store `value` in the `T` context space of this object, which may
+    /// already be read by the object's callbacks on other threads. Callbacks
+    /// observe either the whole value, or no value at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns `value` back if the object has no `T` context space.
+    ///
+    /// # Safety
+    ///
+    /// No value may have been stored in the `T` context space yet, and no other
+    /// thread may store one concurrently
+    pub(super) unsafe fn publish_context<T: ObjectContext + Sync>(
+        &self,
+        value: T,
+    ) -> Result<(), T> {
+        // SAFETY: `self` keeps the object and its context space alive, and the
+        // context space is only accessed through shared references here
+        match unsafe { context_space::<T>(self.as_raw().as_wdf_object()).as_ref() } {
+            Some(context_space) => {
+                // SAFETY: The caller guarantees that the context space has not been
+                // initialized, and that there is no concurrent writer
+                unsafe { context_space.publish(value) };
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Allocate a new `T` context space for this object via
+    /// `WdfObjectAllocateContext` and store `value` in it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the
+    /// context, including when the object already has a `T` context. The error
+    /// variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfObjectAllocateContext Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfobject/nf-wdfobject-wdfobjectallocatecontext#return-value)
+    ///
+    /// # Safety
+    ///
+    /// No callback of this object that accesses its `T` context may run
+    /// while the returned reference exists (ex. the object must not have been
+    /// started, enqueued or connected yet). Callbacks access the context
+    /// through their own handle to the object, so `&mut self` alone does not
+    /// guarantee exclusive access to it.
+    pub unsafe fn allocate_context<T: ObjectContext>(&mut self, value: T) -> NtResult<&mut T> {
+        let mut attributes = T::object_attributes();
+        let mut context: PVOID = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The object is kept alive by `self`, and `attributes` is a valid
+        // `WDF_OBJECT_ATTRIBUTES` describing the context space for `T`.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfObjectAllocateContext,
+                self.as_raw().as_wdf_object(),
+                &mut attributes,
+                &mut context,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: WDF successfully allocated zero-initialized context space of
+        // `ContextSpace<T>`'s size, which lives as long as the object. The caller
+        // guarantees that the object's callbacks do not access it in the meantime.
+        let context_space = unsafe { &mut *context.cast::<ContextSpace<T>>() };
+        Ok(context_space.set(value))
+    }
+}
+
+/// Returns a reference to the `T` context of the object that `object` is a
+/// handle to, or [`None`] if it has no `T` context space, or it has not been
+/// initialized
+///
+/// # Safety
+///
+/// `object` must be a valid handle to a WDF object that remains alive for `'a`
+pub(super) unsafe fn object_context<'a, T: ObjectContext + Sync>(
+    object: WDFOBJECT,
+) -> Option<&'a T> {
+    // SAFETY: The caller guarantees that the object and its context space are alive
+    // for `'a`
+    unsafe { context_space::<T>(object).as_ref() }.and_then(ContextSpace::get)
+}
+
+/// Returns a pointer to the `T` context space of `object`, or a null pointer if
+/// `object` has no `T` context space
+fn context_space<T: ObjectContext>(object: WDFOBJECT) -> *mut ContextSpace<T> {
+    let context;
+    // SAFETY: `object` is a valid handle to a live WDF object, and
+    // `T::type_info()` is valid for the `'static` lifetime
+    unsafe {
+        context = call_unsafe_wdf_function_binding!(
+            WdfObjectGetTypedContextWorker,
+            object,
+            T::type_info().unique_type(),
+        );
+    }
+    context.cast()
+}
+
+/// `EvtCleanupCallback` registered by [`ObjectContext::set_context_type`] to
+/// drop the `T` context of an object before WDF frees its context space
+unsafe extern "C" fn cleanup_context<T: ObjectContext>(object: WDFOBJECT) {
+    // SAFETY: WDF calls `EvtCleanupCallback` exactly once, with a valid `object`,
+    // after which no driver callbacks can access the object's context
+    if let Some(context_space) = unsafe { context_space::<T>(object).as_mut() } {
+        context_space.clear();
+    }
+}
@@ -0,0 +1,609 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(driver_model__driver_type = "KMDF")]
+use wdk_sys::DEVICE_TYPE;
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_DEVICE_IO_TYPE,
+    _WDF_FILEOBJECT_CLASS,
+    _WDF_TRI_STATE,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    STATUS_SUCCESS,
+    WDFCMRESLIST,
+    WDFDEVICE,
+    WDFFILEOBJECT,
+    WDFREQUEST,
+    WDF_DEVICE_IO_TYPE,
+    WDF_FILEOBJECT_CONFIG,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_PNPPOWER_EVENT_CALLBACKS,
+    WDF_POWER_DEVICE_STATE,
+};
+
+use super::{wdf_struct_size, ObjectContext, Ownership, Request, WdfObject};
+use crate::{nt_success, string::UnicodeStr, Guid, NtResult};
+
+/// Callback invoked when the device enters its working (D0) power state. This
+/// is the equivalent of `EvtDeviceD0Entry`.
+pub type D0EntryFn = fn(&Device, WDF_POWER_DEVICE_STATE) -> NtResult<()>;
+
+/// Callback invoked when the device leaves its working (D0) power state. This
+/// is the equivalent of `EvtDeviceD0Exit`.
+pub type D0ExitFn = fn(&Device, WDF_POWER_DEVICE_STATE) -> NtResult<()>;
+
+/// Callback invoked with the raw and translated hardware resources assigned to
+/// the device. This is the equivalent of `EvtDevicePrepareHardware`.
+pub type PrepareHardwareFn = fn(&Device, WDFCMRESLIST, WDFCMRESLIST) -> NtResult<()>;
+
+/// Callback invoked when the device's hardware resources are no longer
+/// accessible. This is the equivalent of `EvtDeviceReleaseHardware`.
+pub type ReleaseHardwareFn = fn(&Device, WDFCMRESLIST) -> NtResult<()>;
+
+/// Callback invoked when the device has been unexpectedly removed. This is the
+/// equivalent of `EvtDeviceSurpriseRemoval`.
+pub type SurpriseRemovalFn = fn(&Device);
+
+/// Callback invoked when an application opens the device. The request must
+/// be completed by the callback. This is the equivalent of
+/// `EvtDeviceFileCreate`.
+pub type FileCreateFn = fn(&Device, Request, WDFFILEOBJECT);
+
+/// Callback invoked when the last handle to a file object is closed. This is
+/// the equivalent of `EvtFileCleanup`.
+pub type FileCleanupFn = fn(&Device, WDFFILEOBJECT);
+
+/// Callback invoked when all references to a file object have been released.
+/// This is the equivalent of `EvtFileClose`.
+pub type FileCloseFn = fn(&Device, WDFFILEOBJECT);
+
+/// The method used to access the data buffers of read, write and device
+/// control requests. This is the equivalent of `WDF_DEVICE_IO_TYPE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoType {
+    /// Data is copied to and from an intermediate system buffer
+    Buffered,
+    /// Data buffers are locked in memory and described by an MDL
+    Direct,
+    /// The driver accesses the requestor's buffers directly
+    Neither,
+}
+
+impl IoType {
+    const fn as_raw(self) -> WDF_DEVICE_IO_TYPE {
+        match self {
+            Self::Buffered => _WDF_DEVICE_IO_TYPE::WdfDeviceIoBuffered,
+            Self::Direct => _WDF_DEVICE_IO_TYPE::WdfDeviceIoDirect,
+            Self::Neither => _WDF_DEVICE_IO_TYPE::WdfDeviceIoNeither,
+        }
+    }
+}
+
+/// Plug and Play and power management callbacks of a [`Device`]. This is the
+/// equivalent of a subset of `WDF_PNPPOWER_EVENT_CALLBACKS`.
+#[derive(Clone, Copy, Default)]
+pub struct PnpPowerCallbacks {
+    /// `EvtDeviceD0Entry`
+    pub d0_entry: Option<D0EntryFn>,
+    /// `EvtDeviceD0Exit`
+    pub d0_exit: Option<D0ExitFn>,
+    /// `EvtDevicePrepareHardware`
+    pub prepare_hardware: Option<PrepareHardwareFn>,
+    /// `EvtDeviceReleaseHardware`
+    pub release_hardware: Option<ReleaseHardwareFn>,
+    /// `EvtDeviceSurpriseRemoval`
+    pub surprise_removal: Option<SurpriseRemovalFn>,
+}
+
+/// File object callbacks of a [`Device`]. This is the equivalent of
+/// `WDF_FILEOBJECT_CONFIG`.
+#[derive(Clone, Copy, Default)]
+pub struct FileObjectConfig {
+    /// `EvtDeviceFileCreate`
+    pub create: Option<FileCreateFn>,
+    /// `EvtFileCleanup`
+    pub cleanup: Option<FileCleanupFn>,
+    /// `EvtFileClose`
+    pub close: Option<FileCloseFn>,
+}
+
+/// The Rust callbacks of a [`Device`], stored in the context space of its
+/// framework device object so that they can be reached from WDF's callbacks
+#[derive(Default, ObjectContext)]
+struct DeviceCallbacks {
+    pnp_power: PnpPowerCallbacks,
+    file_object: FileObjectConfig,
+}
+
+impl DeviceCallbacks {
+    fn is_empty(&self) -> bool {
+        let PnpPowerCallbacks {
+            d0_entry,
+            d0_exit,
+            prepare_hardware,
+            release_hardware,
+            surprise_removal,
+        } = self.pnp_power;
+        let FileObjectConfig {
+            create,
+            cleanup,
+            close,
+        } = self.file_object;
+
+        d0_entry.is_none()
+            && d0_exit.is_none()
+            && prepare_hardware.is_none()
+            && release_hardware.is_none()
+            && surprise_removal.is_none()
+            && create.is_none()
+            && cleanup.is_none()
+            && close.is_none()
+    }
+}
+
+/// Initialization state of a framework device object, which is the equivalent
+/// of a `WDFDEVICE_INIT`.
+///
+/// A [`DeviceInit`] is handed to the `EvtDriverDeviceAdd` callback and is only
+/// valid for the duration of that callback. Calling [`DeviceInit::create`]
+/// consumes it, since WDF takes ownership of the `WDFDEVICE_INIT` when
+/// creating the device.
+pub struct DeviceInit<'a> {
+    wdf_init: PWDFDEVICE_INIT,
+    callbacks: DeviceCallbacks,
+    _marker: PhantomData<&'a mut PWDFDEVICE_INIT>,
+}
+
+impl DeviceInit<'_> {
+    /// Wrap the `WDFDEVICE_INIT` passed to `EvtDriverDeviceAdd`
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be a valid `WDFDEVICE_INIT` that has not been used to
+    /// create a device yet, and must not be used by the caller after the
+    /// returned [`DeviceInit`] is created
+    #[must_use]
+    pub unsafe fn from_raw(device_init: PWDFDEVICE_INIT) -> Self {
+        Self {
+            wdf_init: device_init,
+            callbacks: DeviceCallbacks::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the raw `WDFDEVICE_INIT`, to call `WdfDeviceInit*` functions
+    /// that have no safe wrapper
+    #[must_use]
+    pub const fn as_raw(&mut self) -> PWDFDEVICE_INIT {
+        self.wdf_init
+    }
+
+    /// Set the method used to access the data buffers of read and write
+    /// requests, via `WdfDeviceInitSetIoType`
+    #[must_use]
+    pub fn io_type(self, io_type: IoType) -> Self {
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetIoType,
+                self.wdf_init,
+                io_type.as_raw(),
+            );
+        }
+        self
+    }
+
+    /// Set whether only one application can open the device at a time, via
+    /// `WdfDeviceInitSetExclusive`
+    #[must_use]
+    pub fn exclusive(self, is_exclusive: bool) -> Self {
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetExclusive,
+                self.wdf_init,
+                u8::from(is_exclusive),
+            );
+        }
+        self
+    }
+
+    /// Set the device type (ex. `FILE_DEVICE_UNKNOWN`), via
+    /// `WdfDeviceInitSetDeviceType`
+    #[cfg(driver_model__driver_type = "KMDF")]
+    #[must_use]
+    pub fn device_type(self, device_type: DEVICE_TYPE) -> Self {
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetDeviceType,
+                self.wdf_init,
+                device_type,
+            );
+        }
+        self
+    }
+
+    /// Set the device characteristics (ex. `FILE_DEVICE_SECURE_OPEN`), via
+    /// `WdfDeviceInitSetCharacteristics`. If `or_in_values` is `true`, the
+    /// characteristics are added to the ones already set, instead of
+    /// replacing them.
+    #[cfg(driver_model__driver_type = "KMDF")]
+    #[must_use]
+    pub fn characteristics(self, device_characteristics: u32, or_in_values: bool) -> Self {
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetCharacteristics,
+                self.wdf_init,
+                device_characteristics,
+                u8::from(or_in_values),
+            );
+        }
+        self
+    }
+
+    /// Register the device's Plug and Play and power management callbacks,
+    /// via `WdfDeviceInitSetPnpPowerEventCallbacks`
+    #[must_use]
+    pub fn pnp_power_callbacks(mut self, pnp_power_callbacks: PnpPowerCallbacks) -> Self {
+        let mut pnp_power_event_callbacks = WDF_PNPPOWER_EVENT_CALLBACKS {
+            Size: wdf_struct_size::<WDF_PNPPOWER_EVENT_CALLBACKS>(),
+            EvtDeviceD0Entry: pnp_power_callbacks
+                .d0_entry
+                .is_some()
+                .then_some(evt_device_d0_entry),
+            EvtDeviceD0Exit: pnp_power_callbacks
+                .d0_exit
+                .is_some()
+                .then_some(evt_device_d0_exit),
+            EvtDevicePrepareHardware: pnp_power_callbacks
+                .prepare_hardware
+                .is_some()
+                .then_some(evt_device_prepare_hardware),
+            EvtDeviceReleaseHardware: pnp_power_callbacks
+                .release_hardware
+                .is_some()
+                .then_some(evt_device_release_hardware),
+            EvtDeviceSurpriseRemoval: pnp_power_callbacks
+                .surprise_removal
+                .is_some()
+                .then_some(evt_device_surprise_removal),
+            ..WDF_PNPPOWER_EVENT_CALLBACKS::default()
+        };
+
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`, and WDF copies
+        // `pnp_power_event_callbacks` before returning
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetPnpPowerEventCallbacks,
+                self.wdf_init,
+                &mut pnp_power_event_callbacks,
+            );
+        }
+        self.callbacks.pnp_power = pnp_power_callbacks;
+        self
+    }
+
+    /// Register the device's file object callbacks, via
+    /// `WdfDeviceInitSetFileObjectConfig`. `file_object_attributes` can be
+    /// used to declare a context type for the device's file objects.
+    #[must_use]
+    pub fn file_object_config(
+        mut self,
+        file_object_config: FileObjectConfig,
+        file_object_attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Self {
+        let mut wdf_file_object_config = WDF_FILEOBJECT_CONFIG {
+            Size: wdf_struct_size::<WDF_FILEOBJECT_CONFIG>(),
+            EvtDeviceFileCreate: file_object_config
+                .create
+                .is_some()
+                .then_some(evt_device_file_create),
+            EvtFileClose: file_object_config.close.is_some().then_some(evt_file_close),
+            EvtFileCleanup: file_object_config
+                .cleanup
+                .is_some()
+                .then_some(evt_file_cleanup),
+            AutoForwardCleanupClose: _WDF_TRI_STATE::WdfUseDefault,
+            FileObjectClass: _WDF_FILEOBJECT_CLASS::WdfFileObjectWdfCannotUseFsContexts,
+        };
+        let file_object_attributes =
+            file_object_attributes.map_or(core::ptr::null_mut(), core::ptr::from_mut);
+
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`, and
+        // `file_object_attributes` is either null or a valid `WDF_OBJECT_ATTRIBUTES`
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetFileObjectConfig,
+                self.wdf_init,
+                &mut wdf_file_object_config,
+                file_object_attributes,
+            );
+        }
+        self.callbacks.file_object = file_object_config;
+        self
+    }
+
+    /// Create the framework device object via `WdfDeviceCreate`, consuming the
+    /// `WDFDEVICE_INIT`.
+    ///
+    /// The device is owned by the framework, which deletes it when the device
+    /// is removed, or if `EvtDriverDeviceAdd` returns an error after the
+    /// device was created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the device object, or fails to allocate the context space for its callbacks. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDeviceCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreate#return-value)
+    pub fn create(self, attributes: &mut WDF_OBJECT_ATTRIBUTES) -> NtResult<Device> {
+        let mut device_init = self.wdf_init;
+        let mut wdf_device: WDFDEVICE = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_init` is a valid `WDFDEVICE_INIT` that has not been used to
+        // create a device yet, as guaranteed by `DeviceInit::from_raw`, and it is never
+        // used again since `self` is consumed
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDeviceCreate,
+                &mut device_init,
+                attributes,
+                &mut wdf_device,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: `wdf_device` was just successfully created by WDF
+        let mut device = unsafe { Device::from_raw(wdf_device) };
+        if !self.callbacks.is_empty() {
+            // SAFETY: The device's PnP and power callbacks cannot run until
+            // `EvtDriverDeviceAdd` returns.
+            unsafe { device.allocate_context(self.callbacks) }?;
+        }
+        Ok(device)
+    }
+}
+
+/// WDF Device.
+pub struct Device {
+    wdf_device: WdfObject<WDFDEVICE>,
+}
+
+impl Device {
+    /// Wrap a framework device object handed to a WDF callback
+    ///
+    /// # Safety
+    ///
+    /// `wdf_device` must be a valid handle to a framework device object
+    pub(super) unsafe fn from_raw(wdf_device: WDFDEVICE) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_device` is valid, and PnP devices are
+            // always owned by the framework
+            wdf_device: unsafe { WdfObject::from_raw(wdf_device, Ownership::Framework) },
+        }
+    }
+
+    /// Register an instance of the device interface `interface_class` for the
+    /// device, via `WdfDeviceCreateDeviceInterface`. Applications find the
+    /// device through its registered interfaces.
+    ///
+    /// `reference_string` distinguishes multiple instances of the same
+    /// interface class on one device. The interface is enabled and disabled
+    /// automatically with the device, or explicitly with
+    /// [`Device::set_interface_state`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the device interface. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDeviceCreateDeviceInterface Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreatedeviceinterface#return-value)
+    pub fn create_interface(
+        &self,
+        interface_class: &Guid,
+        reference_string: Option<&UnicodeStr>,
+    ) -> NtResult<()> {
+        let nt_status;
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        // `interface_class` and `reference_string` are valid for the duration of the
+        // call, and WDF copies them.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDeviceCreateDeviceInterface,
+                self.as_raw(),
+                interface_class.as_raw(),
+                reference_string.map_or(core::ptr::null(), UnicodeStr::as_raw),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+
+    /// Enable or disable the instance of the device interface
+    /// `interface_class` with `reference_string` that was registered with
+    /// [`Device::create_interface`], via `WdfDeviceSetDeviceInterfaceState`.
+    pub fn set_interface_state(
+        &self,
+        interface_class: &Guid,
+        reference_string: Option<&UnicodeStr>,
+        enabled: bool,
+    ) {
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        // `interface_class` and `reference_string` are valid for the duration of the
+        // call.
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDeviceSetDeviceInterfaceState,
+                self.as_raw(),
+                interface_class.as_raw(),
+                reference_string.map_or(core::ptr::null(), UnicodeStr::as_raw),
+                u8::from(enabled),
+            );
+        }
+    }
+
+    fn callbacks(&self) -> &DeviceCallbacks {
+        self.context::<DeviceCallbacks>()
+            .expect("device callbacks are only registered when a DeviceCallbacks context exists")
+    }
+}
+
+impl Deref for Device {
+    type Target = WdfObject<WDFDEVICE>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_device
+    }
+}
+
+impl DerefMut for Device {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_device
+    }
+}
+
+fn file_object_device(file_object: WDFFILEOBJECT) -> Device {
+    let wdf_device;
+    // SAFETY: WDF always passes a valid file object to file object callbacks
+    unsafe {
+        wdf_device = call_unsafe_wdf_function_binding!(WdfFileObjectGetDevice, file_object);
+    }
+    // SAFETY: `WdfFileObjectGetDevice` always returns the valid device that the
+    // file object belongs to
+    unsafe { Device::from_raw(wdf_device) }
+}
+
+extern "C" fn evt_device_d0_entry(
+    wdf_device: WDFDEVICE,
+    previous_state: WDF_POWER_DEVICE_STATE,
+) -> NTSTATUS {
+    // SAFETY: WDF always passes a valid device to `EvtDeviceD0Entry`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    let d0_entry = device
+        .callbacks()
+        .pnp_power
+        .d0_entry
+        .expect("EvtDeviceD0Entry is only registered when a d0_entry callback is set");
+
+    match d0_entry(&device, previous_state) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_device_d0_exit(
+    wdf_device: WDFDEVICE,
+    target_state: WDF_POWER_DEVICE_STATE,
+) -> NTSTATUS {
+    // SAFETY: WDF always passes a valid device to `EvtDeviceD0Exit`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    let d0_exit = device
+        .callbacks()
+        .pnp_power
+        .d0_exit
+        .expect("EvtDeviceD0Exit is only registered when a d0_exit callback is set");
+
+    match d0_exit(&device, target_state) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_device_prepare_hardware(
+    wdf_device: WDFDEVICE,
+    resources_raw: WDFCMRESLIST,
+    resources_translated: WDFCMRESLIST,
+) -> NTSTATUS {
+    // SAFETY: WDF always passes a valid device to `EvtDevicePrepareHardware`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    let prepare_hardware = device.callbacks().pnp_power.prepare_hardware.expect(
+        "EvtDevicePrepareHardware is only registered when a prepare_hardware callback is set",
+    );
+
+    match prepare_hardware(&device, resources_raw, resources_translated) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_device_release_hardware(
+    wdf_device: WDFDEVICE,
+    resources_translated: WDFCMRESLIST,
+) -> NTSTATUS {
+    // SAFETY: WDF always passes a valid device to `EvtDeviceReleaseHardware`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    let release_hardware = device.callbacks().pnp_power.release_hardware.expect(
+        "EvtDeviceReleaseHardware is only registered when a release_hardware callback is set",
+    );
+
+    match release_hardware(&device, resources_translated) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_device_surprise_removal(wdf_device: WDFDEVICE) {
+    // SAFETY: WDF always passes a valid device to `EvtDeviceSurpriseRemoval`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    let surprise_removal = device.callbacks().pnp_power.surprise_removal.expect(
+        "EvtDeviceSurpriseRemoval is only registered when a surprise_removal callback is set",
+    );
+
+    surprise_removal(&device);
+}
+
+extern "C" fn evt_device_file_create(
+    wdf_device: WDFDEVICE,
+    wdf_request: WDFREQUEST,
+    file_object: WDFFILEOBJECT,
+) {
+    // SAFETY: WDF always passes a valid device to `EvtDeviceFileCreate`
+    let device = unsafe { Device::from_raw(wdf_device) };
+    // SAFETY: WDF transfers ownership of the uncompleted create request to the
+    // driver
+    let request = unsafe { Request::from_raw(wdf_request) };
+    let create = device
+        .callbacks()
+        .file_object
+        .create
+        .expect("EvtDeviceFileCreate is only registered when a create callback is set");
+
+    create(&device, request, file_object);
+}
+
+extern "C" fn evt_file_cleanup(file_object: WDFFILEOBJECT) {
+    let device = file_object_device(file_object);
+    let cleanup = device
+        .callbacks()
+        .file_object
+        .cleanup
+        .expect("EvtFileCleanup is only registered when a cleanup callback is set");
+
+    cleanup(&device, file_object);
+}
+
+extern "C" fn evt_file_close(file_object: WDFFILEOBJECT) {
+    let device = file_object_device(file_object);
+    let close = device
+        .callbacks()
+        .file_object
+        .close
+        .expect("EvtFileClose is only registered when a close callback is set");
+
+    close(&device, file_object);
+}
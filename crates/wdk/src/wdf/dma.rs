@@ -0,0 +1,986 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    iter::FusedIterator,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_DMA_DIRECTION,
+    _WDF_DMA_PROFILE,
+    BOOLEAN,
+    NTSTATUS,
+    PFN_WDF_PROGRAM_DMA,
+    PHYSICAL_ADDRESS,
+    PSCATTER_GATHER_LIST,
+    SCATTER_GATHER_ELEMENT,
+    STATUS_MORE_PROCESSING_REQUIRED,
+    STATUS_SUCCESS,
+    WDFCOMMONBUFFER,
+    WDFCONTEXT,
+    WDFDEVICE,
+    WDFDMAENABLER,
+    WDFDMATRANSACTION,
+    WDFREQUEST__,
+    WDF_DMA_DIRECTION,
+    WDF_DMA_ENABLER_CONFIG,
+    WDF_DMA_PROFILE,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use super::{wdf_struct_size, Device, ObjectContext, Ownership, Request, WdfObject};
+use crate::{nt_success, NtResult, NtStatus};
+
+/// The DMA capabilities of a device. This is the equivalent of
+/// `WDF_DMA_PROFILE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaProfile {
+    /// The device only supports packet-based DMA with 32-bit addresses
+    Packet,
+    /// The device supports scatter/gather DMA with 32-bit addresses
+    ScatterGather,
+    /// The device only supports packet-based DMA with 64-bit addresses
+    Packet64,
+    /// The device supports scatter/gather DMA with 64-bit addresses
+    ScatterGather64,
+    /// The device supports scatter/gather DMA with 32-bit addresses, with
+    /// separate read and write channels
+    ScatterGatherDuplex,
+    /// The device supports scatter/gather DMA with 64-bit addresses, with
+    /// separate read and write channels
+    ScatterGather64Duplex,
+    /// The device uses the system DMA controller
+    System,
+    /// The device uses the system DMA controller, with separate read and
+    /// write channels
+    SystemDuplex,
+}
+
+impl DmaProfile {
+    const fn as_raw(self) -> WDF_DMA_PROFILE {
+        match self {
+            Self::Packet => _WDF_DMA_PROFILE::WdfDmaProfilePacket,
+            Self::ScatterGather => _WDF_DMA_PROFILE::WdfDmaProfileScatterGather,
+            Self::Packet64 => _WDF_DMA_PROFILE::WdfDmaProfilePacket64,
+            Self::ScatterGather64 => _WDF_DMA_PROFILE::WdfDmaProfileScatterGather64,
+            Self::ScatterGatherDuplex => _WDF_DMA_PROFILE::WdfDmaProfileScatterGatherDuplex,
+            Self::ScatterGather64Duplex => _WDF_DMA_PROFILE::WdfDmaProfileScatterGather64Duplex,
+            Self::System => _WDF_DMA_PROFILE::WdfDmaProfileSystem,
+            Self::SystemDuplex => _WDF_DMA_PROFILE::WdfDmaProfileSystemDuplex,
+        }
+    }
+}
+
+/// The direction of a DMA transfer. This is the equivalent of
+/// `WDF_DMA_DIRECTION`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// The device writes to memory (ex. for a read request)
+    ReadFromDevice,
+    /// The device reads from memory (ex. for a write request)
+    WriteToDevice,
+}
+
+impl DmaDirection {
+    const fn as_raw(self) -> WDF_DMA_DIRECTION {
+        match self {
+            Self::ReadFromDevice => _WDF_DMA_DIRECTION::WdfDmaDirectionReadFromDevice,
+            Self::WriteToDevice => _WDF_DMA_DIRECTION::WdfDmaDirectionWriteToDevice,
+        }
+    }
+
+    const fn from_raw(direction: WDF_DMA_DIRECTION) -> Self {
+        if matches!(direction, _WDF_DMA_DIRECTION::WdfDmaDirectionWriteToDevice) {
+            Self::WriteToDevice
+        } else {
+            Self::ReadFromDevice
+        }
+    }
+}
+
+/// Returns a [`WDF_DMA_ENABLER_CONFIG`] for a device with the DMA capabilities
+/// of `profile`, which can transfer at most `maximum_length` bytes at a time.
+///
+/// This is initialized the same way as the `WDF_DMA_ENABLER_CONFIG_INIT`
+/// function in the WDF headers. Its other members can be set before passing it
+/// to [`DmaEnabler::try_new`].
+#[must_use]
+pub fn dma_enabler_config(profile: DmaProfile, maximum_length: usize) -> WDF_DMA_ENABLER_CONFIG {
+    WDF_DMA_ENABLER_CONFIG {
+        Size: wdf_struct_size::<WDF_DMA_ENABLER_CONFIG>(),
+        Profile: profile.as_raw(),
+        MaximumLength: maximum_length,
+        ..WDF_DMA_ENABLER_CONFIG::default()
+    }
+}
+
+/// WDF DMA Enabler.
+///
+/// A DMA enabler describes the DMA capabilities of a device. It is used to
+/// allocate [`CommonBuffer`]s and create [`DmaTransaction`]s, and is deleted
+/// along with its device.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{dma_enabler_config, object_attributes, Device, DmaEnabler, DmaProfile},
+///     NtResult,
+/// };
+///
+/// fn enable_dma(device: &Device) -> NtResult<DmaEnabler> {
+///     let mut config = dma_enabler_config(DmaProfile::ScatterGather64, 0x10_0000);
+///     DmaEnabler::try_new(device, &mut config, &mut object_attributes())
+/// }
+/// ```
+pub struct DmaEnabler {
+    wdf_dma_enabler: WdfObject<WDFDMAENABLER>,
+}
+
+impl DmaEnabler {
+    /// Try to construct a WDF DMA Enabler object for `device`, via
+    /// `WdfDmaEnablerCreate`. This must be called at `PASSIVE_LEVEL`, typically
+    /// from `EvtDevicePrepareHardware`.
+    ///
+    /// `attributes` must not have a `ParentObject`, since the DMA enabler is
+    /// always parented to `device`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA enabler. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDmaEnablerCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmaenabler/nf-wdfdmaenabler-wdfdmaenablercreate#return-value)
+    pub fn try_new(
+        device: &Device,
+        config: &mut WDF_DMA_ENABLER_CONFIG,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> NtResult<Self> {
+        let mut wdf_dma_enabler: WDFDMAENABLER = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDmaEnablerCreate,
+                device.as_raw(),
+                config,
+                attributes,
+                &mut wdf_dma_enabler,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(Self {
+            // SAFETY: `wdf_dma_enabler` was just successfully created by WDF, and DMA
+            // enablers are always parented to their device
+            wdf_dma_enabler: unsafe { WdfObject::from_raw(wdf_dma_enabler, Ownership::Parent) },
+        })
+    }
+
+    /// Try to construct a WDF DMA Enabler object for `device`. This is an alias
+    /// for [`DmaEnabler::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA enabler. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDmaEnablerCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmaenabler/nf-wdfdmaenabler-wdfdmaenablercreate#return-value)
+    pub fn create(
+        device: &Device,
+        config: &mut WDF_DMA_ENABLER_CONFIG,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> NtResult<Self> {
+        Self::try_new(device, config, attributes)
+    }
+
+    /// Returns the maximum number of bytes that the device can transfer at a
+    /// time
+    #[must_use]
+    pub fn maximum_length(&self) -> usize {
+        let maximum_length;
+        // SAFETY: `wdf_dma_enabler` is a private member of `DmaEnabler`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            maximum_length =
+                call_unsafe_wdf_function_binding!(WdfDmaEnablerGetMaximumLength, self.as_raw());
+        }
+        maximum_length
+    }
+
+    /// Returns the maximum number of elements in the scatter/gather lists
+    /// passed to [`ProgramDmaCallback::program_dma`]
+    #[must_use]
+    pub fn maximum_scatter_gather_elements(&self) -> usize {
+        let maximum_elements;
+        // SAFETY: `wdf_dma_enabler` is a private member of `DmaEnabler`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            maximum_elements = call_unsafe_wdf_function_binding!(
+                WdfDmaEnablerGetMaximumScatterGatherElements,
+                self.as_raw()
+            );
+        }
+        maximum_elements
+    }
+
+    /// Set the maximum number of elements that the device supports in a
+    /// scatter/gather list, via `WdfDmaEnablerSetMaximumScatterGatherElements`.
+    /// This must be called before any [`DmaTransaction`] is created.
+    pub fn set_maximum_scatter_gather_elements(&self, maximum_elements: usize) {
+        // SAFETY: `wdf_dma_enabler` is a private member of `DmaEnabler`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfDmaEnablerSetMaximumScatterGatherElements,
+                self.as_raw(),
+                maximum_elements
+            );
+        }
+    }
+}
+
+impl Deref for DmaEnabler {
+    type Target = WdfObject<WDFDMAENABLER>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_dma_enabler
+    }
+}
+
+impl DerefMut for DmaEnabler {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_dma_enabler
+    }
+}
+
+/// WDF Common Buffer.
+///
+/// A common buffer is memory that is accessible by both the driver, through
+/// [`CommonBuffer::as_slice`] and [`CommonBuffer::as_mut_slice`], and the
+/// device, through [`CommonBuffer::logical_address`]. It is typically used for
+/// descriptor rings shared with the device. Since the device may access the
+/// memory at any time, the driver must synchronize with the device according
+/// to the device's protocol.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{object_attributes, CommonBuffer, DmaEnabler},
+///     NtResult,
+/// };
+///
+/// fn allocate_ring(dma_enabler: &DmaEnabler) -> NtResult<CommonBuffer> {
+///     let mut ring = CommonBuffer::try_new(dma_enabler, 4096, &mut object_attributes())?;
+///     ring.as_mut_slice().fill(0);
+///     let _ring_base = ring.logical_address();
+///     Ok(ring)
+/// }
+/// ```
+pub struct CommonBuffer {
+    wdf_common_buffer: WdfObject<WDFCOMMONBUFFER>,
+}
+
+impl CommonBuffer {
+    /// Try to allocate a common buffer of `length` bytes for `dma_enabler`, via
+    /// `WdfCommonBufferCreate`. This must be called at `PASSIVE_LEVEL`.
+    ///
+    /// `attributes` must not have a `ParentObject`, since the common buffer is
+    /// always parented to `dma_enabler`. The common buffer is freed when
+    /// `dma_enabler` is deleted along with its device, or earlier via
+    /// [`WdfObject::delete`]. The [`CommonBuffer`] holds a reference on it, so
+    /// its handle remains valid until the [`CommonBuffer`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate a common buffer. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfCommonBufferCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfcommonbuffer/nf-wdfcommonbuffer-wdfcommonbuffercreate#return-value)
+    pub fn try_new(
+        dma_enabler: &DmaEnabler,
+        length: usize,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> NtResult<Self> {
+        let mut wdf_common_buffer: WDFCOMMONBUFFER = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfCommonBufferCreate,
+                dma_enabler.as_raw(),
+                length,
+                attributes,
+                &mut wdf_common_buffer,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(Self {
+            // SAFETY: `wdf_common_buffer` was just successfully created by WDF, and
+            // common buffers are always parented to their DMA enabler
+            wdf_common_buffer: unsafe { WdfObject::from_raw(wdf_common_buffer, Ownership::Parent) },
+        })
+    }
+
+    /// Try to allocate a common buffer of `length` bytes for `dma_enabler`.
+    /// This is an alias for [`CommonBuffer::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate a common buffer. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfCommonBufferCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfcommonbuffer/nf-wdfcommonbuffer-wdfcommonbuffercreate#return-value)
+    pub fn create(
+        dma_enabler: &DmaEnabler,
+        length: usize,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> NtResult<Self> {
+        Self::try_new(dma_enabler, length, attributes)
+    }
+
+    /// Returns the length of the common buffer in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        let length;
+        // SAFETY: `wdf_common_buffer` is a private member of `CommonBuffer`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            length = call_unsafe_wdf_function_binding!(WdfCommonBufferGetLength, self.as_raw());
+        }
+        length
+    }
+
+    /// Returns `true` if the common buffer has a length of zero
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the address of the common buffer in the device's address space,
+    /// which is the address to program into the device
+    #[must_use]
+    pub fn logical_address(&self) -> u64 {
+        let logical_address;
+        // SAFETY: `wdf_common_buffer` is a private member of `CommonBuffer`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            logical_address = call_unsafe_wdf_function_binding!(
+                WdfCommonBufferGetAlignedLogicalAddress,
+                self.as_raw()
+            );
+        }
+        logical_address_from_raw(logical_address)
+    }
+
+    /// Returns the contents of the common buffer
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: The virtual address of a common buffer is valid for `len` bytes for
+        // as long as the common buffer, which is kept alive by `self`
+        unsafe { core::slice::from_raw_parts(self.virtual_address(), self.len()) }
+    }
+
+    /// Returns the contents of the common buffer mutably
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: The virtual address of a common buffer is valid for `len` bytes for
+        // as long as the common buffer, which is kept alive by `self`. The mutable
+        // borrow of `self` guarantees that this is the only reference to the memory
+        // held by the driver.
+        unsafe { core::slice::from_raw_parts_mut(self.virtual_address(), self.len()) }
+    }
+
+    fn virtual_address(&self) -> *mut u8 {
+        let virtual_address;
+        // SAFETY: `wdf_common_buffer` is a private member of `CommonBuffer`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            virtual_address = call_unsafe_wdf_function_binding!(
+                WdfCommonBufferGetAlignedVirtualAddress,
+                self.as_raw()
+            );
+        }
+        virtual_address.cast()
+    }
+}
+
+impl Deref for CommonBuffer {
+    type Target = WdfObject<WDFCOMMONBUFFER>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_common_buffer
+    }
+}
+
+impl DerefMut for CommonBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_common_buffer
+    }
+}
+
+/// The scatter/gather list of a DMA transfer, passed to
+/// [`ProgramDmaCallback::program_dma`].
+///
+/// Each [`ScatterGatherElement`] describes a physically contiguous range of
+/// the transfer in the device's address space.
+#[derive(Clone, Copy)]
+pub struct ScatterGatherList<'a> {
+    elements: &'a [SCATTER_GATHER_ELEMENT],
+}
+
+/// An element of a [`ScatterGatherList`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScatterGatherElement {
+    logical_address: u64,
+    length: u32,
+}
+
+/// An iterator over the [`ScatterGatherElement`]s of a [`ScatterGatherList`],
+/// returned by [`ScatterGatherList::iter`].
+#[derive(Clone)]
+pub struct ScatterGatherIter<'a> {
+    elements: core::slice::Iter<'a, SCATTER_GATHER_ELEMENT>,
+}
+
+impl<'a> ScatterGatherList<'a> {
+    /// Wrap a scatter/gather list handed to `EvtProgramDma`
+    ///
+    /// # Safety
+    ///
+    /// `sg_list` must point to a valid scatter/gather list, whose elements are
+    /// valid for `'a`
+    unsafe fn from_raw(sg_list: PSCATTER_GATHER_LIST) -> Self {
+        // SAFETY: The caller guarantees that `sg_list` points to a valid
+        // scatter/gather list
+        let number_of_elements = unsafe { (*sg_list).NumberOfElements };
+        // SAFETY: The caller guarantees that `sg_list` points to a valid
+        // scatter/gather list
+        let elements = unsafe { core::ptr::addr_of!((*sg_list).Elements) };
+
+        Self {
+            // SAFETY: `Elements` is a flexible array member holding `NumberOfElements`
+            // elements, which the caller guarantees are valid for `'a`
+            elements: unsafe {
+                core::slice::from_raw_parts(elements.cast(), number_of_elements as usize)
+            },
+        }
+    }
+
+    /// Returns the number of elements in the list
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the list has no elements
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Returns an iterator over the elements of the list
+    #[must_use]
+    pub fn iter(&self) -> ScatterGatherIter<'a> {
+        ScatterGatherIter {
+            elements: self.elements.iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for ScatterGatherList<'a> {
+    type IntoIter = ScatterGatherIter<'a>;
+    type Item = ScatterGatherElement;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &ScatterGatherList<'a> {
+    type IntoIter = ScatterGatherIter<'a>;
+    type Item = ScatterGatherElement;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl ScatterGatherElement {
+    const fn from_raw(element: &SCATTER_GATHER_ELEMENT) -> Self {
+        Self {
+            logical_address: logical_address_from_raw(element.Address),
+            length: element.Length,
+        }
+    }
+
+    /// Returns the address of the range in the device's address space
+    #[must_use]
+    pub const fn logical_address(&self) -> u64 {
+        self.logical_address
+    }
+
+    /// Returns the length of the range in bytes
+    #[must_use]
+    pub const fn length(&self) -> u32 {
+        self.length
+    }
+}
+
+impl Iterator for ScatterGatherIter<'_> {
+    type Item = ScatterGatherElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements.next().map(ScatterGatherElement::from_raw)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ScatterGatherIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.elements
+            .next_back()
+            .map(ScatterGatherElement::from_raw)
+    }
+}
+
+impl ExactSizeIterator for ScatterGatherIter<'_> {}
+
+impl FusedIterator for ScatterGatherIter<'_> {}
+
+/// Callback of a [`DmaTransaction`].
+///
+/// The implementing type is stored in the context space of the transaction,
+/// so it can hold any per-transaction state (ex. the registers of the DMA
+/// channel that the transaction uses).
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::wdf::{
+///     DmaDirection,
+///     DmaTransaction,
+///     ObjectContext,
+///     ProgramDmaCallback,
+///     ScatterGatherList,
+/// };
+///
+/// #[derive(ObjectContext)]
+/// struct DmaChannel;
+///
+/// impl ProgramDmaCallback for DmaChannel {
+///     fn program_dma(
+///         &self,
+///         _transaction: &DmaTransaction,
+///         _direction: DmaDirection,
+///         scatter_gather_list: ScatterGatherList<'_>,
+///     ) -> bool {
+///         for element in scatter_gather_list {
+///             let (_address, _length) = (element.logical_address(), element.length());
+///             // Write the element to the device's descriptor ring
+///         }
+///         true
+///     }
+/// }
+/// ```
+pub trait ProgramDmaCallback: ObjectContext + Sync {
+    /// `EvtProgramDma`: program the device to transfer the ranges in
+    /// `scatter_gather_list`, in `direction`. This is called at
+    /// `DISPATCH_LEVEL`, once for each transfer needed to complete the
+    /// transaction.
+    ///
+    /// Returns whether the device was programmed. If `false` is returned, the
+    /// driver must complete the transaction via
+    /// [`DmaTransaction::dma_completed_with_length`] with a length of zero.
+    fn program_dma(
+        &self,
+        transaction: &DmaTransaction,
+        direction: DmaDirection,
+        scatter_gather_list: ScatterGatherList<'_>,
+    ) -> bool;
+}
+
+/// WDF DMA Transaction.
+///
+/// A DMA transaction splits an I/O request into one or more DMA transfers that
+/// the device can perform, each programmed by
+/// [`ProgramDmaCallback::program_dma`]. The transaction owns the request
+/// while its buffer is being transferred, and gives it back to be completed
+/// when it is [released](DmaTransaction::release), after which it can be
+/// reused for another request.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{DmaDirection, DmaTransaction, Request},
+///     NtStatus,
+/// };
+/// use wdk_sys::STATUS_SUCCESS;
+///
+/// fn start_read(transaction: &DmaTransaction, request: Request) {
+///     if let Err((nt_status, request)) =
+///         transaction.initialize_using_request(request, DmaDirection::ReadFromDevice)
+///     {
+///         request.complete(nt_status, 0);
+///         return;
+///     }
+///     if let Err(nt_status) = transaction.execute() {
+///         complete_request(transaction, nt_status);
+///     }
+/// }
+///
+/// // Called from the DPC of the device's interrupt when a transfer finishes
+/// fn transfer_finished(transaction: &DmaTransaction) {
+///     if let Some(result) = transaction.dma_completed() {
+///         complete_request(transaction, result.err().unwrap_or(STATUS_SUCCESS.into()));
+///     }
+/// }
+///
+/// fn complete_request(transaction: &DmaTransaction, nt_status: NtStatus) {
+///     let bytes_transferred = transaction.bytes_transferred();
+///     if let Ok(Some(request)) = transaction.release() {
+///         request.complete(nt_status, bytes_transferred);
+///     }
+/// }
+/// ```
+// clippy::struct_field_names: `wdf_dma_transaction` is named like the handles of
+// the other WDF object wrappers
+#[allow(clippy::struct_field_names)]
+pub struct DmaTransaction {
+    wdf_dma_transaction: WdfObject<WDFDMATRANSACTION>,
+    evt_program_dma: PFN_WDF_PROGRAM_DMA,
+    // The request that the transaction was initialized with, which is owned by the
+    // transaction until it is released
+    request: AtomicPtr<WDFREQUEST__>,
+}
+
+impl DmaTransaction {
+    /// Try to construct a WDF DMA Transaction object for `dma_enabler`, via
+    /// `WdfDmaTransactionCreate`, storing `callback` in its context space.
+    ///
+    /// `attributes` must not have an `EvtCleanupCallback` or context type set.
+    /// Without a `ParentObject`, the transaction is parented to `dma_enabler`.
+    /// Either way, the transaction is deleted along with its parent, or earlier
+    /// via [`WdfObject::delete`]. The [`DmaTransaction`] holds a reference on
+    /// it, so its handle remains valid until the [`DmaTransaction`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA transaction. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDmaTransactionCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioncreate#return-value)
+    pub fn try_new<T: ProgramDmaCallback>(
+        dma_enabler: &DmaEnabler,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        callback: T,
+    ) -> NtResult<Self> {
+        T::set_context_type(attributes);
+        let mut wdf_dma_transaction: WDFDMATRANSACTION = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionCreate,
+                dma_enabler.as_raw(),
+                attributes,
+                &mut wdf_dma_transaction,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let mut transaction = Self {
+            // SAFETY: `wdf_dma_transaction` was just successfully created by WDF, and
+            // transactions are always parented to their DMA enabler or to the
+            // `ParentObject` in `attributes`
+            wdf_dma_transaction: unsafe {
+                WdfObject::from_raw(wdf_dma_transaction, Ownership::Parent)
+            },
+            evt_program_dma: Some(evt_program_dma::<T>),
+            request: AtomicPtr::new(core::ptr::null_mut()),
+        };
+        // `T` is always present since it was set in `attributes`.
+        // SAFETY: The callback cannot run until the transaction is executed, which
+        // requires the `DmaTransaction` being returned.
+        if unsafe { transaction.init_context(callback) }.is_err() {
+            unreachable!("DMA transaction should have a context space for its callback");
+        }
+        Ok(transaction)
+    }
+
+    /// Try to construct a WDF DMA Transaction object for `dma_enabler`. This is
+    /// an alias for [`DmaTransaction::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA transaction. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDmaTransactionCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioncreate#return-value)
+    pub fn create<T: ProgramDmaCallback>(
+        dma_enabler: &DmaEnabler,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        callback: T,
+    ) -> NtResult<Self> {
+        Self::try_new(dma_enabler, attributes, callback)
+    }
+
+    /// Wrap a transaction handed to `EvtProgramDma`. The returned
+    /// [`DmaTransaction`] does not own the request of the transaction, so it
+    /// is only returned by [`DmaTransaction::release`] on the
+    /// [`DmaTransaction`] that was initialized with it.
+    ///
+    /// # Safety
+    ///
+    /// `wdf_dma_transaction` must be a valid handle to a transaction created by
+    /// [`DmaTransaction::try_new`] with a `T` callback
+    unsafe fn from_raw<T: ProgramDmaCallback>(wdf_dma_transaction: WDFDMATRANSACTION) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_dma_transaction` is valid. Wrapping it
+            // as parented references it without ever deleting it.
+            wdf_dma_transaction: unsafe {
+                WdfObject::from_raw(wdf_dma_transaction, Ownership::Parent)
+            },
+            evt_program_dma: Some(evt_program_dma::<T>),
+            request: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Initialize the transaction to transfer the buffer of `request`, via
+    /// `WdfDmaTransactionInitializeUsingRequest`. The request must be a read,
+    /// write or device control request of a device using direct I/O.
+    ///
+    /// The transaction takes ownership of `request`, so that it cannot be
+    /// completed while its buffer is being transferred. It is returned by
+    /// [`DmaTransaction::release`] once the transaction has finished. If this
+    /// [`DmaTransaction`] is dropped before then, the request is never
+    /// completed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to initialize the transaction. The error variant will contain an [`NtStatus`] of the failure, along with the [`Request`], which is still owned by the driver and must be completed. Full error documentation is available in the [WdfDmaTransactionInitializeUsingRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioninitializeusingrequest#return-value)
+    pub fn initialize_using_request(
+        &self,
+        request: Request,
+        direction: DmaDirection,
+    ) -> Result<(), (NtStatus, Request)> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state. `request` is owned by the driver and has not been completed,
+        // and `evt_program_dma` was instantiated for the callback stored in the
+        // transaction's context space.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionInitializeUsingRequest,
+                self.as_raw(),
+                request.as_raw(),
+                self.evt_program_dma,
+                direction.as_raw(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((nt_status.into(), request));
+        }
+
+        // WDF fails to initialize a transaction that has not been released, so no
+        // request can already be stored
+        self.request.store(request.into_raw(), Ordering::Release);
+        Ok(())
+    }
+
+    /// Start the transaction, via `WdfDmaTransactionExecute`, which calls
+    /// [`ProgramDmaCallback::program_dma`] for its first transfer. This must be
+    /// called at `IRQL` <= `DISPATCH_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to start the transaction. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDmaTransactionExecute Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactionexecute#return-value)
+    pub fn execute(&self) -> NtResult<()> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state. The callback does not use the context argument.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionExecute,
+                self.as_raw(),
+                core::ptr::null_mut(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+
+    /// Notify WDF that the device finished the current transfer, via
+    /// `WdfDmaTransactionDmaCompleted`. This is typically called from the DPC
+    /// of the device's interrupt.
+    ///
+    /// Returns [`None`] if more transfers are needed, in which case WDF calls
+    /// [`ProgramDmaCallback::program_dma`] for the next one. Otherwise, the
+    /// transaction is finished, and the final result is returned.
+    #[must_use]
+    pub fn dma_completed(&self) -> Option<NtResult<()>> {
+        let mut nt_status: NTSTATUS = STATUS_SUCCESS;
+        let finished;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            finished = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionDmaCompleted,
+                self.as_raw(),
+                &mut nt_status,
+            );
+        }
+        completion_result(finished, nt_status)
+    }
+
+    /// Notify WDF that the device finished the current transfer after
+    /// transferring `transferred_length` bytes, via
+    /// `WdfDmaTransactionDmaCompletedWithLength`. See
+    /// [`DmaTransaction::dma_completed`] for the returned value.
+    #[must_use]
+    pub fn dma_completed_with_length(&self, transferred_length: usize) -> Option<NtResult<()>> {
+        let mut nt_status: NTSTATUS = STATUS_SUCCESS;
+        let finished;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            finished = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionDmaCompletedWithLength,
+                self.as_raw(),
+                transferred_length,
+                &mut nt_status,
+            );
+        }
+        completion_result(finished, nt_status)
+    }
+
+    /// Returns the total number of bytes transferred by the transaction so far
+    #[must_use]
+    pub fn bytes_transferred(&self) -> usize {
+        let bytes_transferred;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            bytes_transferred = call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionGetBytesTransferred,
+                self.as_raw()
+            );
+        }
+        bytes_transferred
+    }
+
+    /// Release the resources of a finished transaction, via
+    /// `WdfDmaTransactionRelease`, so that it can be initialized again.
+    ///
+    /// Returns the request that the transaction was initialized with, which
+    /// must then be completed, or [`None`] if it has already been returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to release the transaction, in which case the transaction keeps ownership of its request. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfDmaTransactionRelease Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactionrelease#return-value)
+    pub fn release(&self) -> NtResult<Option<Request>> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and `WdfObject` guarantees that it is always in a
+        // valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(WdfDmaTransactionRelease, self.as_raw());
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let wdf_request = self.request.swap(core::ptr::null_mut(), Ordering::AcqRel);
+        if wdf_request.is_null() {
+            return Ok(None);
+        }
+        // SAFETY: `wdf_request` was taken from a `Request` by
+        // `initialize_using_request`, and the swap above guarantees that it is only
+        // returned once. The transaction no longer uses it after being released.
+        Ok(Some(unsafe { Request::from_raw(wdf_request) }))
+    }
+}
+
+impl Deref for DmaTransaction {
+    type Target = WdfObject<WDFDMATRANSACTION>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_dma_transaction
+    }
+}
+
+impl DerefMut for DmaTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_dma_transaction
+    }
+}
+
+/// Convert a logical address in the device's address space to the `u64`
+/// programmed into the device
+// clippy::cast_sign_loss: `PHYSICAL_ADDRESS` is signed only because it is a
+// `LARGE_INTEGER`, so its bits are reinterpreted as is
+#[allow(clippy::cast_sign_loss)]
+const fn logical_address_from_raw(logical_address: PHYSICAL_ADDRESS) -> u64 {
+    // SAFETY: `QuadPart` covers the whole `LARGE_INTEGER`, so it is always
+    // initialized
+    let logical_address = unsafe { logical_address.QuadPart };
+    logical_address as u64
+}
+
+/// Convert the result of `WdfDmaTransactionDmaCompleted*` into the value
+/// returned by [`DmaTransaction::dma_completed`]
+fn completion_result(finished: BOOLEAN, nt_status: NTSTATUS) -> Option<NtResult<()>> {
+    if finished == 0 || nt_status == STATUS_MORE_PROCESSING_REQUIRED {
+        return None;
+    }
+
+    if nt_success(nt_status) {
+        Some(Ok(()))
+    } else {
+        Some(Err(nt_status.into()))
+    }
+}
+
+extern "C" fn evt_program_dma<T: ProgramDmaCallback>(
+    wdf_dma_transaction: WDFDMATRANSACTION,
+    _device: WDFDEVICE,
+    _context: WDFCONTEXT,
+    direction: WDF_DMA_DIRECTION,
+    sg_list: PSCATTER_GATHER_LIST,
+) -> BOOLEAN {
+    // SAFETY: WDF always passes a valid handle to a transaction, which was
+    // initialized by `DmaTransaction::initialize_using_request` with this function
+    let transaction = unsafe { DmaTransaction::from_raw::<T>(wdf_dma_transaction) };
+    // SAFETY: WDF always passes a valid scatter/gather list, which is valid until
+    // this callback returns
+    let scatter_gather_list = unsafe { ScatterGatherList::from_raw(sg_list) };
+
+    // The context is always initialized before the transaction can be executed
+    let programmed = transaction.context::<T>().is_some_and(|callback| {
+        callback.program_dma(
+            &transaction,
+            DmaDirection::from_raw(direction),
+            scatter_gather_list,
+        )
+    });
+    u8::from(programmed)
+}
@@ -0,0 +1,266 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::ops::{Deref, DerefMut};
+
+use wdk_sys::{call_unsafe_wdf_function_binding, WDFDPC, WDF_DPC_CONFIG, WDF_OBJECT_ATTRIBUTES};
+
+#[cfg(feature = "alloc")]
+use super::callback::ClosureCallback;
+use super::{
+    callback::stored_callback,
+    context::object_context,
+    wdf_struct_size,
+    ObjectContext,
+    Ownership,
+    WdfObject,
+};
+use crate::{nt_success, NtResult};
+
+/// Callback of a [`Dpc`].
+///
+/// The implementing type is stored in the context space of the DPC, so it can
+/// hold any per-DPC state. The state of the device or queue that the DPC
+/// belongs to can be reached via [`Dpc::parent_context`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// use wdk::wdf::{Dpc, DpcCallback, ObjectContext};
+///
+/// #[derive(ObjectContext)]
+/// struct CompleteTransfers {
+///     completed: AtomicU32,
+/// }
+///
+/// impl DpcCallback for CompleteTransfers {
+///     fn run(&self, _dpc: &Dpc) {
+///         self.completed.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait DpcCallback: ObjectContext + Sync {
+    /// `EvtDpcFunc`: called at `DISPATCH_LEVEL` after the DPC has been
+    /// enqueued
+    fn run(&self, dpc: &Dpc);
+}
+
+/// WDF DPC.
+///
+/// A DPC defers work to `DISPATCH_LEVEL`, independently of any interrupt. DPCs
+/// that finish servicing an [`Interrupt`](super::Interrupt) should instead be
+/// implemented by [`InterruptCallbacks::dpc`](super::InterruptCallbacks::dpc).
+/// A DPC that is already enqueued is not enqueued again, so its callback runs
+/// once for any number of [`Dpc::enqueue`] calls made before it starts.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     println,
+///     wdf::{object_attributes, Device, Dpc},
+///     NtResult,
+/// };
+///
+/// fn defer_completion(device: &Device) -> NtResult<Dpc> {
+///     let mut attributes = object_attributes();
+///     device.set_as_parent_in(&mut attributes);
+///
+///     let dpc = Dpc::builder().create_with_closure(&mut attributes, |_dpc| {
+///         println!("running at DISPATCH_LEVEL")
+///     })?;
+///     let _ = dpc.enqueue();
+///     Ok(dpc)
+/// }
+/// ```
+pub struct Dpc {
+    wdf_dpc: WdfObject<WDFDPC>,
+}
+
+/// Builder for a [`Dpc`], wrapping the configuration in [`WDF_DPC_CONFIG`].
+pub struct DpcBuilder {
+    automatic_serialization: bool,
+}
+
+impl Dpc {
+    /// Returns a [`DpcBuilder`] to configure and create a DPC
+    #[must_use]
+    pub const fn builder() -> DpcBuilder {
+        DpcBuilder {
+            automatic_serialization: true,
+        }
+    }
+
+    /// Wrap a DPC handed to a WDF callback
+    ///
+    /// # Safety
+    ///
+    /// `wdf_dpc` must be a valid handle to a DPC created by the driver
+    unsafe fn from_raw(wdf_dpc: WDFDPC) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_dpc` is valid, and DPCs are always
+            // parented to a device or queue
+            wdf_dpc: unsafe { WdfObject::from_raw(wdf_dpc, Ownership::Parent) },
+        }
+    }
+
+    /// Enqueue the DPC, via `WdfDpcEnqueue`, so that its callback runs at
+    /// `DISPATCH_LEVEL`. This can be called at any `IRQL`.
+    ///
+    /// Returns `false` if the DPC was already enqueued.
+    #[must_use]
+    pub fn enqueue(&self) -> bool {
+        let result;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            result = call_unsafe_wdf_function_binding!(WdfDpcEnqueue, self.as_raw());
+        }
+        result != 0
+    }
+
+    /// Remove the DPC from the DPC queue, via `WdfDpcCancel`. If `wait` is
+    /// `true`, this also waits for a running callback of the DPC to return,
+    /// and must be called at `PASSIVE_LEVEL`.
+    ///
+    /// Returns `true` if the DPC was enqueued and had not started running.
+    #[must_use]
+    pub fn cancel(&self, wait: bool) -> bool {
+        let result;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            result = call_unsafe_wdf_function_binding!(WdfDpcCancel, self.as_raw(), u8::from(wait));
+        }
+        result != 0
+    }
+
+    /// Returns a reference to the `T` context of the device or queue that the
+    /// DPC belongs to, or [`None`] if it has no `T` context space, or it has
+    /// not been initialized
+    ///
+    /// # Safety
+    ///
+    /// The device or queue must outlive the returned reference. Holding a
+    /// [`Dpc`] does not keep its parent alive, since deleting the parent also
+    /// deletes the DPC regardless of the references held on it. Calling this
+    /// from [`DpcCallback::run`] is sound, as the DPC is deleted before its
+    /// parent and its deletion waits for a running callback to return.
+    #[must_use]
+    pub unsafe fn parent_context<T: ObjectContext + Sync>(&self) -> Option<&T> {
+        let parent;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            parent = call_unsafe_wdf_function_binding!(WdfDpcGetParentObject, self.as_raw());
+        }
+
+        // SAFETY: The caller guarantees that the parent outlives the returned
+        // reference
+        unsafe { object_context::<T>(parent) }
+    }
+}
+
+impl Deref for Dpc {
+    type Target = WdfObject<WDFDPC>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_dpc
+    }
+}
+
+impl DerefMut for Dpc {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_dpc
+    }
+}
+
+impl DpcBuilder {
+    /// Set whether the DPC's callback is synchronized with the callbacks of
+    /// its parent device or queue, according to their synchronization scope.
+    /// This is enabled by default, as in `WDF_DPC_CONFIG_INIT`.
+    #[must_use]
+    pub const fn automatic_serialization(mut self, automatic_serialization: bool) -> Self {
+        self.automatic_serialization = automatic_serialization;
+        self
+    }
+
+    /// Create the DPC via `WdfDpcCreate`, storing `callback` in its context
+    /// space.
+    ///
+    /// `attributes` must have a `ParentObject`, which is the device or queue
+    /// that the DPC belongs to, and must not have an `EvtCleanupCallback` or
+    /// context type set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DPC. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDpcCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdpc/nf-wdfdpc-wdfdpccreate#return-value)
+    pub fn create<T: DpcCallback>(
+        self,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        callback: T,
+    ) -> NtResult<Dpc> {
+        let mut dpc_config = WDF_DPC_CONFIG {
+            Size: wdf_struct_size::<WDF_DPC_CONFIG>(),
+            EvtDpcFunc: Some(evt_dpc_func::<T>),
+            AutomaticSerialization: u8::from(self.automatic_serialization),
+        };
+        T::set_context_type(attributes);
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_dpc: WDFDPC = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDpcCreate,
+                &mut dpc_config,
+                attributes,
+                &mut wdf_dpc,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let mut dpc = Dpc {
+            // SAFETY: `wdf_dpc` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_dpc: unsafe { WdfObject::from_raw(wdf_dpc, ownership) },
+        };
+        // `T` is always present since it was set in `attributes`.
+        // SAFETY: The callback cannot run until the DPC is enqueued, which requires
+        // the `Dpc` being returned.
+        if unsafe { dpc.init_context(callback) }.is_err() {
+            unreachable!("DPC should have a context space for its callback");
+        }
+        Ok(dpc)
+    }
+
+    /// Create the DPC via `WdfDpcCreate`, calling `closure` when it runs. See
+    /// [`DpcBuilder::create`] for the requirements on `attributes`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DPC. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDpcCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdpc/nf-wdfdpc-wdfdpccreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create_with_closure(
+        self,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        closure: impl Fn(&Dpc) + Send + Sync + 'static,
+    ) -> NtResult<Dpc> {
+        self.create(attributes, ClosureCallback::new(closure))
+    }
+}
+
+extern "C" fn evt_dpc_func<T: DpcCallback>(wdf_dpc: WDFDPC) {
+    // SAFETY: WDF always passes a valid handle to the DPC being run
+    let dpc = unsafe { Dpc::from_raw(wdf_dpc) };
+    if let Some(callback) = stored_callback::<T, _>(&dpc) {
+        callback.run(&dpc);
+    }
+}
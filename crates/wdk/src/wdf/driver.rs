@@ -0,0 +1,283 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::ops::{Deref, DerefMut};
+
+pub use wdk_sys::__proc_macros::driver_entry;
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_DRIVER_INIT_FLAGS,
+    DRIVER_OBJECT,
+    NTSTATUS,
+    PCUNICODE_STRING,
+    PDRIVER_OBJECT,
+    PWDFDEVICE_INIT,
+    STATUS_SUCCESS,
+    ULONG,
+    WDFDRIVER,
+    WDF_DRIVER_CONFIG,
+};
+
+use super::{wdf_struct_size, DeviceInit, ObjectContext, Ownership, WdfObject};
+use crate::{nt_success, string::UnicodeStr, NtResult};
+
+/// Callback invoked by WDF when the Plug and Play manager reports a new device.
+/// This is the equivalent of `EvtDriverDeviceAdd`.
+pub type DeviceAddFn = fn(&Driver, DeviceInit<'_>) -> NtResult<()>;
+
+/// Callback invoked by WDF before the driver is unloaded. This is the
+/// equivalent of `EvtDriverUnload`.
+pub type UnloadFn = fn(&Driver);
+
+/// The driver object that the system passes to `DriverEntry`.
+#[repr(transparent)]
+pub struct DriverObject(DRIVER_OBJECT);
+
+impl DriverObject {
+    /// Returns a raw pointer to the underlying [`DRIVER_OBJECT`]
+    #[must_use]
+    pub fn as_raw(&mut self) -> PDRIVER_OBJECT {
+        core::ptr::from_mut(&mut self.0)
+    }
+}
+
+/// WDF Driver.
+///
+/// Every WDF driver has exactly one framework driver object, which is created
+/// in `DriverEntry` via [`DriverBuilder::create`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     string::UnicodeStr,
+///     wdf::{driver_entry, object_attributes, DeviceInit, Driver, DriverObject},
+///     NtResult,
+/// };
+///
+/// #[driver_entry]
+/// fn driver_entry(driver: &mut DriverObject, registry_path: &UnicodeStr) -> NtResult<()> {
+///     Driver::builder()
+///         .device_add(device_add)
+///         .create(driver, registry_path)?;
+///     Ok(())
+/// }
+///
+/// fn device_add(_driver: &Driver, device_init: DeviceInit<'_>) -> NtResult<()> {
+///     device_init.create(&mut object_attributes())?;
+///     Ok(())
+/// }
+/// ```
+pub struct Driver {
+    wdf_driver: WdfObject<WDFDRIVER>,
+}
+
+/// Builder for the framework driver object, wrapping the configuration in
+/// [`WDF_DRIVER_CONFIG`].
+#[derive(Default)]
+pub struct DriverBuilder {
+    callbacks: DriverCallbacks,
+    non_pnp: bool,
+    pool_tag: ULONG,
+}
+
+/// The Rust callbacks of a [`Driver`], stored in the context space of its
+/// framework driver object so that they can be reached from WDF's callbacks
+#[derive(Default, ObjectContext)]
+struct DriverCallbacks {
+    device_add: Option<DeviceAddFn>,
+    unload: Option<UnloadFn>,
+}
+
+impl Driver {
+    /// Returns a [`DriverBuilder`] to configure and create the framework
+    /// driver object
+    #[must_use]
+    pub fn builder() -> DriverBuilder {
+        DriverBuilder::default()
+    }
+
+    /// Wrap the framework driver object handed to a WDF callback
+    ///
+    /// # Safety
+    ///
+    /// `wdf_driver` must be a valid handle to the framework driver object
+    unsafe fn from_raw(wdf_driver: WDFDRIVER) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_driver` is valid, and the driver object is
+            // always owned by the framework
+            wdf_driver: unsafe { WdfObject::from_raw(wdf_driver, Ownership::Framework) },
+        }
+    }
+}
+
+impl Deref for Driver {
+    type Target = WdfObject<WDFDRIVER>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_driver
+    }
+}
+
+impl DerefMut for Driver {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_driver
+    }
+}
+
+impl DriverBuilder {
+    /// Set the `EvtDriverDeviceAdd` callback
+    #[must_use]
+    pub fn device_add(mut self, device_add: DeviceAddFn) -> Self {
+        self.callbacks.device_add = Some(device_add);
+        self
+    }
+
+    /// Set the `EvtDriverUnload` callback
+    #[must_use]
+    pub fn unload(mut self, unload: UnloadFn) -> Self {
+        self.callbacks.unload = Some(unload);
+        self
+    }
+
+    /// Mark the driver as a non-PnP driver (`WdfDriverInitNonPnpDriver`).
+    /// Non-PnP drivers must set an [`DriverBuilder::unload`] callback.
+    #[must_use]
+    pub const fn non_pnp(mut self) -> Self {
+        self.non_pnp = true;
+        self
+    }
+
+    /// Set the pool tag that WDF uses for allocations made on behalf of the
+    /// driver
+    #[must_use]
+    pub const fn pool_tag(mut self, pool_tag: ULONG) -> Self {
+        self.pool_tag = pool_tag;
+        self
+    }
+
+    /// Create the framework driver object via `WdfDriverCreate`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the driver object. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfDriverCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdrivercreate#return-value)
+    pub fn create(
+        self,
+        driver_object: &mut DriverObject,
+        registry_path: &UnicodeStr,
+    ) -> NtResult<Driver> {
+        let mut init_flags = 0;
+        if self.non_pnp {
+            // clippy::cast_sign_loss: WDF_DRIVER_INIT_FLAGS values are all positive
+            #[allow(clippy::cast_sign_loss)]
+            {
+                init_flags |=
+                    crate::enum_value(_WDF_DRIVER_INIT_FLAGS::WdfDriverInitNonPnpDriver) as ULONG;
+            }
+        }
+
+        let mut driver_config = WDF_DRIVER_CONFIG {
+            Size: wdf_struct_size::<WDF_DRIVER_CONFIG>(),
+            EvtDriverDeviceAdd: self
+                .callbacks
+                .device_add
+                .is_some()
+                .then_some(evt_driver_device_add),
+            EvtDriverUnload: self.callbacks.unload.is_some().then_some(evt_driver_unload),
+            DriverInitFlags: init_flags,
+            DriverPoolTag: self.pool_tag,
+        };
+        let mut driver_attributes = DriverCallbacks::object_attributes();
+        let mut wdf_driver: WDFDRIVER = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: This is safe because:
+        //         1. `driver_object` and `registry_path` are the arguments passed to
+        //            `DriverEntry`
+        //         2. `driver_attributes` and `driver_config` are valid and fully
+        //            initialized
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfDriverCreate,
+                driver_object.as_raw(),
+                registry_path.as_raw(),
+                &mut driver_attributes,
+                &mut driver_config,
+                &mut wdf_driver,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: `wdf_driver` was just successfully created by WDF
+        let mut driver = unsafe { Driver::from_raw(wdf_driver) };
+        // `DriverCallbacks` is always present since it was set in `driver_attributes`.
+        // SAFETY: The driver's callbacks cannot run until `DriverEntry` returns.
+        if unsafe { driver.init_context(self.callbacks) }.is_err() {
+            unreachable!("driver object should have a DriverCallbacks context");
+        }
+        Ok(driver)
+    }
+}
+
+/// Internal implementation of the `DriverEntry` symbol generated by
+/// [`driver_entry`]. This function is an implementation detail and should
+/// never be called directly, but must be public to be useable by the
+/// generated code.
+///
+/// # Safety
+///
+/// `driver` and `registry_path` must be the arguments passed by the system to
+/// `DriverEntry`
+#[doc(hidden)]
+pub unsafe fn _driver_entry(
+    driver: PDRIVER_OBJECT,
+    registry_path: PCUNICODE_STRING,
+    driver_entry: fn(&mut DriverObject, &UnicodeStr) -> NtResult<()>,
+) -> NTSTATUS {
+    // SAFETY: The system passes a valid, non-null `DRIVER_OBJECT` that is
+    // exclusively accessed by `DriverEntry`, and `DriverObject` is
+    // `repr(transparent)`
+    let driver = unsafe { &mut *driver.cast::<DriverObject>() };
+    // SAFETY: The system passes a valid, non-null `UNICODE_STRING` that is not
+    // mutated for the duration of `DriverEntry`
+    let registry_path = unsafe { UnicodeStr::from_raw(registry_path) };
+
+    match driver_entry(driver, registry_path) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_driver_device_add(
+    wdf_driver: WDFDRIVER,
+    device_init: PWDFDEVICE_INIT,
+) -> NTSTATUS {
+    // SAFETY: WDF always passes a valid handle to the framework driver object
+    let driver = unsafe { Driver::from_raw(wdf_driver) };
+    let device_add = driver
+        .context::<DriverCallbacks>()
+        .and_then(|callbacks| callbacks.device_add)
+        .expect("EvtDriverDeviceAdd is only registered when a device_add callback is set");
+
+    // SAFETY: WDF passes a valid `WDFDEVICE_INIT` that has not been used to create
+    // a device yet, and it is not used again after being wrapped
+    let device_init = unsafe { DeviceInit::from_raw(device_init) };
+
+    match device_add(&driver, device_init) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status.into(),
+    }
+}
+
+extern "C" fn evt_driver_unload(wdf_driver: WDFDRIVER) {
+    // SAFETY: WDF always passes a valid handle to the framework driver object
+    let driver = unsafe { Driver::from_raw(wdf_driver) };
+    let unload = driver
+        .context::<DriverCallbacks>()
+        .and_then(|callbacks| callbacks.unload)
+        .expect("EvtDriverUnload is only registered when an unload callback is set");
+
+    unload(&driver);
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_TRI_STATE,
+    BOOLEAN,
+    NTSTATUS,
+    STATUS_SUCCESS,
+    ULONG,
+    WDFCONTEXT,
+    WDFDEVICE,
+    WDFINTERRUPT,
+    WDFOBJECT,
+    WDF_INTERRUPT_CONFIG,
+};
+
+use super::{wdf_struct_size, Device, ObjectContext, Ownership, WdfObject};
+use crate::{nt_success, NtResult};
+
+/// Callbacks of an [`Interrupt`].
+///
+/// The implementing type is stored in the context space of the interrupt, so
+/// it can hold any per-interrupt state. [`InterruptCallbacks::isr`],
+/// [`InterruptCallbacks::enable`] and [`InterruptCallbacks::disable`] are
+/// called at the device's IRQL (`DIRQL`) while holding the interrupt's spin
+/// lock, so they must be short and can only access nonpaged memory. Work that
+/// does not need to run at `DIRQL` should be deferred to
+/// [`InterruptCallbacks::dpc`] via [`Interrupt::queue_dpc`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// use wdk::wdf::{Interrupt, InterruptCallbacks, ObjectContext};
+///
+/// #[derive(ObjectContext)]
+/// struct DeviceInterrupt {
+///     pending: AtomicU32,
+/// }
+///
+/// impl InterruptCallbacks for DeviceInterrupt {
+///     fn isr(&self, interrupt: &Interrupt, _message_id: u32) -> bool {
+///         self.pending.fetch_add(1, Ordering::Relaxed);
+///         let _ = interrupt.queue_dpc();
+///         true
+///     }
+///
+///     fn dpc(&self, _interrupt: &Interrupt) {
+///         let _pending = self.pending.swap(0, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait InterruptCallbacks: ObjectContext + Sync {
+    /// `EvtInterruptIsr`: service the interrupt, and return whether it was
+    /// generated by the device. For message-signaled interrupts, `message_id`
+    /// is the index of the message that was received.
+    fn isr(&self, interrupt: &Interrupt, message_id: u32) -> bool;
+
+    /// `EvtInterruptDpc`: finish servicing the interrupt at `DISPATCH_LEVEL`,
+    /// after it was queued by [`Interrupt::queue_dpc`]
+    fn dpc(&self, interrupt: &Interrupt) {
+        let _ = interrupt;
+    }
+
+    /// `EvtInterruptEnable`: enable the device's interrupt after it enters
+    /// its working (D0) power state
+    ///
+    /// # Errors
+    ///
+    /// Returning an error fails the device's power up.
+    fn enable(&self, interrupt: &Interrupt) -> NtResult<()> {
+        let _ = interrupt;
+        Ok(())
+    }
+
+    /// `EvtInterruptDisable`: disable the device's interrupt before it leaves
+    /// its working (D0) power state
+    ///
+    /// # Errors
+    ///
+    /// Returning an error is logged by WDF, but does not stop the power down.
+    fn disable(&self, interrupt: &Interrupt) -> NtResult<()> {
+        let _ = interrupt;
+        Ok(())
+    }
+}
+
+/// WDF Interrupt.
+///
+/// An interrupt belongs to a device, and is connected to the interrupt
+/// resource that the Plug and Play manager assigns to the device when it
+/// starts.
+pub struct Interrupt {
+    wdf_interrupt: WdfObject<WDFINTERRUPT>,
+}
+
+/// Builder for an [`Interrupt`], wrapping the configuration in
+/// [`WDF_INTERRUPT_CONFIG`].
+pub struct InterruptBuilder {
+    share_vector: Option<bool>,
+    floating_save: bool,
+    automatic_serialization: bool,
+}
+
+/// An RAII guard of an [`Interrupt`]'s spin lock, returned by
+/// [`Interrupt::lock`]. The lock is released when the guard is dropped.
+///
+/// While the guard exists, the thread runs at the device's IRQL, and the
+/// interrupt's callbacks cannot run.
+#[must_use = "if unused the interrupt lock will immediately be released"]
+pub struct InterruptLockGuard<'a> {
+    interrupt: &'a Interrupt,
+    // The lock must be released by the thread that acquired it, since acquiring it
+    // raised that thread's IRQL
+    _not_send: PhantomData<*const ()>,
+}
+
+/// The closure passed to [`Interrupt::synchronize`], and its result, shared
+/// with [`evt_interrupt_synchronize`]
+struct SynchronizeState<F, R> {
+    closure: Option<F>,
+    result: Option<R>,
+}
+
+impl Interrupt {
+    /// Returns an [`InterruptBuilder`] to configure and create an interrupt
+    #[must_use]
+    pub const fn builder() -> InterruptBuilder {
+        InterruptBuilder {
+            share_vector: None,
+            floating_save: false,
+            automatic_serialization: false,
+        }
+    }
+
+    /// Queue the interrupt's [`InterruptCallbacks::dpc`], via
+    /// `WdfInterruptQueueDpcForIsr`. This is typically called from
+    /// [`InterruptCallbacks::isr`].
+    ///
+    /// Returns `false` if the DPC was already queued.
+    #[must_use]
+    pub fn queue_dpc(&self) -> bool {
+        let result;
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            result = call_unsafe_wdf_function_binding!(WdfInterruptQueueDpcForIsr, self.as_raw());
+        }
+        result != 0
+    }
+
+    /// Run `closure` at the device's IRQL while holding the interrupt's spin
+    /// lock, via `WdfInterruptSynchronize`, so that it cannot run concurrently
+    /// with the interrupt's callbacks. This must be called at `IRQL` <=
+    /// `DISPATCH_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `WdfInterruptSynchronize` returns without calling `closure`,
+    /// which it never does.
+    pub fn synchronize<F: FnOnce() -> R, R>(&self, closure: F) -> R {
+        let mut state = SynchronizeState {
+            closure: Some(closure),
+            result: None,
+        };
+
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state. `state` outlives the call, and `evt_interrupt_synchronize` is
+        // instantiated for its type.
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfInterruptSynchronize,
+                self.as_raw(),
+                Some(evt_interrupt_synchronize::<F, R>),
+                core::ptr::from_mut(&mut state).cast(),
+            );
+        }
+        state
+            .result
+            .expect("WdfInterruptSynchronize should call its callback exactly once")
+    }
+
+    /// Acquire the interrupt's spin lock, via `WdfInterruptAcquireLock`, and
+    /// return a guard that releases it when dropped. This must not be called
+    /// from the interrupt's own callbacks, which already hold the lock.
+    pub fn lock(&self) -> InterruptLockGuard<'_> {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfInterruptAcquireLock, self.as_raw());
+        }
+
+        InterruptLockGuard {
+            interrupt: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Enable the interrupt, via `WdfInterruptEnable`, which calls
+    /// [`InterruptCallbacks::enable`]. This must be called at `PASSIVE_LEVEL`.
+    pub fn enable(&self) {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfInterruptEnable, self.as_raw());
+        }
+    }
+
+    /// Disable the interrupt, via `WdfInterruptDisable`, which calls
+    /// [`InterruptCallbacks::disable`]. This must be called at `PASSIVE_LEVEL`.
+    pub fn disable(&self) {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfInterruptDisable, self.as_raw());
+        }
+    }
+}
+
+impl Deref for Interrupt {
+    type Target = WdfObject<WDFINTERRUPT>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_interrupt
+    }
+}
+
+impl DerefMut for Interrupt {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_interrupt
+    }
+}
+
+impl Drop for InterruptLockGuard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: The interrupt is valid for the lifetime of the guard, and its lock
+        // was acquired by this thread when the guard was created
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfInterruptReleaseLock, self.interrupt.as_raw());
+        }
+    }
+}
+
+impl InterruptBuilder {
+    /// Set whether the interrupt vector can be shared with other devices. By
+    /// default, this is determined by the interrupt resource assigned to the
+    /// device.
+    #[must_use]
+    pub const fn share_vector(mut self, share_vector: bool) -> Self {
+        self.share_vector = Some(share_vector);
+        self
+    }
+
+    /// Set whether the system saves the floating point state of the processor
+    /// before calling [`InterruptCallbacks::isr`]. This is only supported on
+    /// x86.
+    #[must_use]
+    pub const fn floating_save(mut self, floating_save: bool) -> Self {
+        self.floating_save = floating_save;
+        self
+    }
+
+    /// Set whether [`InterruptCallbacks::dpc`] is synchronized with the
+    /// callbacks of the device, according to its synchronization scope
+    #[must_use]
+    pub const fn automatic_serialization(mut self, automatic_serialization: bool) -> Self {
+        self.automatic_serialization = automatic_serialization;
+        self
+    }
+
+    /// Create the interrupt for `device` via `WdfInterruptCreate`, storing
+    /// `callbacks` in its context space. The interrupt is deleted along with
+    /// its device.
+    ///
+    /// This must be called from `EvtDriverDeviceAdd`, after the device has
+    /// been created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the interrupt. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfInterruptCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfinterrupt/nf-wdfinterrupt-wdfinterruptcreate#return-value)
+    pub fn create<T: InterruptCallbacks>(
+        self,
+        device: &Device,
+        callbacks: T,
+    ) -> NtResult<Interrupt> {
+        let mut interrupt_config = WDF_INTERRUPT_CONFIG {
+            Size: wdf_struct_size::<WDF_INTERRUPT_CONFIG>(),
+            ShareVector: match self.share_vector {
+                Some(true) => _WDF_TRI_STATE::WdfTrue,
+                Some(false) => _WDF_TRI_STATE::WdfFalse,
+                None => _WDF_TRI_STATE::WdfUseDefault,
+            },
+            FloatingSave: u8::from(self.floating_save),
+            AutomaticSerialization: u8::from(self.automatic_serialization),
+            EvtInterruptIsr: Some(evt_interrupt_isr::<T>),
+            EvtInterruptDpc: Some(evt_interrupt_dpc::<T>),
+            EvtInterruptEnable: Some(evt_interrupt_enable::<T>),
+            EvtInterruptDisable: Some(evt_interrupt_disable::<T>),
+            ReportInactiveOnPowerDown: _WDF_TRI_STATE::WdfUseDefault,
+            ..WDF_INTERRUPT_CONFIG::default()
+        };
+        let mut interrupt_attributes = T::object_attributes();
+        let mut wdf_interrupt: WDFINTERRUPT = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `device` keeps its device alive, and `interrupt_config` and
+        // `interrupt_attributes` are valid and fully initialized
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfInterruptCreate,
+                device.as_raw(),
+                &mut interrupt_config,
+                &mut interrupt_attributes,
+                &mut wdf_interrupt,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let mut interrupt = Interrupt {
+            // SAFETY: `wdf_interrupt` was just successfully created by WDF, and
+            // interrupts are always parented to their device
+            wdf_interrupt: unsafe { WdfObject::from_raw(wdf_interrupt, Ownership::Parent) },
+        };
+        // `T` is always present since it was set in `interrupt_attributes`.
+        // SAFETY: The callbacks cannot run until WDF connects the interrupt when the
+        // device enters D0, which cannot happen during the `EvtDriverDeviceAdd` that
+        // creates it.
+        if unsafe { interrupt.init_context(callbacks) }.is_err() {
+            unreachable!("interrupt should have a context space for its callbacks");
+        }
+        Ok(interrupt)
+    }
+}
+
+/// Wrap the interrupt handed to a WDF callback, and call `callback` with the
+/// interrupt's callbacks. Returns [`None`] if the callbacks have not been
+/// stored yet.
+///
+/// The interrupt is not referenced, since most interrupt callbacks run at
+/// `DIRQL`, where `WdfObjectReference` cannot be called. WDF keeps the
+/// interrupt alive for the duration of its callbacks.
+fn with_callbacks<T: InterruptCallbacks, R>(
+    wdf_interrupt: WDFINTERRUPT,
+    callback: impl FnOnce(&T, &Interrupt) -> R,
+) -> Option<R> {
+    let interrupt = ManuallyDrop::new(Interrupt {
+        // SAFETY: WDF passes a valid handle to the interrupt. Wrapping it as
+        // driver-owned does not reference it, and `ManuallyDrop` ensures that it is
+        // never deleted through this `WdfObject`.
+        wdf_interrupt: unsafe { WdfObject::from_raw(wdf_interrupt, Ownership::Driver) },
+    });
+
+    interrupt
+        .context::<T>()
+        .map(|callbacks| callback(callbacks, &interrupt))
+}
+
+extern "C" fn evt_interrupt_isr<T: InterruptCallbacks>(
+    wdf_interrupt: WDFINTERRUPT,
+    message_id: ULONG,
+) -> BOOLEAN {
+    let is_device_interrupt = with_callbacks::<T, _>(wdf_interrupt, |callbacks, interrupt| {
+        callbacks.isr(interrupt, message_id)
+    });
+    // An interrupt received before the callbacks are stored cannot be serviced, so
+    // it is reported as not generated by the device
+    u8::from(is_device_interrupt.unwrap_or(false))
+}
+
+extern "C" fn evt_interrupt_dpc<T: InterruptCallbacks>(
+    wdf_interrupt: WDFINTERRUPT,
+    _associated_object: WDFOBJECT,
+) {
+    with_callbacks::<T, _>(wdf_interrupt, |callbacks, interrupt| {
+        callbacks.dpc(interrupt);
+    });
+}
+
+extern "C" fn evt_interrupt_enable<T: InterruptCallbacks>(
+    wdf_interrupt: WDFINTERRUPT,
+    _associated_device: WDFDEVICE,
+) -> NTSTATUS {
+    match with_callbacks::<T, _>(wdf_interrupt, |callbacks, interrupt| {
+        callbacks.enable(interrupt)
+    }) {
+        Some(Err(nt_status)) => nt_status.into(),
+        Some(Ok(())) | None => STATUS_SUCCESS,
+    }
+}
+
+extern "C" fn evt_interrupt_disable<T: InterruptCallbacks>(
+    wdf_interrupt: WDFINTERRUPT,
+    _associated_device: WDFDEVICE,
+) -> NTSTATUS {
+    match with_callbacks::<T, _>(wdf_interrupt, |callbacks, interrupt| {
+        callbacks.disable(interrupt)
+    }) {
+        Some(Err(nt_status)) => nt_status.into(),
+        Some(Ok(())) | None => STATUS_SUCCESS,
+    }
+}
+
+extern "C" fn evt_interrupt_synchronize<F: FnOnce() -> R, R>(
+    _wdf_interrupt: WDFINTERRUPT,
+    context: WDFCONTEXT,
+) -> BOOLEAN {
+    // SAFETY: `context` is the `SynchronizeState<F, R>` passed by
+    // `Interrupt::synchronize`, which is exclusively borrowed for the duration of
+    // `WdfInterruptSynchronize`
+    let state = unsafe { &mut *context.cast::<SynchronizeState<F, R>>() };
+    if let Some(closure) = state.closure.take() {
+        state.result = Some(closure());
+    }
+    u8::from(true)
+}
@@ -0,0 +1,358 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::ops::{Deref, DerefMut};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_IO_QUEUE_DISPATCH_TYPE,
+    _WDF_TRI_STATE,
+    STATUS_INVALID_DEVICE_REQUEST,
+    STATUS_INVALID_DEVICE_STATE,
+    ULONG,
+    WDFQUEUE,
+    WDFREQUEST,
+    WDF_IO_QUEUE_CONFIG,
+    WDF_IO_QUEUE_DISPATCH_TYPE,
+};
+
+use super::{wdf_struct_size, Device, ObjectContext, Ownership, Request, WdfObject};
+use crate::{nt_success, NtResult};
+
+/// How an [`IoQueue`] delivers requests to the driver. This is the equivalent
+/// of `WDF_IO_QUEUE_DISPATCH_TYPE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispatchType {
+    /// Requests are delivered one at a time, and the next request is only
+    /// delivered once the previous one has been completed
+    Sequential,
+    /// Requests are delivered as soon as they arrive, so the callbacks can be
+    /// invoked concurrently
+    Parallel,
+    /// Requests are never delivered, and must be retrieved by the driver via
+    /// [`IoQueue::retrieve_next_request`]
+    Manual,
+}
+
+impl DispatchType {
+    const fn as_raw(self) -> WDF_IO_QUEUE_DISPATCH_TYPE {
+        match self {
+            Self::Sequential => _WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchSequential,
+            Self::Parallel => _WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel,
+            Self::Manual => _WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchManual,
+        }
+    }
+}
+
+/// Request handlers of an [`IoQueue`].
+///
+/// The implementing type is stored in the context space of the queue, so it
+/// can hold any per-queue state. Every [`Request`] passed to a handler must be
+/// completed, forwarded, or converted into a raw handle. The default handlers
+/// complete the request with `STATUS_INVALID_DEVICE_REQUEST`, which is what
+/// WDF does for request types without a registered handler.
+///
+/// Handlers of a [`DispatchType::Parallel`] queue can be invoked concurrently.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::wdf::{IoQueue, IoQueueCallbacks, ObjectContext, Request};
+/// use wdk_sys::STATUS_SUCCESS;
+///
+/// #[derive(ObjectContext)]
+/// struct EchoQueue;
+///
+/// impl IoQueueCallbacks for EchoQueue {
+///     fn write(&self, _queue: &IoQueue, request: Request, length: usize) {
+///         request.complete(STATUS_SUCCESS.into(), length);
+///     }
+/// }
+/// ```
+pub trait IoQueueCallbacks: ObjectContext + Sync {
+    /// `EvtIoRead`: handle a read request of `length` bytes
+    fn read(&self, queue: &IoQueue, request: Request, length: usize) {
+        let _ = (queue, length);
+        request.complete(STATUS_INVALID_DEVICE_REQUEST.into(), 0);
+    }
+
+    /// `EvtIoWrite`: handle a write request of `length` bytes
+    fn write(&self, queue: &IoQueue, request: Request, length: usize) {
+        let _ = (queue, length);
+        request.complete(STATUS_INVALID_DEVICE_REQUEST.into(), 0);
+    }
+
+    /// `EvtIoDeviceControl`: handle a device I/O control request
+    fn device_control(
+        &self,
+        queue: &IoQueue,
+        request: Request,
+        output_buffer_length: usize,
+        input_buffer_length: usize,
+        io_control_code: u32,
+    ) {
+        let _ = (
+            queue,
+            output_buffer_length,
+            input_buffer_length,
+            io_control_code,
+        );
+        request.complete(STATUS_INVALID_DEVICE_REQUEST.into(), 0);
+    }
+}
+
+/// WDF I/O Queue.
+pub struct IoQueue {
+    wdf_queue: WdfObject<WDFQUEUE>,
+}
+
+/// Builder for an [`IoQueue`], wrapping the configuration in
+/// [`WDF_IO_QUEUE_CONFIG`].
+pub struct IoQueueBuilder {
+    dispatch_type: DispatchType,
+    default_queue: bool,
+    power_managed: Option<bool>,
+    allow_zero_length_requests: bool,
+}
+
+impl IoQueue {
+    /// Returns an [`IoQueueBuilder`] to configure and create a queue with
+    /// `dispatch_type`
+    #[must_use]
+    pub const fn builder(dispatch_type: DispatchType) -> IoQueueBuilder {
+        IoQueueBuilder {
+            dispatch_type,
+            default_queue: false,
+            power_managed: None,
+            allow_zero_length_requests: false,
+        }
+    }
+
+    /// Wrap a queue handed to a WDF callback
+    ///
+    /// # Safety
+    ///
+    /// `wdf_queue` must be a valid handle to a queue created by the driver
+    unsafe fn from_raw(wdf_queue: WDFQUEUE) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_queue` is valid, and queues are always
+            // parented to their device
+            wdf_queue: unsafe { WdfObject::from_raw(wdf_queue, Ownership::Parent) },
+        }
+    }
+
+    /// Returns the device that the queue belongs to, via
+    /// `WdfIoQueueGetDevice`
+    #[must_use]
+    pub fn device(&self) -> Device {
+        let wdf_device;
+        // SAFETY: `wdf_queue` is a private member of `IoQueue`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            wdf_device = call_unsafe_wdf_function_binding!(WdfIoQueueGetDevice, self.as_raw());
+        }
+        // SAFETY: `WdfIoQueueGetDevice` always returns the valid device that the queue
+        // belongs to
+        unsafe { Device::from_raw(wdf_device) }
+    }
+
+    /// Start delivering requests from the queue, via `WdfIoQueueStart`
+    pub fn start(&self) {
+        // SAFETY: `wdf_queue` is a private member of `IoQueue`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfIoQueueStart, self.as_raw());
+        }
+    }
+
+    /// Take the next request out of a [`DispatchType::Manual`] queue, via
+    /// `WdfIoQueueRetrieveNextRequest`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the queue has no requests, or WDF fails to retrieve one. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfIoQueueRetrieveNextRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfio/nf-wdfio-wdfioqueueretrievenextrequest#return-value)
+    pub fn retrieve_next_request(&self) -> NtResult<Request> {
+        let mut wdf_request: WDFREQUEST = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_queue` is a private member of `IoQueue`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfIoQueueRetrieveNextRequest,
+                self.as_raw(),
+                &mut wdf_request,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: WDF transferred ownership of the uncompleted request to the driver
+        Ok(unsafe { Request::from_raw(wdf_request) })
+    }
+}
+
+impl Deref for IoQueue {
+    type Target = WdfObject<WDFQUEUE>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_queue
+    }
+}
+
+impl DerefMut for IoQueue {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_queue
+    }
+}
+
+impl IoQueueBuilder {
+    /// Make the queue the device's default queue, which receives all requests
+    /// that are not configured to be dispatched to another queue
+    #[must_use]
+    pub const fn default_queue(mut self, default_queue: bool) -> Self {
+        self.default_queue = default_queue;
+        self
+    }
+
+    /// Set whether the queue is stopped while the device is not in its
+    /// working (D0) power state. By default, WDF power manages the queues of
+    /// devices that are the power policy owner.
+    #[must_use]
+    pub const fn power_managed(mut self, power_managed: bool) -> Self {
+        self.power_managed = Some(power_managed);
+        self
+    }
+
+    /// Set whether read and write requests with a length of zero are delivered
+    /// to the driver, instead of being completed by WDF
+    #[must_use]
+    pub const fn allow_zero_length_requests(mut self, allow_zero_length_requests: bool) -> Self {
+        self.allow_zero_length_requests = allow_zero_length_requests;
+        self
+    }
+
+    /// Create the queue for `device` via `WdfIoQueueCreate`, storing
+    /// `callbacks` in its context space. The queue is deleted along with its
+    /// device.
+    ///
+    /// A queue of a device that has already started can be presented requests
+    /// before `WdfIoQueueCreate` returns, and so before `callbacks` is stored.
+    /// Such requests are completed with `STATUS_INVALID_DEVICE_STATE`. Create
+    /// default queues from [`Driver::builder`](super::Driver::builder)'s
+    /// `device_add` callback, before the device starts, to avoid this.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the queue. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfIoQueueCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfio/nf-wdfio-wdfioqueuecreate#return-value)
+    pub fn create<T: IoQueueCallbacks>(self, device: &Device, callbacks: T) -> NtResult<IoQueue> {
+        let dispatches_requests = self.dispatch_type != DispatchType::Manual;
+        let mut queue_config = WDF_IO_QUEUE_CONFIG {
+            Size: wdf_struct_size::<WDF_IO_QUEUE_CONFIG>(),
+            DispatchType: self.dispatch_type.as_raw(),
+            PowerManaged: match self.power_managed {
+                Some(true) => _WDF_TRI_STATE::WdfTrue,
+                Some(false) => _WDF_TRI_STATE::WdfFalse,
+                None => _WDF_TRI_STATE::WdfUseDefault,
+            },
+            AllowZeroLengthRequests: u8::from(self.allow_zero_length_requests),
+            DefaultQueue: u8::from(self.default_queue),
+            EvtIoRead: dispatches_requests.then_some(evt_io_read::<T>),
+            EvtIoWrite: dispatches_requests.then_some(evt_io_write::<T>),
+            EvtIoDeviceControl: dispatches_requests.then_some(evt_io_device_control::<T>),
+            ..WDF_IO_QUEUE_CONFIG::default()
+        };
+        if self.dispatch_type == DispatchType::Parallel {
+            // Equivalent to `WDF_IO_QUEUE_CONFIG_INIT`, which allows an unlimited number of
+            // requests to be presented to the driver at once
+            queue_config.Settings.Parallel.NumberOfPresentedRequests = ULONG::MAX;
+        }
+        let mut queue_attributes = T::object_attributes();
+        let mut wdf_queue: WDFQUEUE = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `device` keeps its device alive, and `queue_config` and
+        // `queue_attributes` are valid and fully initialized
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfIoQueueCreate,
+                device.as_raw(),
+                &mut queue_config,
+                &mut queue_attributes,
+                &mut wdf_queue,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: `wdf_queue` was just successfully created by WDF
+        let queue = unsafe { IoQueue::from_raw(wdf_queue) };
+        // SAFETY: The queue was just created, so nothing else has stored its context,
+        // and request handlers only read it
+        if unsafe { queue.publish_context(callbacks) }.is_err() {
+            // `T` is always present since it was set in `queue_attributes`
+            unreachable!("queue should have a context space for its callbacks");
+        }
+        Ok(queue)
+    }
+}
+
+/// Wrap the arguments of a request handler, and call `handler` with the
+/// queue's callbacks. If the queue's callbacks have not been stored yet, the
+/// request is completed with `STATUS_INVALID_DEVICE_STATE`.
+fn dispatch<T: IoQueueCallbacks>(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    handler: impl FnOnce(&T, &IoQueue, Request),
+) {
+    // SAFETY: WDF always passes a valid queue to request handlers
+    let queue = unsafe { IoQueue::from_raw(wdf_queue) };
+    // SAFETY: WDF transfers ownership of the uncompleted request to the driver when
+    // calling request handlers
+    let request = unsafe { Request::from_raw(wdf_request) };
+
+    match queue.context::<T>() {
+        Some(callbacks) => handler(callbacks, &queue, request),
+        None => request.complete(STATUS_INVALID_DEVICE_STATE.into(), 0),
+    }
+}
+
+extern "C" fn evt_io_read<T: IoQueueCallbacks>(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    length: usize,
+) {
+    dispatch::<T>(wdf_queue, wdf_request, |callbacks, queue, request| {
+        callbacks.read(queue, request, length);
+    });
+}
+
+extern "C" fn evt_io_write<T: IoQueueCallbacks>(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    length: usize,
+) {
+    dispatch::<T>(wdf_queue, wdf_request, |callbacks, queue, request| {
+        callbacks.write(queue, request, length);
+    });
+}
+
+extern "C" fn evt_io_device_control<T: IoQueueCallbacks>(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    output_buffer_length: usize,
+    input_buffer_length: usize,
+    io_control_code: ULONG,
+) {
+    dispatch::<T>(wdf_queue, wdf_request, |callbacks, queue, request| {
+        callbacks.device_control(
+            queue,
+            request,
+            output_buffer_length,
+            input_buffer_length,
+            io_control_code,
+        );
+    });
+}
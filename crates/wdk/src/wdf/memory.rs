@@ -0,0 +1,376 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _POOL_TYPE,
+    PVOID,
+    STATUS_INVALID_BUFFER_SIZE,
+    WDFMEMORY,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use super::{Ownership, Request, WdfObject};
+use crate::{nt_success, NtResult};
+
+/// WDF Memory.
+///
+/// A memory object owns a buffer that can be passed to other WDF APIs (ex. to
+/// format requests for I/O targets) by handle, via [`WdfObject::as_raw`]. The
+/// buffer is accessed through [`Memory::as_slice`] and
+/// [`Memory::as_mut_slice`], or copied to and from with
+/// [`Memory::copy_to_slice`] and [`Memory::copy_from_slice`].
+///
+/// The buffers of a [`Request`] can also be retrieved as memory objects, via
+/// [`Request::input_memory`] and [`Request::output_memory`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{object_attributes, Memory},
+///     NtResult,
+/// };
+///
+/// fn build_command() -> NtResult<Memory> {
+///     let mut command = Memory::try_new(&mut object_attributes(), 64)?;
+///     command.as_mut_slice()[0] = 0x01;
+///     command.copy_from_slice(1, &[0x12, 0x34])?;
+///     Ok(command)
+/// }
+/// ```
+pub struct Memory {
+    wdf_memory: WdfObject<WDFMEMORY>,
+}
+
+/// The input buffer of a [`Request`] as a [`Memory`], returned by
+/// [`Request::input_memory`].
+///
+/// The memory object belongs to the request, so it is borrowed from it and
+/// only gives immutable access to the buffer.
+pub struct RequestMemory<'a> {
+    memory: Memory,
+    _marker: PhantomData<&'a Request>,
+}
+
+/// The output buffer of a [`Request`] as a [`Memory`], returned by
+/// [`Request::output_memory`].
+///
+/// The memory object belongs to the request, so it is mutably borrowed from
+/// it. Unlike [`RequestMemory`], this does not dereference mutably to a
+/// [`Memory`], which could otherwise be moved out of the borrow of the request
+/// (ex. via [`core::mem::swap`]) and outlive it. The buffer is instead written
+/// through [`RequestMemoryMut::as_mut_slice`] and
+/// [`RequestMemoryMut::copy_from_slice`].
+pub struct RequestMemoryMut<'a> {
+    memory: Memory,
+    _marker: PhantomData<&'a mut Request>,
+}
+
+impl Memory {
+    /// Try to allocate a memory object with a buffer of `size` bytes from
+    /// nonpaged pool, via `WdfMemoryCreate`. The buffer is tagged with the
+    /// driver's pool tag. WDF does not initialize the buffer, so it is zeroed
+    /// before being returned.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the memory object. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycreate#return-value)
+    pub fn try_new(attributes: &mut WDF_OBJECT_ATTRIBUTES, size: usize) -> NtResult<Self> {
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_memory: WDFMEMORY = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state. A pool tag of 0 selects the driver's pool tag.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfMemoryCreate,
+                attributes,
+                _POOL_TYPE::NonPagedPoolNx,
+                0,
+                size,
+                &mut wdf_memory,
+                core::ptr::null_mut(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let memory = Self {
+            // SAFETY: `wdf_memory` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_memory: unsafe { WdfObject::from_raw(wdf_memory, ownership) },
+        };
+
+        let (buffer, length) = memory.buffer();
+        // SAFETY: The buffer of a memory object is valid for writes of `length`
+        // bytes, and nothing else can access it since the memory object was just
+        // created. Zeroing it ensures that it is never read uninitialized through
+        // `as_slice`.
+        unsafe {
+            core::ptr::write_bytes(buffer.cast::<u8>(), 0, length);
+        }
+        Ok(memory)
+    }
+
+    /// Try to allocate a memory object with a buffer of `size` bytes from
+    /// nonpaged pool. This is an alias for [`Memory::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the memory object. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycreate#return-value)
+    pub fn create(attributes: &mut WDF_OBJECT_ATTRIBUTES, size: usize) -> NtResult<Self> {
+        Self::try_new(attributes, size)
+    }
+
+    /// Try to create a memory object for the existing `buffer`, via
+    /// `WdfMemoryCreatePreallocated`. Since WDF may access the buffer for as
+    /// long as the memory object exists, it must be borrowed for `'static`
+    /// (ex. a buffer allocated with [`Box::leak`]).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the memory object. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCreatePreallocated Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycreatepreallocated#return-value)
+    ///
+    /// [`Box::leak`]: https://doc.rust-lang.org/alloc/boxed/struct.Box.html#method.leak
+    pub fn try_new_preallocated(
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        buffer: &'static mut [u8],
+    ) -> NtResult<Self> {
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_memory: WDFMEMORY = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state. `buffer` is exclusively borrowed for the rest of
+        // the program, so it outlives the memory object.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfMemoryCreatePreallocated,
+                attributes,
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                &mut wdf_memory,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(Self {
+            // SAFETY: `wdf_memory` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_memory: unsafe { WdfObject::from_raw(wdf_memory, ownership) },
+        })
+    }
+
+    /// Returns the length of the buffer in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffer().1
+    }
+
+    /// Returns `true` if the buffer has a length of zero
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the contents of the buffer
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        let (buffer, length) = self.buffer();
+        if length == 0 {
+            return &[];
+        }
+
+        // SAFETY: The buffer of a memory object is valid for `length` bytes for as
+        // long as the memory object, which is kept alive by `self`
+        unsafe { core::slice::from_raw_parts(buffer.cast::<u8>(), length) }
+    }
+
+    /// Returns the contents of the buffer mutably
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        let (buffer, length) = self.buffer();
+        if length == 0 {
+            return &mut [];
+        }
+
+        // SAFETY: The buffer of a memory object is valid for `length` bytes for as
+        // long as the memory object, which is kept alive by `self`. The mutable
+        // borrow of `self` guarantees that this is the only reference to the buffer.
+        unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), length) }
+    }
+
+    /// Copy `buffer.len()` bytes starting at `offset` in the memory object into
+    /// `buffer`, via `WdfMemoryCopyToBuffer`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INVALID_BUFFER_SIZE` if the range to copy is not within the memory object, or an error if WDF fails to copy it. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCopyToBuffer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycopytobuffer#return-value)
+    pub fn copy_to_slice(&self, offset: usize, buffer: &mut [u8]) -> NtResult<()> {
+        self.check_range(offset, buffer.len())?;
+
+        let nt_status;
+        // SAFETY: `wdf_memory` is a private member of `Memory`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state. `buffer`
+        // is valid for writes of `buffer.len()` bytes.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfMemoryCopyToBuffer,
+                self.as_raw(),
+                offset,
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+
+    /// Copy `buffer` into the memory object, starting at `offset`, via
+    /// `WdfMemoryCopyFromBuffer`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INVALID_BUFFER_SIZE` if the range to copy is not within the memory object, or an error if WDF fails to copy it. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCopyFromBuffer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycopyfrombuffer#return-value)
+    pub fn copy_from_slice(&mut self, offset: usize, buffer: &[u8]) -> NtResult<()> {
+        self.check_range(offset, buffer.len())?;
+
+        let nt_status;
+        // SAFETY: `wdf_memory` is a private member of `Memory`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state. `buffer`
+        // is valid for reads of `buffer.len()` bytes, and WDF only reads from it.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfMemoryCopyFromBuffer,
+                self.as_raw(),
+                offset,
+                buffer.as_ptr().cast_mut().cast(),
+                buffer.len(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the buffer of the memory object and its length, via
+    /// `WdfMemoryGetBuffer`
+    fn buffer(&self) -> (PVOID, usize) {
+        let mut length = 0;
+        let buffer;
+        // SAFETY: `wdf_memory` is a private member of `Memory`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            buffer =
+                call_unsafe_wdf_function_binding!(WdfMemoryGetBuffer, self.as_raw(), &mut length);
+        }
+        (buffer, length)
+    }
+
+    /// Check that the `length` bytes starting at `offset` are within the buffer
+    fn check_range(&self, offset: usize, length: usize) -> NtResult<()> {
+        match offset.checked_add(length) {
+            Some(end) if end <= self.len() => Ok(()),
+            _ => Err(STATUS_INVALID_BUFFER_SIZE.into()),
+        }
+    }
+}
+
+impl Deref for Memory {
+    type Target = WdfObject<WDFMEMORY>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_memory
+    }
+}
+
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_memory
+    }
+}
+
+impl RequestMemory<'_> {
+    /// Wrap the memory object of a request's input buffer
+    ///
+    /// # Safety
+    ///
+    /// `wdf_memory` must be a valid handle to the input memory of a request
+    /// that is borrowed for the lifetime of the returned value
+    pub(super) unsafe fn from_raw(wdf_memory: WDFMEMORY) -> Self {
+        Self {
+            memory: Memory {
+                // SAFETY: The caller guarantees `wdf_memory` is valid. It belongs to its
+                // request, so the driver must never delete it.
+                wdf_memory: unsafe { WdfObject::from_raw(wdf_memory, Ownership::Framework) },
+            },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Deref for RequestMemory<'_> {
+    type Target = Memory;
+
+    fn deref(&self) -> &Self::Target {
+        &self.memory
+    }
+}
+
+impl RequestMemoryMut<'_> {
+    /// Wrap the memory object of a request's output buffer
+    ///
+    /// # Safety
+    ///
+    /// `wdf_memory` must be a valid handle to the output memory of a request
+    /// that is mutably borrowed for the lifetime of the returned value
+    pub(super) unsafe fn from_raw(wdf_memory: WDFMEMORY) -> Self {
+        Self {
+            memory: Memory {
+                // SAFETY: The caller guarantees `wdf_memory` is valid. It belongs to its
+                // request, so the driver must never delete it.
+                wdf_memory: unsafe { WdfObject::from_raw(wdf_memory, Ownership::Framework) },
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the contents of the buffer mutably
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.memory.as_mut_slice()
+    }
+
+    /// Copy `buffer` into the memory object, starting at `offset`, via
+    /// `WdfMemoryCopyFromBuffer`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_INVALID_BUFFER_SIZE` if the range to copy is not within the memory object, or an error if WDF fails to copy it. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfMemoryCopyFromBuffer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfmemory/nf-wdfmemory-wdfmemorycopyfrombuffer#return-value)
+    pub fn copy_from_slice(&mut self, offset: usize, buffer: &[u8]) -> NtResult<()> {
+        self.memory.copy_from_slice(offset, buffer)
+    }
+}
+
+impl Deref for RequestMemoryMut<'_> {
+    type Target = Memory;
+
+    fn deref(&self) -> &Self::Target {
+        &self.memory
+    }
+}
@@ -3,8 +3,39 @@
 
 //! Safe abstractions over WDF APIs
 
+pub use context::*;
+pub use device::*;
+#[cfg(driver_model__driver_type = "KMDF")]
+pub use dma::*;
+#[cfg(driver_model__driver_type = "KMDF")]
+pub use dpc::*;
+pub use driver::*;
+#[cfg(driver_model__driver_type = "KMDF")]
+pub use interrupt::*;
+pub use io_queue::*;
+pub use memory::*;
+pub use object::*;
+pub use request::*;
 pub use spinlock::*;
 pub use timer::*;
+pub use wait_lock::*;
+pub use work_item::*;
 
+mod callback;
+mod context;
+mod device;
+#[cfg(driver_model__driver_type = "KMDF")]
+mod dma;
+#[cfg(driver_model__driver_type = "KMDF")]
+mod dpc;
+mod driver;
+#[cfg(driver_model__driver_type = "KMDF")]
+mod interrupt;
+mod io_queue;
+mod memory;
+mod object;
+mod request;
 mod spinlock;
 mod timer;
+mod wait_lock;
+mod work_item;
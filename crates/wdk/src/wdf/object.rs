@@ -0,0 +1,290 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::mem::ManuallyDrop;
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    _WDF_EXECUTION_LEVEL,
+    _WDF_SYNCHRONIZATION_SCOPE,
+    LONG,
+    ULONG,
+    WDFDEVICE,
+    WDFDRIVER,
+    WDFFILEOBJECT,
+    WDFMEMORY,
+    WDFOBJECT,
+    WDFQUEUE,
+    WDFSPINLOCK,
+    WDFTIMER,
+    WDFWAITLOCK,
+    WDFWORKITEM,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(driver_model__driver_type = "KMDF")]
+use wdk_sys::{WDFCOMMONBUFFER, WDFDMAENABLER, WDFDMATRANSACTION, WDFDPC, WDFINTERRUPT};
+
+/// A raw handle to a WDF object (ex. [`WDFDEVICE`], [`WDFTIMER`]).
+///
+/// All WDF object handles can be passed to the generic `WdfObject*` family of
+/// APIs, which accept a [`WDFOBJECT`]. This trait provides that conversion so
+/// that [`WdfObject`] can manage the lifetime of any WDF object type.
+///
+/// # Safety
+///
+/// Implementors must be handle types produced by WDF for a framework object,
+/// such that the value returned by [`WdfHandle::as_wdf_object`] is a valid
+/// [`WDFOBJECT`] for as long as the handle itself is valid.
+pub unsafe trait WdfHandle: Copy {
+    /// Returns this handle as a generic [`WDFOBJECT`]
+    fn as_wdf_object(self) -> WDFOBJECT;
+}
+
+macro_rules! impl_wdf_handle {
+    ($($handle:ty),+ $(,)?) => {
+        $(
+            // SAFETY: `$handle` is declared via `DECLARE_HANDLE` in the WDF headers, and
+            // every such handle is also a valid `WDFOBJECT`.
+            unsafe impl WdfHandle for $handle {
+                fn as_wdf_object(self) -> WDFOBJECT {
+                    self.cast()
+                }
+            }
+        )+
+    };
+}
+
+impl_wdf_handle!(
+    WDFDEVICE,
+    WDFDRIVER,
+    WDFFILEOBJECT,
+    WDFMEMORY,
+    WDFQUEUE,
+    WDFSPINLOCK,
+    WDFTIMER,
+    WDFWAITLOCK,
+    WDFWORKITEM,
+);
+
+#[cfg(driver_model__driver_type = "KMDF")]
+impl_wdf_handle!(
+    WDFCOMMONBUFFER,
+    WDFDMAENABLER,
+    WDFDMATRANSACTION,
+    WDFDPC,
+    WDFINTERRUPT,
+);
+
+/// Returns a [`WDF_OBJECT_ATTRIBUTES`] with default values.
+///
+/// This is initialized the same way as the `WDF_OBJECT_ATTRIBUTES_INIT`
+/// function in the WDF headers, so its execution level and synchronization
+/// scope are inherited from the parent object.
+#[must_use]
+pub fn object_attributes() -> WDF_OBJECT_ATTRIBUTES {
+    WDF_OBJECT_ATTRIBUTES {
+        Size: wdf_struct_size::<WDF_OBJECT_ATTRIBUTES>(),
+        ExecutionLevel: _WDF_EXECUTION_LEVEL::WdfExecutionLevelInheritFromParent,
+        SynchronizationScope: _WDF_SYNCHRONIZATION_SCOPE::WdfSynchronizationScopeInheritFromParent,
+        ..WDF_OBJECT_ATTRIBUTES::default()
+    }
+}
+
+/// Returns the size of `T` as a [`ULONG`], for use in the `Size` field that
+/// most WDF structures start with.
+///
+/// # Panics
+///
+/// Panics if the size of `T` does not fit in a [`ULONG`]
+pub(super) const fn wdf_struct_size<T>() -> ULONG {
+    let size = core::mem::size_of::<T>();
+    assert!(size <= ULONG::MAX as usize);
+
+    // clippy::cast_possible_truncation cannot currently check compile-time constants: https://github.com/rust-lang/rust-clippy/issues/9613
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        size as ULONG
+    }
+}
+
+/// Describes who is responsible for deleting a WDF object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ownership {
+    /// The driver created the object without an explicit parent, and WDF
+    /// parented it to the driver object (ex. a `WDFMEMORY` or `WDFSPINLOCK`),
+    /// so the driver is responsible for deleting it. WDF would otherwise only
+    /// delete it when the driver unloads. The object is deleted via
+    /// `WdfObjectDelete` when the [`WdfObject`] is dropped.
+    ///
+    /// Objects that WDF parents to another object by default (ex. a common
+    /// buffer, which is parented to its DMA enabler) are
+    /// [`Ownership::Parent`], even without an explicit parent.
+    Driver,
+    /// The driver created the object with a parent other than the driver
+    /// object, either an explicit `ParentObject` in its
+    /// [`WDF_OBJECT_ATTRIBUTES`] or a default parent chosen by WDF, so the
+    /// framework deletes it when the parent is deleted. The driver may still
+    /// delete it earlier via [`WdfObject::delete`]. The [`WdfObject`] holds
+    /// a reference on the object, so its handle remains valid even if the
+    /// parent is deleted first.
+    Parent,
+    /// The framework created the object (ex. a `WDFDEVICE` passed to
+    /// `EvtDriverDeviceAdd`) and controls its lifetime. The driver must never
+    /// delete it. The [`WdfObject`] holds a reference on the object, so its
+    /// handle remains valid for as long as the [`WdfObject`] exists.
+    Framework,
+}
+
+impl Ownership {
+    /// Determine the [`Ownership`] of an object that the driver is creating
+    /// with `attributes`, based off of whether a `ParentObject` was specified.
+    /// This is only correct for object types that WDF parents to the driver
+    /// object when no `ParentObject` is specified.
+    #[must_use]
+    pub fn from_attributes(attributes: &WDF_OBJECT_ATTRIBUTES) -> Self {
+        if attributes.ParentObject.is_null() {
+            Self::Driver
+        } else {
+            Self::Parent
+        }
+    }
+}
+
+/// An owned handle to a WDF object.
+///
+/// [`WdfObject`] ties the lifetime of a WDF object to a Rust value according
+/// to its [`Ownership`]:
+/// * [`Ownership::Driver`] objects are deleted when the [`WdfObject`] is
+///   dropped, so that they do not leak until the driver unloads.
+/// * [`Ownership::Parent`] and [`Ownership::Framework`] objects are referenced
+///   via `WdfObjectReference` when wrapped and dereferenced via
+///   `WdfObjectDereference` when dropped, so the handle can never dangle, and
+///   the object is never deleted twice.
+///
+/// All safe wrappers in [`crate::wdf`] store their handle in a [`WdfObject`].
+#[derive(Debug)]
+pub struct WdfObject<H: WdfHandle> {
+    handle: H,
+    ownership: Ownership,
+}
+
+// SAFETY: WDF object handles can be used from any thread, and the reference
+// held on the object is released through `WdfObjectDereference` or
+// `WdfObjectDelete`, which can also be called from any thread
+unsafe impl<H: WdfHandle> Send for WdfObject<H> {}
+// SAFETY: WDF functions can be called concurrently on the same object handle,
+// and the context space is only shared through `&self` when it is `Sync`
+unsafe impl<H: WdfHandle> Sync for WdfObject<H> {}
+
+impl<H: WdfHandle> WdfObject<H> {
+    /// Take ownership of a raw WDF object handle.
+    ///
+    /// If `ownership` is [`Ownership::Parent`] or [`Ownership::Framework`], a
+    /// reference is acquired on the object, and is released when the returned
+    /// [`WdfObject`] is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid handle to a live WDF object. If `ownership` is
+    /// [`Ownership::Driver`], the caller transfers the responsibility of
+    /// deleting the object to the returned [`WdfObject`], and must not delete
+    /// it through any other means. If `ownership` is [`Ownership::Parent`],
+    /// the object must have been created by the driver. The object's context
+    /// space must not be mutably accessed through any other [`WdfObject`] for
+    /// `handle` while this one exists.
+    pub unsafe fn from_raw(handle: H, ownership: Ownership) -> Self {
+        if ownership != Ownership::Driver {
+            reference(handle.as_wdf_object());
+        }
+        Self { handle, ownership }
+    }
+
+    /// Returns the raw handle to the WDF object.
+    ///
+    /// The handle is only valid for as long as this [`WdfObject`] exists.
+    #[must_use]
+    pub const fn as_raw(&self) -> H {
+        self.handle
+    }
+
+    /// Returns who is responsible for deleting the WDF object
+    #[must_use]
+    pub const fn ownership(&self) -> Ownership {
+        self.ownership
+    }
+
+    /// Set this object as the `ParentObject` in `attributes`, so that an
+    /// object created with `attributes` is deleted when this object is deleted
+    pub fn set_as_parent_in(&self, attributes: &mut WDF_OBJECT_ATTRIBUTES) {
+        attributes.ParentObject = self.handle.as_wdf_object();
+    }
+
+    /// Delete the WDF object via `WdfObjectDelete`.
+    ///
+    /// WDF deletes all of an object's children before deleting the object
+    /// itself. For [`Ownership::Framework`] objects, which the driver is not
+    /// allowed to delete, this only releases the reference held by this
+    /// [`WdfObject`], the same as dropping it.
+    pub fn delete(self) {
+        let this = ManuallyDrop::new(self);
+        if this.ownership != Ownership::Framework {
+            delete(this.handle.as_wdf_object());
+        }
+        if this.ownership != Ownership::Driver {
+            dereference(this.handle.as_wdf_object());
+        }
+    }
+}
+
+impl<H: WdfHandle> Drop for WdfObject<H> {
+    fn drop(&mut self) {
+        match self.ownership {
+            Ownership::Driver => delete(self.handle.as_wdf_object()),
+            Ownership::Parent | Ownership::Framework => {
+                dereference(self.handle.as_wdf_object());
+            }
+        }
+    }
+}
+
+fn delete(object: WDFOBJECT) {
+    // SAFETY: `object` is a valid handle to a driver-deletable object, as
+    // guaranteed by the `Ownership` of the `WdfObject` it was obtained from.
+    unsafe {
+        call_unsafe_wdf_function_binding!(WdfObjectDelete, object);
+    }
+}
+
+// clippy::cast_possible_wrap: line numbers never exceed `LONG::MAX`
+#[allow(clippy::cast_possible_wrap)]
+fn reference(object: WDFOBJECT) {
+    // SAFETY: `object` is a valid handle to a live WDF object, as guaranteed by
+    // the caller of `WdfObject::from_raw`, and `File` is a null-terminated
+    // string.
+    unsafe {
+        call_unsafe_wdf_function_binding!(
+            WdfObjectReferenceActual,
+            object,
+            core::ptr::null_mut(),
+            line!() as LONG,
+            concat!(file!(), "\0").as_ptr().cast(),
+        );
+    }
+}
+
+// clippy::cast_possible_wrap: line numbers never exceed `LONG::MAX`
+#[allow(clippy::cast_possible_wrap)]
+fn dereference(object: WDFOBJECT) {
+    // SAFETY: `object` was referenced in `WdfObject::from_raw`, and this is the
+    // only place that reference is released, and `File` is a null-terminated
+    // string.
+    unsafe {
+        call_unsafe_wdf_function_binding!(
+            WdfObjectDereferenceActual,
+            object,
+            core::ptr::null_mut(),
+            line!() as LONG,
+            concat!(file!(), "\0").as_ptr().cast(),
+        );
+    }
+}
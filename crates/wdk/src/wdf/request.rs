@@ -0,0 +1,656 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::mem::ManuallyDrop;
+#[cfg(driver_model__driver_type = "KMDF")]
+use core::{marker::PhantomData, ops::Deref};
+
+pub use wdk_sys::__proc_macros::IoControl;
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    PVOID,
+    STATUS_BUFFER_TOO_SMALL,
+    STATUS_DATATYPE_MISALIGNMENT,
+    ULONG_PTR,
+    WDFMEMORY,
+    WDFREQUEST,
+};
+#[cfg(driver_model__driver_type = "KMDF")]
+use wdk_sys::{
+    ntddk::MmMapLockedPagesSpecifyCache,
+    MdlMappingNoExecute,
+    _MEMORY_CACHING_TYPE,
+    _MM_PAGE_PRIORITY,
+    _MODE,
+    KPROCESSOR_MODE,
+    MDL_MAPPED_TO_SYSTEM_VA,
+    MDL_SOURCE_IS_NONPAGED_POOL,
+    PMDL,
+    STATUS_INSUFFICIENT_RESOURCES,
+    ULONG,
+};
+
+use super::{IoQueue, RequestMemory, RequestMemoryMut};
+use crate::{nt_success, NtResult, NtStatus};
+
+/// WDF Request.
+///
+/// A [`Request`] represents an I/O request that has been delivered to the
+/// driver, and must be completed exactly once. Completing a request via
+/// [`Request::complete`] consumes it, so it cannot be completed twice or
+/// accessed after completion. Dropping a [`Request`] without completing it
+/// completes it with [`NtStatus::CANCELLED`], since the requestor would
+/// otherwise wait forever, and logs the dropped request in debug builds.
+///
+/// Ownership of a request can also be given back to WDF via
+/// [`Request::forward_to_queue`], or taken over by raw code via
+/// [`Request::into_raw`].
+///
+/// The buffers of a request are borrowed from it, so they cannot be accessed
+/// after the request is completed. Since the input and output buffers of
+/// `METHOD_BUFFERED` requests share the same memory, the input buffer can
+/// only be borrowed immutably, and the output buffer mutably.
+#[must_use = "requests must be completed, forwarded, or converted into a raw handle"]
+pub struct Request {
+    wdf_request: WDFREQUEST,
+}
+
+// SAFETY: WDF allows requests to be completed from any thread, and `Request`
+// has exclusive access to the request until it is completed.
+unsafe impl Send for Request {}
+
+impl Request {
+    /// Take ownership of a request delivered to the driver
+    ///
+    /// # Safety
+    ///
+    /// `wdf_request` must be a valid request that is owned by the driver, and
+    /// has not been completed. The caller must not complete or otherwise use
+    /// the request after the returned [`Request`] is created.
+    pub const unsafe fn from_raw(wdf_request: WDFREQUEST) -> Self {
+        Self { wdf_request }
+    }
+
+    /// Returns the raw handle to the request.
+    ///
+    /// The handle is only valid until the [`Request`] is completed.
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFREQUEST {
+        self.wdf_request
+    }
+
+    /// Give up ownership of the request without completing it, returning its
+    /// raw handle. The caller becomes responsible for completing it.
+    #[must_use]
+    pub fn into_raw(self) -> WDFREQUEST {
+        let this = ManuallyDrop::new(self);
+        this.wdf_request
+    }
+
+    /// Complete the request with `status`, and `information` set to a
+    /// request-specific value (ex. the number of bytes transferred), via
+    /// `WdfRequestCompleteWithInformation`
+    pub fn complete(self, status: NtStatus, information: usize) {
+        let wdf_request = self.into_raw();
+
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`, and it is never
+        // used again since `self` is consumed
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfRequestCompleteWithInformation,
+                wdf_request,
+                status.as_raw(),
+                information as ULONG_PTR,
+            );
+        }
+    }
+
+    /// Requeue the request to another I/O queue of the same device, via
+    /// `WdfRequestForwardToIoQueue`. WDF then delivers the request to the
+    /// callbacks of `queue`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to requeue the request. The error variant will contain an [`NtStatus`] of the failure, along with the [`Request`], which is still owned by the driver and must be completed. Full error documentation is available in the [WdfRequestForwardToIoQueue Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestforwardtoioqueue#return-value)
+    pub fn forward_to_queue(self, queue: &IoQueue) -> Result<(), (NtStatus, Self)> {
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, and `queue` keeps its queue alive
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestForwardToIoQueue,
+                self.wdf_request,
+                queue.as_raw(),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((nt_status.into(), self));
+        }
+
+        // WDF owns the request again, so it must not be flagged as leaked
+        let _ = self.into_raw();
+        Ok(())
+    }
+}
+
+/// Plain old data that can be viewed in place in a request buffer.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` or `#[repr(transparent)]` (or
+/// primitives), must be valid for every possible bit pattern, and must not
+/// contain any references or pointers, since request buffers contain
+/// arbitrary data provided by the requestor.
+pub unsafe trait Pod: Copy + 'static {
+    /// Read a `Self` from the start of `buffer`, which does not need to be
+    /// aligned for `Self`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `buffer` is smaller than `Self`
+    /// (`STATUS_BUFFER_TOO_SMALL`).
+    fn read_from(buffer: &[u8]) -> NtResult<Self> {
+        if buffer.len() < core::mem::size_of::<Self>() {
+            return Err(STATUS_BUFFER_TOO_SMALL.into());
+        }
+
+        // SAFETY: `buffer` is valid for reads of `size_of::<Self>()` bytes, and `Self:
+        // Pod` is valid for any bit pattern
+        Ok(unsafe { buffer.as_ptr().cast::<Self>().read_unaligned() })
+    }
+}
+
+macro_rules! impl_pod {
+    ($($type:ty),+ $(,)?) => {
+        $(
+            // SAFETY: Every bit pattern is a valid value of a primitive integer.
+            unsafe impl Pod for $type {}
+        )+
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// SAFETY: An array of `Pod` values is valid for every bit pattern, and has no
+// padding between its elements.
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// An enum of the IOCTLs handled by a driver, which can be decoded from a
+/// device control request.
+///
+/// This trait is usually implemented with `#[derive(IoControl)]`, which also
+/// declares the I/O control code of each IOCTL. The input of an IOCTL is read
+/// from the input buffer of the request, while its output should be written to
+/// [`Request::output`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::wdf::{IoControl, Pod, Request};
+/// use wdk_sys::{FILE_ANY_ACCESS, FILE_DEVICE_UNKNOWN, METHOD_BUFFERED, STATUS_SUCCESS};
+///
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Version {
+///     major: u32,
+///     minor: u32,
+/// }
+///
+/// // SAFETY: `Version` is `#[repr(C)]`, has no padding and only contains integers
+/// unsafe impl Pod for Version {}
+///
+/// #[derive(IoControl)]
+/// #[ioctl(device_type = FILE_DEVICE_UNKNOWN)]
+/// enum Ioctl {
+///     #[ioctl(function = 0x800, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS, output = Version)]
+///     GetVersion,
+///     #[ioctl(function = 0x801, method = METHOD_BUFFERED, access = FILE_ANY_ACCESS)]
+///     SetLevel(u32),
+/// }
+///
+/// fn device_control(mut request: Request, io_control_code: u32) {
+///     let Ok(ioctl) = request.io_control::<Ioctl>(io_control_code) else {
+///         // Unknown IOCTL, or an input buffer that is too small
+///         return;
+///     };
+///     match ioctl {
+///         Ioctl::GetVersion => {
+///             if let Ok(version) = request.output::<Version>() {
+///                 *version = Version { major: 1, minor: 0 };
+///             }
+///         }
+///         Ioctl::SetLevel(_level) => {}
+///     }
+/// }
+/// ```
+pub trait IoControl: Sized {
+    /// Decode the IOCTL with I/O control code `io_control_code`, reading its
+    /// input from `input_buffer`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `io_control_code` is not one of
+    /// the IOCTLs (`STATUS_INVALID_DEVICE_REQUEST`), or `input_buffer` is
+    /// too small for its input (`STATUS_BUFFER_TOO_SMALL`).
+    fn decode(io_control_code: u32, input_buffer: &[u8]) -> NtResult<Self>;
+
+    /// Returns the I/O control code of the IOCTL
+    fn io_control_code(&self) -> u32;
+
+    /// Returns the minimum length of the output buffer of the IOCTL
+    fn output_length(&self) -> usize;
+}
+
+impl Request {
+    /// Returns the input buffer of the request, via
+    /// `WdfRequestRetrieveInputBuffer`. This is the buffer of write and device
+    /// control requests.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the buffer is smaller than `minimum_length` (`STATUS_BUFFER_TOO_SMALL`), or WDF fails to retrieve it. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveInputBuffer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveinputbuffer#return-value)
+    pub fn input_buffer(&self, minimum_length: usize) -> NtResult<&[u8]> {
+        let mut buffer: PVOID = core::ptr::null_mut();
+        let mut length = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveInputBuffer,
+                self.wdf_request,
+                minimum_length,
+                &mut buffer,
+                &mut length,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        if length < minimum_length {
+            return Err(STATUS_BUFFER_TOO_SMALL.into());
+        }
+
+        // SAFETY: WDF returned a buffer that is valid for reads of `length` bytes until
+        // the request is completed, which cannot happen while `self` is borrowed
+        Ok(unsafe { core::slice::from_raw_parts(buffer.cast::<u8>(), length) })
+    }
+
+    /// Returns the output buffer of the request, via
+    /// `WdfRequestRetrieveOutputBuffer`. This is the buffer of read and device
+    /// control requests.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the buffer is smaller than `minimum_length` (`STATUS_BUFFER_TOO_SMALL`), or WDF fails to retrieve it. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveOutputBuffer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveoutputbuffer#return-value)
+    pub fn output_buffer(&mut self, minimum_length: usize) -> NtResult<&mut [u8]> {
+        let mut buffer: PVOID = core::ptr::null_mut();
+        let mut length = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveOutputBuffer,
+                self.wdf_request,
+                minimum_length,
+                &mut buffer,
+                &mut length,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+        if length < minimum_length {
+            return Err(STATUS_BUFFER_TOO_SMALL.into());
+        }
+
+        // SAFETY: WDF returned a buffer that is valid for reads and writes of `length`
+        // bytes until the request is completed, which cannot happen while `self` is
+        // mutably borrowed
+        Ok(unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), length) })
+    }
+
+    /// Returns the input buffer of the request as a [`Memory`](super::Memory),
+    /// via `WdfRequestRetrieveInputMemory`, so that it can be passed to other
+    /// WDF APIs by handle
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to retrieve the memory object. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveInputMemory Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveinputmemory#return-value)
+    pub fn input_memory(&self) -> NtResult<RequestMemory<'_>> {
+        let mut wdf_memory: WDFMEMORY = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveInputMemory,
+                self.wdf_request,
+                &mut wdf_memory,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: WDF returned a valid memory object that lives until the request is
+        // completed, which cannot happen while `self` is borrowed
+        Ok(unsafe { RequestMemory::from_raw(wdf_memory) })
+    }
+
+    /// Returns the output buffer of the request as a [`Memory`](super::Memory),
+    /// via `WdfRequestRetrieveOutputMemory`, so that it can be passed to other
+    /// WDF APIs by handle
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to retrieve the memory object. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveOutputMemory Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveoutputmemory#return-value)
+    pub fn output_memory(&mut self) -> NtResult<RequestMemoryMut<'_>> {
+        let mut wdf_memory: WDFMEMORY = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveOutputMemory,
+                self.wdf_request,
+                &mut wdf_memory,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // SAFETY: WDF returned a valid memory object that lives until the request is
+        // completed, which cannot happen while `self` is mutably borrowed
+        Ok(unsafe { RequestMemoryMut::from_raw(wdf_memory) })
+    }
+
+    /// Returns a view of the start of the input buffer as a `T`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input buffer cannot be
+    /// retrieved (see [`Request::input_buffer`]), is smaller than `T`
+    /// (`STATUS_BUFFER_TOO_SMALL`), or is not aligned for `T`
+    /// (`STATUS_DATATYPE_MISALIGNMENT`). The error variant will contain an
+    /// [`NtStatus`] of the failure.
+    pub fn input<T: Pod>(&self) -> NtResult<&T> {
+        let buffer = self.input_buffer(core::mem::size_of::<T>())?;
+        let buffer = buffer.as_ptr().cast::<T>();
+        if !buffer.is_aligned() {
+            return Err(STATUS_DATATYPE_MISALIGNMENT.into());
+        }
+
+        // SAFETY: `buffer` is aligned, valid for reads of `size_of::<T>()` bytes for
+        // the lifetime of the borrow of `self`, and `T: Pod` is valid for any
+        // bit pattern
+        Ok(unsafe { &*buffer })
+    }
+
+    /// Returns a mutable view of the start of the output buffer as a `T`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the output buffer cannot be
+    /// retrieved (see [`Request::output_buffer`]), is smaller than `T`
+    /// (`STATUS_BUFFER_TOO_SMALL`), or is not aligned for `T`
+    /// (`STATUS_DATATYPE_MISALIGNMENT`). The error variant will contain an
+    /// [`NtStatus`] of the failure.
+    pub fn output<T: Pod>(&mut self) -> NtResult<&mut T> {
+        let buffer = self.output_buffer(core::mem::size_of::<T>())?;
+        let buffer = buffer.as_mut_ptr().cast::<T>();
+        if !buffer.is_aligned() {
+            return Err(STATUS_DATATYPE_MISALIGNMENT.into());
+        }
+
+        // SAFETY: `buffer` is aligned, valid for reads and writes of `size_of::<T>()`
+        // bytes for the lifetime of the mutable borrow of `self`, and `T: Pod` is valid
+        // for any bit pattern
+        Ok(unsafe { &mut *buffer })
+    }
+
+    /// Decode a device control request with I/O control code `io_control_code`
+    /// into an [`IoControl`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `io_control_code` is not one of
+    /// the IOCTLs of `T`, or the input buffer is too small for its input. See
+    /// [`IoControl::decode`].
+    pub fn io_control<T: IoControl>(&self, io_control_code: u32) -> NtResult<T> {
+        // WDF fails to retrieve the input buffer of requests without one, which is
+        // only an error if the IOCTL has an input
+        let input_buffer = self.input_buffer(0).unwrap_or_default();
+        T::decode(io_control_code, input_buffer)
+    }
+
+    /// Returns the MDL describing the input buffer of a direct I/O request, via
+    /// `WdfRequestRetrieveInputWdmMdl`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to retrieve the MDL. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveInputWdmMdl Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveinputwdmmdl#return-value)
+    #[cfg(driver_model__driver_type = "KMDF")]
+    pub fn input_mdl(&self) -> NtResult<Mdl<'_>> {
+        let mut mdl: PMDL = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveInputWdmMdl,
+                self.wdf_request,
+                &mut mdl,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // WDF returned a valid MDL that lives until the request is completed, which
+        // cannot happen while `self` is borrowed
+        Ok(Mdl {
+            mdl,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the MDL describing the output buffer of a direct I/O request,
+    /// via `WdfRequestRetrieveOutputWdmMdl`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to retrieve the MDL. The error variant will contain an [`NtStatus`] of the failure. Full error documentation is available in the [WdfRequestRetrieveOutputWdmMdl Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveoutputwdmmdl#return-value)
+    #[cfg(driver_model__driver_type = "KMDF")]
+    pub fn output_mdl(&mut self) -> NtResult<MdlMut<'_>> {
+        let mut mdl: PMDL = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveOutputWdmMdl,
+                self.wdf_request,
+                &mut mdl,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        // WDF returned a valid MDL that lives until the request is completed, which
+        // cannot happen while `self` is mutably borrowed
+        Ok(MdlMut {
+            mdl: Mdl {
+                mdl,
+                _marker: PhantomData,
+            },
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A memory descriptor list (MDL) describing the locked pages of a direct I/O
+/// buffer, borrowed from the [`Request`] it belongs to.
+///
+/// An [`Mdl`] only gives read access to the buffer, since several of them can
+/// describe the same buffer at once (ex. the input buffer of a request). See
+/// [`MdlMut`] for the output buffer of a request.
+#[cfg(driver_model__driver_type = "KMDF")]
+pub struct Mdl<'a> {
+    mdl: PMDL,
+    _marker: PhantomData<&'a Request>,
+}
+
+/// A memory descriptor list (MDL) describing the locked pages of the output
+/// buffer of a direct I/O request, mutably borrowed from the [`Request`] it
+/// belongs to.
+///
+/// This gives write access to the buffer, in addition to the read access of
+/// [`Mdl`].
+#[cfg(driver_model__driver_type = "KMDF")]
+pub struct MdlMut<'a> {
+    mdl: Mdl<'a>,
+    _marker: PhantomData<&'a mut Request>,
+}
+
+#[cfg(driver_model__driver_type = "KMDF")]
+impl Mdl<'_> {
+    /// Returns the length in bytes of the buffer described by the MDL. This is
+    /// the equivalent of `MmGetMdlByteCount`.
+    #[must_use]
+    pub fn byte_count(&self) -> usize {
+        // SAFETY: `mdl` is valid for as long as the request it is borrowed from
+        let byte_count = unsafe { (*self.mdl).ByteCount };
+        byte_count as usize
+    }
+
+    /// Returns the raw pointer to the underlying [`wdk_sys::MDL`]
+    #[must_use]
+    pub const fn as_raw(&self) -> PMDL {
+        self.mdl
+    }
+
+    /// Returns the buffer described by the MDL, mapped into system address
+    /// space
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the buffer
+    /// cannot be mapped into system address space.
+    pub fn system_buffer(&self) -> NtResult<&[u8]> {
+        let buffer = self.system_address()?;
+
+        // SAFETY: `buffer` is a system address mapping the `byte_count()` bytes of the
+        // locked buffer described by the MDL, which lives as long as the borrow of
+        // `self`
+        Ok(unsafe { core::slice::from_raw_parts(buffer, self.byte_count()) })
+    }
+
+    /// Returns the system address of the buffer described by the MDL, mapping
+    /// it if needed. This is the equivalent of `MmGetSystemAddressForMdlSafe`
+    /// with `NormalPagePriority | MdlMappingNoExecute`.
+    fn system_address(&self) -> NtResult<*mut u8> {
+        // SAFETY: `mdl` is valid for as long as the request it is borrowed from
+        let mdl_flags = unsafe { (*self.mdl).MdlFlags };
+        // clippy::cast_sign_loss: `MdlFlags` is a bitfield stored in a `CSHORT`
+        #[allow(clippy::cast_sign_loss)]
+        let mdl_flags = ULONG::from(mdl_flags as u16);
+        if mdl_flags & (MDL_MAPPED_TO_SYSTEM_VA | MDL_SOURCE_IS_NONPAGED_POOL) != 0 {
+            // SAFETY: `mdl` is valid for as long as the request it is borrowed from
+            return Ok(unsafe { (*self.mdl).MappedSystemVa }.cast());
+        }
+
+        let system_address;
+        // SAFETY: `mdl` describes the locked buffer of a direct I/O request, which is
+        // mapped with the same arguments as `MmGetSystemAddressForMdlSafe`. The mapping
+        // is released by the I/O manager when the request is completed.
+        unsafe {
+            // clippy::cast_possible_truncation: `KernelMode` is 0
+            // clippy::cast_sign_loss: `NormalPagePriority` is positive
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            {
+                system_address = MmMapLockedPagesSpecifyCache(
+                    self.mdl,
+                    crate::enum_value(_MODE::KernelMode) as KPROCESSOR_MODE,
+                    _MEMORY_CACHING_TYPE::MmCached,
+                    core::ptr::null_mut(),
+                    0,
+                    crate::enum_value(_MM_PAGE_PRIORITY::NormalPagePriority) as ULONG
+                        | MdlMappingNoExecute,
+                );
+            }
+        }
+        if system_address.is_null() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES.into());
+        }
+        Ok(system_address.cast())
+    }
+}
+
+#[cfg(driver_model__driver_type = "KMDF")]
+impl MdlMut<'_> {
+    /// Returns the buffer described by the MDL, mapped into system address
+    /// space
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the buffer
+    /// cannot be mapped into system address space.
+    pub fn system_buffer_mut(&mut self) -> NtResult<&mut [u8]> {
+        let buffer = self.mdl.system_address()?;
+
+        // SAFETY: `buffer` is a system address mapping the `byte_count()` bytes of the
+        // locked output buffer described by the MDL, which lives as long as the mutable
+        // borrow of `self`. Only one `MdlMut` can exist for the output buffer of a
+        // request at a time, since it mutably borrows the request.
+        Ok(unsafe { core::slice::from_raw_parts_mut(buffer, self.byte_count()) })
+    }
+}
+
+#[cfg(driver_model__driver_type = "KMDF")]
+impl<'a> Deref for MdlMut<'a> {
+    type Target = Mdl<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mdl
+    }
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        // clippy::used_underscore_items: `println!` is implemented by `_print`, which
+        // is only public so that the macro can be used outside of this crate
+        #[cfg(all(
+            debug_assertions,
+            any(feature = "alloc", driver_model__driver_type = "UMDF")
+        ))]
+        #[allow(clippy::used_underscore_items)]
+        {
+            crate::println!(
+                "WDFREQUEST {:p} was dropped without being completed",
+                self.wdf_request
+            );
+        }
+
+        // SAFETY: `wdf_request` is a valid request that is owned by the driver and has
+        // not been completed, as guaranteed by `Request::from_raw`. Every method that
+        // completes or gives away the request consumes `self` without dropping it.
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfRequestComplete,
+                self.wdf_request,
+                NtStatus::CANCELLED.as_raw(),
+            );
+        }
+    }
+}
@@ -1,9 +1,16 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 
-use wdk_sys::{call_unsafe_wdf_function_binding, NTSTATUS, WDFSPINLOCK, WDF_OBJECT_ATTRIBUTES};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
 
-use crate::nt_success;
+use wdk_sys::{call_unsafe_wdf_function_binding, WDFSPINLOCK, WDF_OBJECT_ATTRIBUTES};
+
+use super::{Ownership, WdfObject};
+use crate::{nt_success, NtResult};
 
 /// WDF Spin Lock.
 ///
@@ -11,26 +18,70 @@
 /// runs at `IRQL` <= `DISPATCH_LEVEL`. When a driver thread acquires a spin
 /// lock, the system sets the thread's IRQL to `DISPATCH_LEVEL`. When the thread
 /// releases the lock, the system restores the thread's IRQL to its previous
-/// level. A driver that is not using automatic framework synchronization might
-/// use a spin lock to synchronize access to a device object's context space, if
-/// the context space is writable and if more than one of the driver's event
-/// callback functions access the space. Before a driver can use a framework
-/// spin lock it must call [`SpinLock::try_new()`] to create a [`SpinLock`]. The
-/// driver can then call [`SpinLock::acquire`] to acquire the lock and
-/// [`SpinLock::release()`] to release it.
-pub struct SpinLock {
-    wdf_spin_lock: WDFSPINLOCK,
+/// level.
+///
+/// Like [`std::sync::Mutex`], a [`SpinLock`] owns the data it protects, which
+/// can only be accessed through the [`SpinLockGuard`] returned by
+/// [`SpinLock::lock`]. The lock is released when the guard is dropped, so it
+/// cannot be left acquired or released twice. Since the guard raises the IRQL
+/// of the thread that acquired the lock, it cannot be sent to another thread.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{object_attributes, SpinLock},
+///     NtResult,
+/// };
+///
+/// fn count_requests() -> NtResult<()> {
+///     let request_count = SpinLock::try_new(&mut object_attributes(), 0u32)?;
+///
+///     *request_count.lock() += 1;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+pub struct SpinLock<T> {
+    wdf_spin_lock: WdfObject<WDFSPINLOCK>,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: WDF spin lock handles can be used from any thread, and the protected
+// data is only ever accessed by the thread holding the lock
+unsafe impl<T: Send> Send for SpinLock<T> {}
+// SAFETY: The protected data is only ever accessed by the thread holding the
+// lock, so sharing the lock only requires that the data can be sent between
+// threads
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+/// An RAII guard of a locked [`SpinLock`], returned by [`SpinLock::lock`].
+///
+/// The protected data can be accessed through this guard's [`Deref`] and
+/// [`DerefMut`] implementations. The lock is released when the guard is
+/// dropped.
+#[must_use = "if unused the SpinLock will immediately unlock"]
+pub struct SpinLockGuard<'a, T> {
+    spin_lock: &'a SpinLock<T>,
+    // The lock must be released by the thread that acquired it, since acquiring it
+    // raised that thread's IRQL
+    _not_send: PhantomData<*const ()>,
 }
-impl SpinLock {
-    /// Try to construct a WDF Spin Lock object
+
+// SAFETY: A shared reference to the guard only gives shared access to the
+// protected data
+unsafe impl<T: Sync> Sync for SpinLockGuard<'_, T> {}
+
+impl<T> SpinLock<T> {
+    /// Try to construct a WDF Spin Lock object protecting `data`
     ///
     /// # Errors
     ///
-    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFSpinLock Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfspinlockcreate#return-value)
-    pub fn try_new(attributes: &mut WDF_OBJECT_ATTRIBUTES) -> Result<Self, NTSTATUS> {
-        let mut spin_lock = Self {
-            wdf_spin_lock: core::ptr::null_mut(),
-        };
+    /// This function will return an error if WDF fails to contruct a spin lock. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WDFSpinLock Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfspinlockcreate#return-value)
+    pub fn try_new(attributes: &mut WDF_OBJECT_ATTRIBUTES, data: T) -> NtResult<Self> {
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_spin_lock: WDFSPINLOCK = core::ptr::null_mut();
 
         let nt_status;
         // SAFETY: The resulting ffi object is stored in a private member and not
@@ -40,37 +91,84 @@ pub fn try_new(attributes: &mut WDF_OBJECT_ATTRIBUTES) -> Result<Self, NTSTATUS>
             nt_status = call_unsafe_wdf_function_binding!(
                 WdfSpinLockCreate,
                 attributes,
-                &mut spin_lock.wdf_spin_lock,
+                &mut wdf_spin_lock,
             );
         }
-        nt_success(nt_status).then_some(spin_lock).ok_or(nt_status)
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(Self {
+            // SAFETY: `wdf_spin_lock` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_spin_lock: unsafe { WdfObject::from_raw(wdf_spin_lock, ownership) },
+            data: UnsafeCell::new(data),
+        })
     }
 
-    /// Try to construct a WDF Spin Lock object. This is an alias for
-    /// [`SpinLock::try_new()`]
+    /// Try to construct a WDF Spin Lock object protecting `data`. This is an
+    /// alias for [`SpinLock::try_new()`]
     ///
     /// # Errors
     ///
-    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFSpinLock Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfspinlockcreate#return-value)
-    pub fn create(attributes: &mut WDF_OBJECT_ATTRIBUTES) -> Result<Self, NTSTATUS> {
-        Self::try_new(attributes)
+    /// This function will return an error if WDF fails to contruct a spin lock. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WDFSpinLock Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfspinlockcreate#return-value)
+    pub fn create(attributes: &mut WDF_OBJECT_ATTRIBUTES, data: T) -> NtResult<Self> {
+        Self::try_new(attributes, data)
     }
 
-    /// Acquire the spinlock
-    pub fn acquire(&self) {
+    /// Acquire the spin lock, and return a guard that releases it when dropped
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
         // SAFETY: `wdf_spin_lock` is a private member of `SpinLock`, originally created
-        // by WDF, and this module guarantees that it is always in a valid state.
+        // by WDF, and `WdfObject` guarantees that it is always in a valid state.
         unsafe {
-            call_unsafe_wdf_function_binding!(WdfSpinLockAcquire, self.wdf_spin_lock);
+            call_unsafe_wdf_function_binding!(WdfSpinLockAcquire, self.wdf_spin_lock.as_raw());
+        }
+
+        SpinLockGuard {
+            spin_lock: self,
+            _not_send: PhantomData,
         }
     }
 
-    /// Release the spinlock
-    pub fn release(&self) {
-        // SAFETY: `wdf_spin_lock` is a private member of `SpinLock`, originally created
-        // by WDF, and this module guarantees that it is always in a valid state.
+    /// Returns a mutable reference to the protected data. No locking is needed,
+    /// since the mutable borrow guarantees that the lock is not held.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consume the spin lock, returning the protected data
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The spin lock is held for the lifetime of the guard, so no other
+        // thread can access the data
+        unsafe { &*self.spin_lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The spin lock is held for the lifetime of the guard, and the guard
+        // is mutably borrowed, so this is the only reference to the data
+        unsafe { &mut *self.spin_lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_spin_lock` is valid for the lifetime of the guard, and was
+        // acquired by this thread when the guard was created
         unsafe {
-            call_unsafe_wdf_function_binding!(WdfSpinLockRelease, self.wdf_spin_lock);
+            call_unsafe_wdf_function_binding!(
+                WdfSpinLockRelease,
+                self.spin_lock.wdf_spin_lock.as_raw()
+            );
         }
     }
 }
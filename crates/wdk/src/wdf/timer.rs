@@ -1,33 +1,236 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 
+use core::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
 use wdk_sys::{
     call_unsafe_wdf_function_binding,
-    NTSTATUS,
+    ULONG,
     WDFTIMER,
     WDF_OBJECT_ATTRIBUTES,
     WDF_TIMER_CONFIG,
 };
 
-use crate::nt_success;
+#[cfg(feature = "alloc")]
+use super::callback::ClosureCallback;
+use super::{callback::stored_callback, wdf_struct_size, ObjectContext, Ownership, WdfObject};
+use crate::{nt_success, time::Timeout, NtResult};
+
+/// Callback of a [`Timer`].
+///
+/// The implementing type is stored in the context space of the timer, so it
+/// can hold any per-timer state. Timers whose callback only needs captured
+/// state can instead be created with [`TimerBuilder::create_with_closure`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// use wdk::wdf::{ObjectContext, Timer, TimerCallback};
+///
+/// #[derive(ObjectContext)]
+/// struct Watchdog {
+///     expirations: AtomicU32,
+/// }
+///
+/// impl TimerCallback for Watchdog {
+///     fn expired(&self, _timer: &Timer) {
+///         self.expirations.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait TimerCallback: ObjectContext + Sync {
+    /// `EvtTimerFunc`: called when the timer expires
+    fn expired(&self, timer: &Timer);
+}
 
 /// WDF Timer.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::time::Duration;
+///
+/// use wdk::{
+///     println,
+///     wdf::{object_attributes, Device, Timer},
+///     NtResult,
+/// };
+///
+/// fn start_polling(device: &Device) -> NtResult<Timer> {
+///     let mut attributes = object_attributes();
+///     device.set_as_parent_in(&mut attributes);
+///
+///     let timer = Timer::builder()
+///         .period(Duration::from_secs(1))
+///         .create_with_closure(&mut attributes, |_timer| println!("polling"))?;
+///     let _ = timer.start(Duration::from_millis(100));
+///     Ok(timer)
+/// }
+/// ```
 pub struct Timer {
-    wdf_timer: WDFTIMER,
+    wdf_timer: WdfObject<WDFTIMER>,
+}
+
+/// Builder for a [`Timer`], wrapping the configuration in
+/// [`WDF_TIMER_CONFIG`].
+pub struct TimerBuilder {
+    period: Duration,
+    tolerable_delay: Duration,
+    use_high_resolution_timer: bool,
+    automatic_serialization: bool,
 }
+
 impl Timer {
-    /// Try to construct a WDF Timer object
+    /// Returns a [`TimerBuilder`] to configure and create a one-shot timer
+    #[must_use]
+    pub const fn builder() -> TimerBuilder {
+        TimerBuilder {
+            period: Duration::ZERO,
+            tolerable_delay: Duration::ZERO,
+            use_high_resolution_timer: false,
+            automatic_serialization: true,
+        }
+    }
+
+    /// Wrap a timer handed to a WDF callback
+    ///
+    /// # Safety
+    ///
+    /// `wdf_timer` must be a valid handle to a timer created by the driver
+    unsafe fn from_raw(wdf_timer: WDFTIMER) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_timer` is valid, and timers are always
+            // parented to a device or queue
+            wdf_timer: unsafe { WdfObject::from_raw(wdf_timer, Ownership::Parent) },
+        }
+    }
+
+    /// Start the [`Timer`]'s clock, so that it expires at `due_time`. A
+    /// [`Duration`] can be passed for a due time relative to now.
+    ///
+    /// Returns `true` if the timer was already started, in which case it is
+    /// restarted with the new due time.
+    #[must_use]
+    pub fn start(&self, due_time: impl Into<Timeout>) -> bool {
+        let result;
+        // SAFETY: `wdf_timer` is a private member of `Timer`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            result = call_unsafe_wdf_function_binding!(
+                WdfTimerStart,
+                self.wdf_timer.as_raw(),
+                due_time.into().as_raw()
+            );
+        }
+        result != 0
+    }
+
+    /// Stop the [`Timer`]'s clock. If `wait` is `true`, this waits for all
+    /// running callbacks of the timer to return, and must be called at
+    /// `PASSIVE_LEVEL`.
+    ///
+    /// Returns `true` if the timer was started and had not expired yet.
+    #[must_use]
+    pub fn stop(&self, wait: bool) -> bool {
+        let result;
+        // SAFETY: `wdf_timer` is a private member of `Timer`, originally created by
+        // WDF, and `WdfObject` guarantees that it is always in a valid state.
+        unsafe {
+            result = call_unsafe_wdf_function_binding!(
+                WdfTimerStop,
+                self.wdf_timer.as_raw(),
+                u8::from(wait)
+            );
+        }
+        result != 0
+    }
+}
+
+impl Deref for Timer {
+    type Target = WdfObject<WDFTIMER>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_timer
+    }
+}
+
+impl DerefMut for Timer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_timer
+    }
+}
+
+impl TimerBuilder {
+    /// Make the timer periodic, expiring every `period` after its first due
+    /// time. The period is rounded up to whole milliseconds. A period of zero
+    /// makes the timer one-shot, which is the default.
+    #[must_use]
+    pub const fn period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Allow the system to delay the expiration of the timer by up to
+    /// `tolerable_delay`, so that it can be coalesced with other timers to
+    /// save power. The delay is rounded up to whole milliseconds.
+    ///
+    /// This cannot be combined with
+    /// [`TimerBuilder::use_high_resolution_timer`].
+    #[must_use]
+    pub const fn tolerable_delay(mut self, tolerable_delay: Duration) -> Self {
+        self.tolerable_delay = tolerable_delay;
+        self
+    }
+
+    /// Set whether the timer expires with the precision of the system clock,
+    /// rather than the default system clock tick. High resolution timers are
+    /// only supported by KMDF 1.13 and later, and increase power consumption.
+    #[must_use]
+    pub const fn use_high_resolution_timer(mut self, use_high_resolution_timer: bool) -> Self {
+        self.use_high_resolution_timer = use_high_resolution_timer;
+        self
+    }
+
+    /// Set whether the timer's callback is synchronized with the callbacks of
+    /// its parent device or queue, according to their synchronization scope.
+    /// This is enabled by default, as in `WDF_TIMER_CONFIG_INIT`.
+    #[must_use]
+    pub const fn automatic_serialization(mut self, automatic_serialization: bool) -> Self {
+        self.automatic_serialization = automatic_serialization;
+        self
+    }
+
+    /// Create the timer via `WdfTimerCreate`, storing `callback` in its context
+    /// space.
+    ///
+    /// `attributes` must have a `ParentObject`, which is the device or queue
+    /// that the timer belongs to, and must not have an `EvtCleanupCallback`
+    /// or context type set.
     ///
     /// # Errors
     ///
-    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
-    pub fn try_new(
-        timer_config: &mut WDF_TIMER_CONFIG,
+    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
+    pub fn create<T: TimerCallback>(
+        self,
         attributes: &mut WDF_OBJECT_ATTRIBUTES,
-    ) -> Result<Self, NTSTATUS> {
-        let mut timer = Self {
-            wdf_timer: core::ptr::null_mut(),
+        callback: T,
+    ) -> NtResult<Timer> {
+        let mut timer_config = WDF_TIMER_CONFIG {
+            Size: wdf_struct_size::<WDF_TIMER_CONFIG>(),
+            EvtTimerFunc: Some(evt_timer_func::<T>),
+            Period: duration_to_milliseconds(self.period),
+            AutomaticSerialization: u8::from(self.automatic_serialization),
+            TolerableDelay: duration_to_milliseconds(self.tolerable_delay),
+            UseHighResolutionTimer: u8::from(self.use_high_resolution_timer),
         };
+        T::set_context_type(attributes);
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_timer: WDFTIMER = core::ptr::null_mut();
 
         let nt_status;
         // SAFETY: The resulting ffi object is stored in a private member and not
@@ -36,48 +239,56 @@ pub fn try_new(
         unsafe {
             nt_status = call_unsafe_wdf_function_binding!(
                 WdfTimerCreate,
-                timer_config,
+                &mut timer_config,
                 attributes,
-                &mut timer.wdf_timer,
+                &mut wdf_timer,
             );
         }
-        nt_success(nt_status).then_some(timer).ok_or(nt_status)
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let mut timer = Timer {
+            // SAFETY: `wdf_timer` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_timer: unsafe { WdfObject::from_raw(wdf_timer, ownership) },
+        };
+        // `T` is always present since it was set in `attributes`.
+        // SAFETY: The callback cannot run until the timer is started, which requires
+        // the `Timer` being returned.
+        if unsafe { timer.init_context(callback) }.is_err() {
+            unreachable!("timer should have a context space for its callback");
+        }
+        Ok(timer)
     }
 
-    /// Try to construct a WDF Timer object
+    /// Create the timer via `WdfTimerCreate`, calling `closure` when it
+    /// expires. See [`TimerBuilder::create`] for the requirements on
+    /// `attributes`.
     ///
     /// # Errors
     ///
-    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
-    pub fn create(
-        timer_config: &mut WDF_TIMER_CONFIG,
+    /// This function will return an error if WDF fails to contruct a timer. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create_with_closure(
+        self,
         attributes: &mut WDF_OBJECT_ATTRIBUTES,
-    ) -> Result<Self, NTSTATUS> {
-        Self::try_new(timer_config, attributes)
+        closure: impl Fn(&Timer) + Send + Sync + 'static,
+    ) -> NtResult<Timer> {
+        self.create(attributes, ClosureCallback::new(closure))
     }
+}
 
-    /// Start the [`Timer`]'s clock
-    #[must_use]
-    pub fn start(&self, due_time: i64) -> bool {
-        let result;
-        // SAFETY: `wdf_timer` is a private member of `Timer`, originally created by
-        // WDF, and this module guarantees that it is always in a valid state.
-        unsafe {
-            result = call_unsafe_wdf_function_binding!(WdfTimerStart, self.wdf_timer, due_time);
-        }
-        result != 0
-    }
+/// Convert `duration` to the whole number of milliseconds expected by
+/// [`WDF_TIMER_CONFIG`], rounding up and saturating
+fn duration_to_milliseconds(duration: Duration) -> ULONG {
+    ULONG::try_from(duration.as_nanos().div_ceil(1_000_000)).unwrap_or(ULONG::MAX)
+}
 
-    /// Stop the [`Timer`]'s clock
-    #[must_use]
-    pub fn stop(&self, wait: bool) -> bool {
-        let result;
-        // SAFETY: `wdf_timer` is a private member of `Timer`, originally created by
-        // WDF, and this module guarantees that it is always in a valid state.
-        unsafe {
-            result =
-                call_unsafe_wdf_function_binding!(WdfTimerStop, self.wdf_timer, u8::from(wait));
-        }
-        result != 0
+extern "C" fn evt_timer_func<T: TimerCallback>(wdf_timer: WDFTIMER) {
+    // SAFETY: WDF always passes a valid handle to the expired timer
+    let timer = unsafe { Timer::from_raw(wdf_timer) };
+    if let Some(callback) = stored_callback::<T, _>(&timer) {
+        callback.expired(&timer);
     }
 }
@@ -0,0 +1,254 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    NTSTATUS,
+    STATUS_TIMEOUT,
+    WDFWAITLOCK,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use super::{Ownership, WdfObject};
+use crate::{debug_assert_passive_level, nt_success, time::Timeout, NtResult};
+
+/// WDF Wait Lock.
+///
+/// Use framework wait locks to synchronize access to driver data from code that
+/// runs at `PASSIVE_LEVEL` and may block, or touch pageable memory, while
+/// holding the lock. Acquiring a wait lock enters a critical region, which
+/// disables the delivery of normal kernel APCs to the thread until the lock is
+/// released. Code running at `DISPATCH_LEVEL` must use a
+/// [`SpinLock`](super::SpinLock) instead, or [`WaitLock::try_lock`], which does
+/// not wait.
+///
+/// Like [`std::sync::Mutex`], a [`WaitLock`] owns the data it protects, which
+/// can only be accessed through the [`WaitLockGuard`] returned by
+/// [`WaitLock::lock`] or [`WaitLock::try_lock_for`]. The lock is released when
+/// the guard is dropped. Since the lock must be released by the thread that
+/// acquired it, the guard cannot be sent to another thread.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::time::Duration;
+///
+/// use wdk::{
+///     wdf::{object_attributes, WaitLock},
+///     NtResult,
+/// };
+///
+/// fn update_configuration() -> NtResult<()> {
+///     let configuration = WaitLock::try_new(&mut object_attributes(), [0u8; 16])?;
+///
+///     configuration.lock()[0] = 1;
+///     // Fails with `STATUS_TIMEOUT` if the lock is not acquired within 10ms
+///     configuration.try_lock_for(Duration::from_millis(10))?[1] = 2;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+pub struct WaitLock<T> {
+    wdf_wait_lock: WdfObject<WDFWAITLOCK>,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: WDF wait lock handles can be used from any thread, and the protected
+// data is only ever accessed by the thread holding the lock
+unsafe impl<T: Send> Send for WaitLock<T> {}
+// SAFETY: The protected data is only ever accessed by the thread holding the
+// lock, so sharing the lock only requires that the data can be sent between
+// threads
+unsafe impl<T: Send> Sync for WaitLock<T> {}
+
+/// An RAII guard of a locked [`WaitLock`], returned by [`WaitLock::lock`],
+/// [`WaitLock::try_lock`] and [`WaitLock::try_lock_for`].
+///
+/// The protected data can be accessed through this guard's [`Deref`] and
+/// [`DerefMut`] implementations. The lock is released when the guard is
+/// dropped.
+#[must_use = "if unused the WaitLock will immediately unlock"]
+pub struct WaitLockGuard<'a, T> {
+    wait_lock: &'a WaitLock<T>,
+    // The lock must be released by the thread that acquired it, since acquiring it
+    // entered a critical region on that thread
+    _not_send: PhantomData<*const ()>,
+}
+
+// SAFETY: A shared reference to the guard only gives shared access to the
+// protected data
+unsafe impl<T: Sync> Sync for WaitLockGuard<'_, T> {}
+
+impl<T> WaitLock<T> {
+    /// Try to construct a WDF Wait Lock object protecting `data`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a wait lock. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfWaitLockCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfwaitlockcreate#return-value)
+    pub fn try_new(attributes: &mut WDF_OBJECT_ATTRIBUTES, data: T) -> NtResult<Self> {
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_wait_lock: WDFWAITLOCK = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfWaitLockCreate,
+                attributes,
+                &mut wdf_wait_lock,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(Self {
+            // SAFETY: `wdf_wait_lock` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_wait_lock: unsafe { WdfObject::from_raw(wdf_wait_lock, ownership) },
+            data: UnsafeCell::new(data),
+        })
+    }
+
+    /// Try to construct a WDF Wait Lock object protecting `data`. This is an
+    /// alias for [`WaitLock::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a wait lock. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfWaitLockCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfsync/nf-wdfsync-wdfwaitlockcreate#return-value)
+    pub fn create(attributes: &mut WDF_OBJECT_ATTRIBUTES, data: T) -> NtResult<Self> {
+        Self::try_new(attributes, data)
+    }
+
+    /// Acquire the wait lock, waiting for as long as it takes, and return a
+    /// guard that releases it when dropped.
+    ///
+    /// This must be called at `PASSIVE_LEVEL`, which is asserted in debug
+    /// builds of kernel-mode drivers.
+    pub fn lock(&self) -> WaitLockGuard<'_, T> {
+        debug_assert_passive_level();
+
+        let nt_status = self.acquire(core::ptr::null_mut());
+        debug_assert!(
+            nt_success(nt_status) && nt_status != STATUS_TIMEOUT,
+            "WdfWaitLockAcquire without a timeout should always acquire the lock"
+        );
+
+        self.guard()
+    }
+
+    /// Try to acquire the wait lock without waiting. This can be called at
+    /// `IRQL` <= `DISPATCH_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_TIMEOUT` if the
+    /// lock is held by another thread.
+    pub fn try_lock(&self) -> NtResult<WaitLockGuard<'_, T>> {
+        let mut timeout = 0;
+        self.try_acquire(&mut timeout)
+    }
+
+    /// Try to acquire the wait lock, waiting at most `timeout` for it to be
+    /// released by another thread.
+    ///
+    /// Unless `timeout` is zero, this must be called at `PASSIVE_LEVEL`, which
+    /// is asserted in debug builds of kernel-mode drivers.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing `STATUS_TIMEOUT` if the
+    /// lock could not be acquired within `timeout`.
+    pub fn try_lock_for(&self, timeout: Duration) -> NtResult<WaitLockGuard<'_, T>> {
+        if !timeout.is_zero() {
+            debug_assert_passive_level();
+        }
+
+        let mut timeout = Timeout::Relative(timeout).as_raw();
+        self.try_acquire(&mut timeout)
+    }
+
+    /// Returns a mutable reference to the protected data. No locking is needed,
+    /// since the mutable borrow guarantees that the lock is not held.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consume the wait lock, returning the protected data
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    fn try_acquire(&self, timeout: &mut i64) -> NtResult<WaitLockGuard<'_, T>> {
+        let nt_status = self.acquire(timeout);
+        // `STATUS_TIMEOUT` is a success status, so it is checked first
+        if nt_status == STATUS_TIMEOUT || !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        Ok(self.guard())
+    }
+
+    fn acquire(&self, timeout: *mut i64) -> NTSTATUS {
+        let nt_status;
+        // SAFETY: `wdf_wait_lock` is a private member of `WaitLock`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state. `timeout` is either null or points to a valid timeout.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfWaitLockAcquire,
+                self.wdf_wait_lock.as_raw(),
+                timeout,
+            );
+        }
+        nt_status
+    }
+
+    const fn guard(&self) -> WaitLockGuard<'_, T> {
+        WaitLockGuard {
+            wait_lock: self,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for WaitLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The wait lock is held for the lifetime of the guard, so no other
+        // thread can access the data
+        unsafe { &*self.wait_lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for WaitLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The wait lock is held for the lifetime of the guard, and the guard
+        // is mutably borrowed, so this is the only reference to the data
+        unsafe { &mut *self.wait_lock.data.get() }
+    }
+}
+
+impl<T> Drop for WaitLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_wait_lock` is valid for the lifetime of the guard, and was
+        // acquired by this thread when the guard was created
+        unsafe {
+            call_unsafe_wdf_function_binding!(
+                WdfWaitLockRelease,
+                self.wait_lock.wdf_wait_lock.as_raw()
+            );
+        }
+    }
+}
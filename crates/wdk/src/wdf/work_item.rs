@@ -0,0 +1,300 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use core::ops::{Deref, DerefMut};
+
+use wdk_sys::{
+    call_unsafe_wdf_function_binding,
+    WDFWORKITEM,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_WORKITEM_CONFIG,
+};
+
+#[cfg(feature = "alloc")]
+use super::callback::ClosureCallback;
+use super::{
+    callback::stored_callback,
+    context::object_context,
+    wdf_struct_size,
+    ObjectContext,
+    Ownership,
+    WdfObject,
+};
+use crate::{debug_assert_passive_level, nt_success, NtResult};
+
+/// Callback of a [`WorkItem`].
+///
+/// The implementing type is stored in the context space of the work item, so
+/// it can hold any per-work item state. The state of the device or queue that
+/// the work item belongs to can be reached via [`WorkItem::parent_context`].
+/// Work items whose callback only needs captured state can instead be created
+/// with [`WorkItemBuilder::create_with_closure`].
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use core::sync::atomic::{AtomicBool, Ordering};
+///
+/// use wdk::wdf::{ObjectContext, WorkItem, WorkItemCallback};
+///
+/// #[derive(ObjectContext)]
+/// struct DeviceContext {
+///     configured: AtomicBool,
+/// }
+///
+/// #[derive(ObjectContext)]
+/// struct Configure;
+///
+/// impl WorkItemCallback for Configure {
+///     fn run(&self, work_item: &WorkItem) {
+///         // SAFETY: WDF waits for a running work item callback to return before
+///         // deleting the work item, and deletes the work item before its parent
+///         let device_context = unsafe { work_item.parent_context::<DeviceContext>() };
+///         if let Some(device_context) = device_context {
+///             device_context.configured.store(true, Ordering::Release);
+///         }
+///     }
+/// }
+/// ```
+pub trait WorkItemCallback: ObjectContext + Sync {
+    /// `EvtWorkItem`: called at `PASSIVE_LEVEL` by a system worker thread
+    /// after the work item has been enqueued
+    fn run(&self, work_item: &WorkItem);
+}
+
+/// WDF Work Item.
+///
+/// Work items defer work to a system worker thread running at `PASSIVE_LEVEL`,
+/// typically from code running at `DISPATCH_LEVEL` (ex. a DPC). A work item
+/// that is already enqueued is not enqueued again, so its callback runs once
+/// for any number of [`WorkItem::enqueue`] calls made before it starts.
+///
+/// Dropping a [`WorkItem`] created by [`WorkItemBuilder::create`] flushes it,
+/// waiting for an enqueued callback to finish, so the callback can never run
+/// after the driver has released the state it uses. It must therefore be
+/// dropped at `PASSIVE_LEVEL`, and not from its own callback.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{
+///     println,
+///     wdf::{object_attributes, Device, WorkItem},
+///     NtResult,
+/// };
+///
+/// fn defer_logging(device: &Device) -> NtResult<WorkItem> {
+///     let mut attributes = object_attributes();
+///     device.set_as_parent_in(&mut attributes);
+///
+///     let work_item = WorkItem::builder().create_with_closure(&mut attributes, |_work_item| {
+///         println!("running at PASSIVE_LEVEL")
+///     })?;
+///     work_item.enqueue();
+///     Ok(work_item)
+/// }
+/// ```
+pub struct WorkItem {
+    wdf_work_item: WdfObject<WDFWORKITEM>,
+    flush_on_drop: bool,
+}
+
+/// Builder for a [`WorkItem`], wrapping the configuration in
+/// [`WDF_WORKITEM_CONFIG`].
+pub struct WorkItemBuilder {
+    automatic_serialization: bool,
+}
+
+impl WorkItem {
+    /// Returns a [`WorkItemBuilder`] to configure and create a work item
+    #[must_use]
+    pub const fn builder() -> WorkItemBuilder {
+        WorkItemBuilder {
+            automatic_serialization: true,
+        }
+    }
+
+    /// Wrap a work item handed to a WDF callback. The returned [`WorkItem`] is
+    /// not flushed when dropped, since it is dropped from the work item's own
+    /// callback.
+    ///
+    /// # Safety
+    ///
+    /// `wdf_work_item` must be a valid handle to a work item created by the
+    /// driver
+    unsafe fn from_raw(wdf_work_item: WDFWORKITEM) -> Self {
+        Self {
+            // SAFETY: The caller guarantees `wdf_work_item` is valid, and work items are
+            // always parented to a device or queue
+            wdf_work_item: unsafe { WdfObject::from_raw(wdf_work_item, Ownership::Parent) },
+            flush_on_drop: false,
+        }
+    }
+
+    /// Enqueue the work item, via `WdfWorkItemEnqueue`, so that its callback
+    /// is run by a system worker thread. This can be called at `IRQL` <=
+    /// `DISPATCH_LEVEL`.
+    pub fn enqueue(&self) {
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfWorkItemEnqueue, self.as_raw());
+        }
+    }
+
+    /// Wait for the work item's callback to finish if it is enqueued or
+    /// running, via `WdfWorkItemFlush`.
+    ///
+    /// This must be called at `PASSIVE_LEVEL`, which is asserted in debug
+    /// builds of kernel-mode drivers, and must not be called from the work
+    /// item's own callback.
+    pub fn flush(&self) {
+        debug_assert_passive_level();
+
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_wdf_function_binding!(WdfWorkItemFlush, self.as_raw());
+        }
+    }
+
+    /// Returns a reference to the `T` context of the device or queue that the
+    /// work item belongs to, or [`None`] if it has no `T` context space, or it
+    /// has not been initialized
+    ///
+    /// # Safety
+    ///
+    /// The parent object must not be deleted while the returned reference
+    /// exists. The [`WorkItem`] only holds a reference on the work item itself,
+    /// which does not keep its parent alive. This holds inside
+    /// [`WorkItemCallback::run`], since WDF deletes the work item (waiting for
+    /// the callback to return) before its parent.
+    #[must_use]
+    pub unsafe fn parent_context<T: ObjectContext + Sync>(&self) -> Option<&T> {
+        let parent;
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and `WdfObject` guarantees that it is always in a valid
+        // state.
+        unsafe {
+            parent = call_unsafe_wdf_function_binding!(WdfWorkItemGetParentObject, self.as_raw());
+        }
+
+        // SAFETY: The caller guarantees that the parent is not deleted while the
+        // returned reference exists
+        unsafe { object_context::<T>(parent) }
+    }
+}
+
+impl Deref for WorkItem {
+    type Target = WdfObject<WDFWORKITEM>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.wdf_work_item
+    }
+}
+
+impl DerefMut for WorkItem {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.wdf_work_item
+    }
+}
+
+impl Drop for WorkItem {
+    fn drop(&mut self) {
+        if self.flush_on_drop {
+            self.flush();
+        }
+    }
+}
+
+impl WorkItemBuilder {
+    /// Set whether the work item's callback is synchronized with the callbacks
+    /// of its parent device or queue, according to their synchronization
+    /// scope. This is enabled by default, as in `WDF_WORKITEM_CONFIG_INIT`.
+    #[must_use]
+    pub const fn automatic_serialization(mut self, automatic_serialization: bool) -> Self {
+        self.automatic_serialization = automatic_serialization;
+        self
+    }
+
+    /// Create the work item via `WdfWorkItemCreate`, storing `callback` in its
+    /// context space.
+    ///
+    /// `attributes` must have a `ParentObject`, which is the device or queue
+    /// that the work item belongs to, and must not have an
+    /// `EvtCleanupCallback` or context type set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a work item. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfWorkItemCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfworkitem/nf-wdfworkitem-wdfworkitemcreate#return-value)
+    pub fn create<T: WorkItemCallback>(
+        self,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        callback: T,
+    ) -> NtResult<WorkItem> {
+        let mut work_item_config = WDF_WORKITEM_CONFIG {
+            Size: wdf_struct_size::<WDF_WORKITEM_CONFIG>(),
+            EvtWorkItemFunc: Some(evt_work_item::<T>),
+            AutomaticSerialization: u8::from(self.automatic_serialization),
+        };
+        T::set_context_type(attributes);
+        let ownership = Ownership::from_attributes(attributes);
+        let mut wdf_work_item: WDFWORKITEM = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_wdf_function_binding!(
+                WdfWorkItemCreate,
+                &mut work_item_config,
+                attributes,
+                &mut wdf_work_item,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status.into());
+        }
+
+        let mut work_item = WorkItem {
+            // SAFETY: `wdf_work_item` was just successfully created by WDF, and its
+            // `ownership` is derived from the attributes it was created with.
+            wdf_work_item: unsafe { WdfObject::from_raw(wdf_work_item, ownership) },
+            flush_on_drop: true,
+        };
+        // `T` is always present since it was set in `attributes`.
+        // SAFETY: The callback cannot run until the work item is enqueued, which
+        // requires the `WorkItem` being returned.
+        if unsafe { work_item.init_context(callback) }.is_err() {
+            unreachable!("work item should have a context space for its callback");
+        }
+        Ok(work_item)
+    }
+
+    /// Create the work item via `WdfWorkItemCreate`, calling `closure` when it
+    /// runs. See [`WorkItemBuilder::create`] for the requirements on
+    /// `attributes`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a work item. The error variant will contain an [`NtStatus`](crate::NtStatus) of the failure. Full error documentation is available in the [WdfWorkItemCreate Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfworkitem/nf-wdfworkitem-wdfworkitemcreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create_with_closure(
+        self,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+        closure: impl Fn(&WorkItem) + Send + Sync + 'static,
+    ) -> NtResult<WorkItem> {
+        self.create(attributes, ClosureCallback::new(closure))
+    }
+}
+
+extern "C" fn evt_work_item<T: WorkItemCallback>(wdf_work_item: WDFWORKITEM) {
+    // SAFETY: WDF always passes a valid handle to the work item being run
+    let work_item = unsafe { WorkItem::from_raw(wdf_work_item) };
+    if let Some(callback) = stored_callback::<T, _>(&work_item) {
+        callback.run(&work_item);
+    }
+}
@@ -24,7 +24,6 @@
     DRIVER_OBJECT,
     NTSTATUS,
     PCUNICODE_STRING,
-    ULONG,
     UNICODE_STRING,
     WCHAR,
     WDFDEVICE,
@@ -60,26 +59,9 @@
         DbgPrint(string.as_ptr());
     }
 
-    let mut driver_config = {
-        let wdf_driver_config_size: ULONG;
-
-        // clippy::cast_possible_truncation cannot currently check compile-time constants: https://github.com/rust-lang/rust-clippy/issues/9613
-        #[allow(clippy::cast_possible_truncation)]
-        {
-            const WDF_DRIVER_CONFIG_SIZE: usize = core::mem::size_of::<WDF_DRIVER_CONFIG>();
-
-            // Manually assert there is not truncation since clippy doesn't work for
-            // compile-time constants
-            const { assert!(WDF_DRIVER_CONFIG_SIZE <= ULONG::MAX as usize) }
-
-            wdf_driver_config_size = WDF_DRIVER_CONFIG_SIZE as ULONG;
-        }
-
-        WDF_DRIVER_CONFIG {
-            Size: wdf_driver_config_size,
-            EvtDriverDeviceAdd: Some(evt_driver_device_add),
-            ..WDF_DRIVER_CONFIG::default()
-        }
+    let mut driver_config = WDF_DRIVER_CONFIG {
+        EvtDriverDeviceAdd: Some(evt_driver_device_add),
+        ..WDF_DRIVER_CONFIG::init()
     };
 
     let driver_attributes = WDF_NO_OBJECT_ATTRIBUTES;